@@ -1,7 +1,10 @@
 pub mod response {
     use serde::{Deserialize, Serialize};
     use sithra_kit::{
-        transport::datapack::DataPack,
+        transport::{
+            datapack::DataPack,
+            error::{DataError, ErrorCode},
+        },
         types::{message::Message, smallvec::SmallVec},
     };
     use ulid::Ulid;
@@ -27,9 +30,18 @@ pub mod response {
                 data,
             } = self;
             if retcode >= 400 {
-                return DataPack::builder().correlate(echo).bot_id(bot_id).build_with_error(
-                    format!("Call OneBot API Error, RETCODE: {retcode}, STATUS: {status}"),
-                );
+                let code = match retcode {
+                    1400 => ErrorCode::InvalidRequest,
+                    1401 | 1403 => ErrorCode::PermissionDenied,
+                    1404 => ErrorCode::NotSupported,
+                    _ => ErrorCode::Other,
+                };
+                let error = DataError::new(
+                    code,
+                    format_args!("Call OneBot API Error, RETCODE: {retcode}, STATUS: {status}"),
+                )
+                .with_details(serde_json::json!({ "retcode": retcode, "status": status }));
+                return DataPack::builder().correlate(echo).bot_id(bot_id).build_with_error(error);
             }
             let Some(data) = data else {
                 return DataPack::builder().correlate(echo).bot_id(bot_id).build_with_payload(());
@@ -39,6 +51,7 @@ pub mod response {
                     let payload: Message = Message {
                         id:      send_msg.message_id,
                         content: SmallVec::new(),
+                        role:    None,
                     };
                     DataPack::builder().correlate(echo).build_with_payload(payload)
                 }
@@ -2,29 +2,43 @@
 use serde_json::json;
 use sithra_kit::{
     server::{
-        extract::{correlation::Correlation, payload::Payload, state::State},
+        extract::{
+            attachments::Attachments, correlation::Correlation, payload::Payload, state::State,
+        },
+        request::Request,
         response::Response,
     },
-    transport::channel::Channel,
+    transport::{
+        channel::{Channel, ChannelType},
+        error::{DataError, ErrorCode},
+    },
     types::{
         channel::SetMute,
         message::{Segments, SendMessage},
     },
 };
 
-use crate::{AdapterState, api::request::ApiCall, message::OneBotSegment, util::send_req};
+use crate::{
+    AdapterState,
+    api::request::ApiCall,
+    message::OneBotSegment,
+    util::{resolve_attachment, send_req},
+};
 
 pub async fn send_message(
     Payload(payload): Payload<SendMessage>,
     State(state): State<AdapterState>,
     Correlation(id): Correlation,
     channel: Channel,
+    Attachments(attachments): Attachments,
 ) -> Option<Response> {
-    let segments = payload.content.into_iter().filter_map(|s| match OneBotSegment::try_from(s) {
-        Ok(segment) => match segment {
-            OneBotSegment(segment) => Some(segment),
-        },
-        Err(_err) => None,
+    let segments = payload.content.into_iter().filter_map(|s| {
+        match OneBotSegment::try_from(resolve_attachment(s, &attachments)) {
+            Ok(segment) => match segment {
+                OneBotSegment(segment) => Some(segment),
+            },
+            Err(_err) => None,
+        }
     });
     let segments: Segments<_> = if state.convert_file_base64 {
         let mut result = Segments::new();
@@ -35,7 +49,23 @@ pub async fn send_message(
     } else {
         segments.collect()
     };
-    let req = if let Some(group_id) = channel.parent_id {
+    // Replies to group members go to their group, a group addressed by
+    // `group:<id>` is its own target.
+    let group_id = match channel.ty {
+        ChannelType::Group => channel.parent_id.or_else(|| Some(channel.id.clone())),
+        _ => channel.parent_id,
+    };
+    let req = if let (Some(guild_id), Some(channel_id)) = (channel.guild_id, &group_id) {
+        ApiCall::new(
+            "send_guild_channel_msg",
+            json!({
+                "guild_id": guild_id,
+                "channel_id": channel_id,
+                "message": segments
+            }),
+            id,
+        )
+    } else if let Some(group_id) = group_id {
         ApiCall::new(
             "send_msg",
             json!({
@@ -70,11 +100,14 @@ pub async fn set_mute(
         ty: _,
         name: _,
         parent_id,
-        self_id: _,
+        ..
     } = channel;
     let Some(parent_id) = parent_id else {
         log::error!("Set Mute Failed to get parent_id");
-        let mut response = Response::error("Failed to get parent_id");
+        let mut response = Response::error(DataError::new(
+            ErrorCode::InvalidRequest,
+            "Failed to get parent_id",
+        ));
         response.correlate(id);
         return Some(response);
     };
@@ -90,3 +123,16 @@ pub async fn set_mute(
     );
     send_req(&state, id, &req, "set_mute")
 }
+
+/// Answers commands this adapter does not support, so the caller gets an
+/// error instead of waiting for a response that never comes.
+pub async fn unsupported(req: Request) -> Option<Response> {
+    if !req.path.starts_with("/command/") {
+        return None;
+    }
+    log::warn!("Unsupported command: {}", req.path);
+    Some(Response::error(DataError::new(
+        ErrorCode::NotSupported,
+        format_args!("Unsupported command: {}", req.path),
+    )))
+}
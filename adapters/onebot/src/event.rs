@@ -4,7 +4,7 @@ use sithra_kit::{
         channel::Channel,
         datapack::{DataPack, RequestDataPack},
     },
-    types::{message::Message, smallvec::SmallVec},
+    types::{message::Message, role::Role, smallvec::SmallVec},
 };
 
 use crate::{
@@ -35,6 +35,18 @@ impl RawEvent {
                     msg_event.user_id.clone(),
                     msg_event.message_type.call_name(),
                 )),
+                MessageEventKind::Guild {
+                    ref guild_id,
+                    ref channel_id,
+                    ..
+                } => Some(
+                    Channel::DirectFromGroup(
+                        channel_id.clone(),
+                        msg_event.user_id.clone(),
+                        msg_event.message_type.call_name(),
+                    )
+                    .set_guild_id(guild_id),
+                ),
             },
             _ => None,
         }
@@ -66,6 +78,7 @@ impl RawEvent {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case", tag = "post_type")]
+#[allow(clippy::large_enum_variant)]
 pub enum PostType {
     Message(MessageEvent),
     Notice,
@@ -89,6 +102,7 @@ pub struct MessageEvent {
 impl From<MessageEvent> for Message {
     fn from(value: MessageEvent) -> Self {
         Self {
+            role:    value.message_type.role(),
             id:      value.message_id,
             content: value
                 .message
@@ -110,13 +124,32 @@ pub enum MessageEventKind {
         group_id: String,
         sender:   GroupSender,
     },
+    Guild {
+        #[serde(deserialize_with = "de_str_from_num")]
+        guild_id:   String,
+        #[serde(deserialize_with = "de_str_from_num")]
+        channel_id: String,
+        sender:     PrivateSender,
+    },
 }
 
 impl MessageEventKind {
+    /// The role of the sender in the group, `None` outside of groups.
+    #[must_use]
+    pub fn role(&self) -> Option<Role> {
+        match self {
+            Self::Group { sender, .. } => match sender.role.as_deref()? {
+                "owner" | "admin" => Some(Role::Moderator),
+                _ => Some(Role::Member),
+            },
+            Self::Private { .. } | Self::Guild { .. } => None,
+        }
+    }
+
     #[must_use]
     pub fn call_name(&self) -> String {
         match self {
-            Self::Private { sender } => sender.nickname.clone(),
+            Self::Private { sender } | Self::Guild { sender, .. } => sender.nickname.clone(),
             Self::Group { sender, .. } => {
                 if let Some(card) = &sender.card {
                     if card.is_empty() {
@@ -141,4 +174,6 @@ pub struct PrivateSender {
 pub struct GroupSender {
     nickname: String,
     card:     Option<String>,
+    /// `owner`, `admin` or `member`.
+    role:     Option<String>,
 }
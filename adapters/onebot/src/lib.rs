@@ -38,5 +38,12 @@ mod tests {
         let r = r#"{"message_type":"group","sub_type":"normal","message_id":1684373655,"group_id":905311025,"user_id":191697786,"anonymous":null,"message":[{"type":"mface","data":{"url":"https://gxh.vip.qq.com/club/item/parcel/item/e9/e99c237b82636920cc5de7f29c08daca/raw300.gif","emoji_package_id":241144,"emoji_id":"e99c237b82636920cc5de7f29c08daca","key":"e779bd64f58c0c04","summary":"[\u75AF\u72C2\u6444\u5165]"}},{"type":"text","data":{"text":"[\u75AF\u72C2\u6444\u5165]"}}],"raw_message":"[CQ:mface]\u0026#91;\u75AF\u72C2\u6444\u5165\u0026#93;","font":0,"sender":{"user_id":191697786,"nickname":"Mo9uier","card":"","sex":"unknown","age":0,"area":"","level":"31","role":"member","title":""},"message_style":{"bubble_id":2086380,"pendant_id":0,"font_id":0,"font_effect_id":0,"is_cs_font_effect_enabled":false,"bubble_diy_text_id":0},"time":1752067348,"self_id":1921576220,"post_type":"message"}"#;
 
         let _e: RawEvent = serde_json::from_str(r).unwrap();
+
+        let r = r#"{"message_type":"guild","sub_type":"channel","message_id":"BAC3HLRYvXdYAAAAAAAAAAAAAAAAAAAA","guild_id":49857441636955271,"channel_id":1596682,"user_id":144115218678093368,"message":[{"type":"text","data":{"text":"hi"}}],"sender":{"user_id":144115218678093368,"nickname":"Lee","tiny_id":"144115218678093368"},"self_tiny_id":"144115218676630200","time":1752067348,"self_id":1921576220,"post_type":"message"}"#;
+        let e: RawEvent = serde_json::from_str(r).unwrap();
+        assert_eq!(
+            e.channel().unwrap().to_string(),
+            "guild:49857441636955271/group:1596682/direct:144115218678093368@1921576220"
+        );
     }
 }
@@ -9,7 +9,7 @@ use sithra_adapter_onebot::{
         request::ApiCall,
         response::{ApiResponse, ApiResponseKind},
     },
-    endpoint::{send_message, set_mute},
+    endpoint::{send_message, set_mute, unsupported},
     util::{ConnectionManager, is_loopback},
 };
 use sithra_kit::{
@@ -82,6 +82,7 @@ async fn main() {
     let plugin = plugin.map(|r| {
         r.route_typed(SendMessage::on(send_message))
             .route_typed(SetMute::on(set_mute))
+            .fallback(unsupported)
             .layer(BotId::new(plugin_id.clone()))
             .with_state(state)
     });
@@ -3,7 +3,14 @@ use std::{net::IpAddr, time::Duration};
 use base64::{Engine, prelude::BASE64_STANDARD};
 use hyper::header::HeaderValue;
 use serde::{Deserialize, Deserializer, Serialize};
-use sithra_kit::server::response::Response;
+use sithra_kit::{
+    server::response::Response,
+    transport::{
+        Bytes,
+        error::{DataError, ErrorCode},
+    },
+    types::message::Segment,
+};
 use thiserror::Error;
 use tokio::{fs, io::AsyncReadExt, sync::mpsc};
 use tokio_tungstenite::{
@@ -18,8 +25,19 @@ pub(crate) fn de_str_from_num<'de, D>(deserializer: D) -> Result<String, D::Erro
 where
     D: Deserializer<'de>,
 {
-    let num: i64 = Deserialize::deserialize(deserializer)?;
-    Ok(num.to_string())
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNum {
+        Signed(i64),
+        Unsigned(u64),
+        Str(String),
+    }
+    // Guild events carry unsigned 64-bit ids and string message ids.
+    Ok(match Deserialize::deserialize(deserializer)? {
+        StrOrNum::Signed(num) => num.to_string(),
+        StrOrNum::Unsigned(num) => num.to_string(),
+        StrOrNum::Str(s) => s,
+    })
 }
 
 pub fn send_req<T: Serialize>(
@@ -42,7 +60,10 @@ pub fn send_req<T: Serialize>(
     let result = state.ws_tx.send(WsMessage::Text(req.into()));
     if let Err(ws_err) = result {
         log::error!("Failed to send {err} request: {ws_err}");
-        let mut response = Response::error(format!("Failed to send {err} request: {ws_err}"));
+        let mut response = Response::error(DataError::new(
+            ErrorCode::PeerOffline,
+            format_args!("Failed to send {err} request: {ws_err}"),
+        ));
         response.correlate(id);
         return Some(response);
     }
@@ -227,6 +248,20 @@ pub async fn or_in_base64(url_str: &str) -> Result<String, ProcessError> {
     }
 }
 
+/// Inlines the attachment referenced by `segment`, if any, as a `base64://`
+/// file string. Segments referencing a missing attachment are left as-is.
+#[must_use]
+pub fn resolve_attachment(segment: Segment, attachments: &[Bytes]) -> Segment {
+    let Some(data) = segment.attachment_index().and_then(|i| attachments.get(i)) else {
+        return segment;
+    };
+    let base64 = BASE64_STANDARD.encode(data);
+    Segment {
+        ty:   segment.ty,
+        data: format!("base64://{base64}").into(),
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ProcessError {
     #[error("File IO error: {0}")]
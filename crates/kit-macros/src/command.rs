@@ -0,0 +1,318 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{
+    Attribute, Data, DeriveInput, Fields, GenericArgument, Ident, LitChar, LitInt, LitStr, Meta,
+    PathArguments, Result, Token, Type,
+    meta::ParseNestedMeta,
+    parse::{ParseStream, Parser},
+};
+
+#[derive(Default)]
+pub struct CommandAttrs {
+    name:    Option<LitStr>,
+    aliases: Vec<LitStr>,
+    prefix:  Option<LitStr>,
+}
+
+impl CommandAttrs {
+    fn parse(input: &DeriveInput) -> Result<Self> {
+        let mut attrs = Self::default();
+        for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("command")) {
+            attr.parse_nested_meta(|meta| attrs.parse_meta(&meta))?;
+        }
+        Ok(attrs)
+    }
+
+    /// Parses `#[command("name", alias = "...", prefix = "...",
+    /// priority = 10)]` on a handler, where the name can also be given as
+    /// `name = "..."`. Returns the priority separately.
+    pub fn parse_handler(attr: &Attribute) -> Result<(Self, Option<i32>)> {
+        let mut attrs = Self::default();
+        let mut priority = None;
+        if matches!(attr.meta, Meta::Path(_)) {
+            return Ok((attrs, priority));
+        }
+        attr.parse_args_with(|input: ParseStream| {
+            if input.peek(LitStr) {
+                attrs.name = Some(input.parse()?);
+                if input.is_empty() {
+                    return Ok(());
+                }
+                input.parse::<Token![,]>()?;
+            }
+            syn::meta::parser(|meta| {
+                if meta.path.is_ident("priority") {
+                    priority = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    Ok(())
+                } else {
+                    attrs.parse_meta(&meta)
+                }
+            })
+            .parse2(input.parse()?)
+        })?;
+        Ok((attrs, priority))
+    }
+
+    fn parse_meta(&mut self, meta: &ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("alias") {
+            self.aliases.push(meta.value()?.parse()?);
+        } else if meta.path.is_ident("prefix") {
+            self.prefix = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `name`, `alias` or `prefix`"));
+        }
+        Ok(())
+    }
+
+    /// The `CommandSpec` of the command, named `default_name` unless the
+    /// attribute names it.
+    pub fn spec(&self, default_name: &str, args: impl Iterator<Item = TokenStream>) -> TokenStream {
+        let name = self.name.as_ref().map_or_else(|| default_name.to_owned(), LitStr::value);
+        let aliases = &self.aliases;
+        let prefix = option(self.prefix.as_ref());
+        quote! {
+            static SPEC: ::sithra_kit::command::CommandSpec = ::sithra_kit::command::CommandSpec {
+                name:    #name,
+                aliases: &[#(#aliases),*],
+                prefix:  #prefix,
+                args:    &[#(#args),*],
+            };
+            &SPEC
+        }
+    }
+}
+
+enum Kind {
+    Positional,
+    Optional,
+    Rest,
+    Flag {
+        long:  String,
+        short: Option<LitChar>,
+    },
+    Option {
+        long:     String,
+        short:    Option<LitChar>,
+        required: bool,
+    },
+}
+
+struct Arg {
+    ident: Ident,
+    name:  String,
+    /// The type of a single value.
+    ty:    Type,
+    kind:  Kind,
+}
+
+/// The `T` of `wrapper<T>`, if `ty` is spelled that way.
+fn inner_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) if args.args.len() == 1 => Some(ty),
+        _ => None,
+    }
+}
+
+/// `value` as an `Option` expression.
+fn option<T: ToTokens>(value: Option<&T>) -> TokenStream {
+    value.map_or_else(
+        || quote!(::core::option::Option::None),
+        |value| quote!(::core::option::Option::Some(#value)),
+    )
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+}
+
+impl Arg {
+    fn parse(field: &syn::Field) -> Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let name = ident.to_string().trim_start_matches("r#").to_owned();
+        let mut long = None;
+        let mut short = None;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("arg")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("long") {
+                    long = Some(if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse::<LitStr>()?.value()
+                    } else {
+                        name.replace('_', "-")
+                    });
+                } else if meta.path.is_ident("short") {
+                    short = Some(meta.value()?.parse::<LitChar>()?);
+                } else {
+                    return Err(meta.error("expected `long` or `short`"));
+                }
+                Ok(())
+            })?;
+        }
+        let named = long.is_some() || short.is_some();
+        let long = long.unwrap_or_else(|| name.replace('_', "-"));
+        let (ty, kind) = if is_bool(&field.ty) {
+            (field.ty.clone(), Kind::Flag { long, short })
+        } else if let Some(ty) = inner_type(&field.ty, "Option") {
+            let kind = if named {
+                Kind::Option {
+                    long,
+                    short,
+                    required: false,
+                }
+            } else {
+                Kind::Optional
+            };
+            (ty.clone(), kind)
+        } else if named {
+            (
+                field.ty.clone(),
+                Kind::Option {
+                    long,
+                    short,
+                    required: true,
+                },
+            )
+        } else if let Some(ty) = inner_type(&field.ty, "Vec") {
+            (ty.clone(), Kind::Rest)
+        } else {
+            (field.ty.clone(), Kind::Positional)
+        };
+        Ok(Self {
+            ident,
+            name,
+            ty,
+            kind,
+        })
+    }
+
+    fn spec(&self) -> TokenStream {
+        let name = &self.name;
+        let kind = match &self.kind {
+            Kind::Positional => quote!(Required),
+            Kind::Optional => quote!(Optional),
+            Kind::Rest => quote!(Rest),
+            Kind::Flag { long, short: s } => {
+                let s = option(s.as_ref());
+                quote!(Flag { long: #long, short: #s })
+            }
+            Kind::Option {
+                long,
+                short: s,
+                required,
+            } => {
+                let s = option(s.as_ref());
+                quote!(Option { long: #long, short: #s, required: #required })
+            }
+        };
+        quote! {
+            ::sithra_kit::command::ArgSpec {
+                name: #name,
+                kind: ::sithra_kit::command::ArgKind::#kind,
+            }
+        }
+    }
+
+    fn take(&self) -> TokenStream {
+        let Self {
+            ident,
+            name,
+            ty,
+            kind,
+        } = self;
+        let value = match kind {
+            Kind::Positional => quote!(args.required::<#ty>(#name)?),
+            Kind::Optional => quote!(args.optional::<#ty>(#name)?),
+            Kind::Rest => quote!(args.rest::<#ty>(#name)?),
+            Kind::Flag { long, short } => {
+                let short = option(short.as_ref());
+                quote!(args.flag(#long, #short))
+            }
+            Kind::Option {
+                long,
+                short,
+                required,
+            } => {
+                let short = option(short.as_ref());
+                let value = quote!(args.option::<#ty>(#name, #long, #short)?);
+                if *required {
+                    quote! {
+                        #value.ok_or(::sithra_kit::command::CommandError::MissingValue(#name))?
+                    }
+                } else {
+                    value
+                }
+            }
+        };
+        quote!(let #ident = #value;)
+    }
+
+    const fn is_named(&self) -> bool {
+        matches!(self.kind, Kind::Flag { .. } | Kind::Option { .. })
+    }
+}
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let attrs = CommandAttrs::parse(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "`Command` can only be derived for structs",
+        ));
+    };
+    let args = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().map(Arg::parse).collect::<Result<Vec<_>>>()?,
+        Fields::Unit => Vec::new(),
+        Fields::Unnamed(_) => {
+            return Err(syn::Error::new_spanned(
+                &data.fields,
+                "`Command` needs named fields or a unit struct",
+            ));
+        }
+    };
+
+    let ident = &input.ident;
+    let spec = attrs.spec(
+        &ident.to_string().to_lowercase(),
+        args.iter().map(Arg::spec),
+    );
+    // Named arguments can appear anywhere, so they are taken first.
+    let takes = args
+        .iter()
+        .filter(|arg| arg.is_named())
+        .chain(args.iter().filter(|arg| !arg.is_named()))
+        .map(Arg::take);
+    let construct = if matches!(data.fields, Fields::Unit) {
+        quote!(Self)
+    } else {
+        let idents = args.iter().map(|arg| &arg.ident);
+        quote!(Self { #(#idents),* })
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::sithra_kit::command::CommandArgs for #ident #ty_generics #where_clause {
+            fn spec() -> &'static ::sithra_kit::command::CommandSpec {
+                #spec
+            }
+
+            #[allow(unused_variables)]
+            fn from_args(
+                args: &mut ::sithra_kit::command::Args,
+            ) -> ::core::result::Result<Self, ::sithra_kit::command::CommandError> {
+                #(#takes)*
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
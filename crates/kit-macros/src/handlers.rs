@@ -0,0 +1,228 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    FnArg, Ident, Item, ItemFn, ItemMod, LitInt, Result, Token, Type,
+    parse::{Parse, ParseStream},
+};
+
+use crate::command::CommandAttrs;
+
+/// Arguments of `#[handlers]`.
+pub struct HandlersArgs {
+    state: Option<Type>,
+}
+
+impl Parse for HandlersArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { state: None });
+        }
+        let key = input.parse::<Ident>()?;
+        if key != "state" {
+            return Err(syn::Error::new_spanned(key, "expected `state`"));
+        }
+        input.parse::<Token![=]>()?;
+        let state = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self { state: Some(state) })
+    }
+}
+
+/// `#[on(Type)]` or `#[on(Type, priority = 10)]`.
+struct On {
+    ty:       Type,
+    priority: Option<i32>,
+}
+
+impl Parse for On {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ty = input.parse()?;
+        let mut priority = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            if key != "priority" {
+                return Err(syn::Error::new_spanned(key, "expected `priority`"));
+            }
+            input.parse::<Token![=]>()?;
+            priority = Some(input.parse::<LitInt>()?.base10_parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self { ty, priority })
+    }
+}
+
+/// A handler of a route type, registered with `Routes::on`.
+fn register(ty: &TokenStream, priority: Option<i32>, handler: &Ident) -> TokenStream {
+    let priority = priority.map_or_else(
+        || quote!(::core::option::Option::None),
+        |priority| quote!(::core::option::Option::Some(#priority)),
+    );
+    quote!(.on(<#ty>::path(), #priority, <#ty>::__on(#handler)))
+}
+
+/// A handler that is only called for messages invoking the command, taking
+/// the same extractors as `func`.
+fn command_handler(func: &ItemFn, attrs: &CommandAttrs) -> Result<(TokenStream, Ident)> {
+    let sig = &func.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "command handlers must be `async`",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "command handlers cannot be generic",
+        ));
+    }
+    let ident = &sig.ident;
+    let command = format_ident!("__{}_command", ident);
+    let handler = format_ident!("__{}_command_handler", ident);
+    let mut names = Vec::new();
+    let mut params = Vec::new();
+    for (i, input) in sig.inputs.iter().enumerate() {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "command handlers cannot take `self`",
+            ));
+        };
+        let name = format_ident!("__arg{}", i);
+        let ty = &input.ty;
+        params.push(quote!(#name: #ty));
+        names.push(name);
+    }
+    let output = &sig.output;
+    let spec = attrs.spec(
+        &ident.to_string(),
+        std::iter::once(quote! {
+            ::sithra_kit::command::ArgSpec {
+                name: "args",
+                kind: ::sithra_kit::command::ArgKind::Rest,
+            }
+        }),
+    );
+    let items = quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        struct #command;
+
+        impl ::sithra_kit::command::CommandArgs for #command {
+            fn spec() -> &'static ::sithra_kit::command::CommandSpec {
+                #spec
+            }
+
+            fn from_args(
+                args: &mut ::sithra_kit::command::Args,
+            ) -> ::core::result::Result<Self, ::sithra_kit::command::CommandError> {
+                args.rest::<::sithra_kit::command::Token>("args")?;
+                ::core::result::Result::Ok(Self)
+            }
+        }
+
+        #[doc(hidden)]
+        async fn #handler(
+            _: ::sithra_kit::command::Command<#command>,
+            #(#params),*
+        ) #output {
+            #ident(#(#names),*).await
+        }
+    };
+    Ok((items, handler))
+}
+
+pub fn expand(args: &HandlersArgs, mut module: ItemMod) -> Result<TokenStream> {
+    let Some((_, items)) = &mut module.content else {
+        return Err(syn::Error::new_spanned(
+            &module,
+            "`#[handlers]` needs an inline module",
+        ));
+    };
+    let mut handlers = Vec::new();
+    let mut generated = Vec::new();
+    for item in items.iter_mut() {
+        let Item::Fn(func) = item else {
+            continue;
+        };
+        let mut routes = Vec::new();
+        let mut commands = Vec::new();
+        let mut error = None;
+        func.attrs.retain(|attr| {
+            let result = if attr.path().is_ident("on") {
+                attr.parse_args::<On>().map(|on| routes.push(on))
+            } else if attr.path().is_ident("command") {
+                CommandAttrs::parse_handler(attr).map(|attrs| commands.push(attrs))
+            } else {
+                return true;
+            };
+            if let Err(err) = result {
+                error.get_or_insert(err);
+            }
+            false
+        });
+        if let Some(err) = error {
+            return Err(err);
+        }
+        if commands.len() > 1 {
+            return Err(syn::Error::new_spanned(
+                &func.sig.ident,
+                "a handler can only be one command, add aliases instead",
+            ));
+        }
+        for On { ty, priority } in routes {
+            handlers.push(register(&ty.to_token_stream(), priority, &func.sig.ident));
+        }
+        for (attrs, priority) in commands {
+            let (items, handler) = command_handler(func, &attrs)?;
+            generated.push(items);
+            handlers.push(register(
+                &quote!(::sithra_kit::types::message::Message),
+                priority,
+                &handler,
+            ));
+        }
+    }
+
+    let router_ty = quote!(::sithra_kit::server::routing::router::Router);
+    let collected_ty = quote!(::sithra_kit::routes::Routes);
+    let (collect_fn, register_fn) = if let Some(state) = &args.state {
+        (
+            quote!(routes() -> #collected_ty<#state>),
+            quote!(router(router: #router_ty<#state>) -> #router_ty<#state>),
+        )
+    } else {
+        let prefixes = (!generated.is_empty()).then(|| {
+            quote! {
+                ::sithra_kit::command::CommandPrefixes: ::sithra_kit::server::traits::FromRef<S>,
+            }
+        });
+        let bounds = quote! {
+            where
+                S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static,
+                #prefixes
+        };
+        (
+            quote!(routes<S>() -> #collected_ty<S> #bounds),
+            quote!(router<S>(router: #router_ty<S>) -> #router_ty<S> #bounds),
+        )
+    };
+    items.extend(
+        generated
+            .into_iter()
+            .chain([quote! {
+                /// The handlers of this module, to merge with those of other
+                /// modules handling the same route types.
+                pub fn #collect_fn {
+                    #collected_ty::new() #(#handlers)*
+                }
+
+                /// Registers the handlers of this module.
+                pub fn #register_fn {
+                    routes().register(router)
+                }
+            }])
+            .map(Item::Verbatim),
+    );
+    Ok(module.into_token_stream())
+}
@@ -1,70 +1,64 @@
-// use proc_macro::TokenStream;
-// use quote::{format_ident, quote};
-// use syn::{
-//     ItemFn, Result, Type,
-//     parse::{Parse, ParseStream},
-//     parse_macro_input,
-// };
+use proc_macro::TokenStream;
+use syn::{DeriveInput, ItemMod, parse_macro_input};
 
-// struct AttributeArgs {
-//     ty: Type,
-// }
+mod command;
+mod handlers;
 
-// impl Parse for AttributeArgs {
-//     fn parse(input: ParseStream) -> Result<Self> {
-//         let ty = input.parse::<Type>()?;
-//         Ok(AttributeArgs { ty })
-//     }
-// }
+/// Derives `sithra_kit::command::CommandArgs`.
+///
+/// The struct is configured with `#[command(name = "...", alias = "...",
+/// prefix = "...")]`, all optional. The name defaults to the lowercased
+/// struct name, and `alias` can be repeated. Fields are taken as arguments:
+/// `bool` fields are flags, `#[arg(long)]`, `#[arg(long = "...")]` and
+/// `#[arg(short = 'x')]` fields are named options, `Option<T>` fields are
+/// optional, a `Vec<T>` field takes the rest and any other field is a
+/// required positional argument.
+#[proc_macro_derive(Command, attributes(command, arg))]
+pub fn derive_command(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    command::derive(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
 
-// /// Attribute macro that registers a handler function with a typed route.
-// ///
-// /// # Example
-// /// ```
-// /// #[on(Message)]
-// /// async fn message_handler(
-// ///     Payload(str): Payload<String>,
-// ///     State(()): State<()>,
-// /// ) {
-// ///     // handler implementation
-// /// }
-// /// ```
-// ///
-// /// This macro generates a constant that contains the route path for the
-// /// handler.
-// #[proc_macro_attribute]
-// pub fn on(args: TokenStream, input: TokenStream) -> TokenStream {
-//     let args = parse_macro_input!(args as AttributeArgs);
-//     let input = parse_macro_input!(input as ItemFn);
-
-//     let ty = &args.ty;
-
-//     let vis = input.vis.clone();
-//     let fn_name = &input.sig.ident;
-//     let const_name = format_ident!("{fn_name}__path__");
-
-//     let output = quote! {
-//         #input
-
-//         #[doc(hidden)]
-//         #vis const #const_name: &'static str = #ty::_check(&#fn_name);
-//     };
-
-//     output.into()
-// }
-
-// #[proc_macro]
-// pub fn path_(input: TokenStream) -> TokenStream {
-//     let input_str = input.to_string();
-//     let output_str = format!("{input_str}__path__");
-
-//     match output_str.parse() {
-//         Ok(tokens) => tokens,
-//         Err(e) => {
-//             let error = format!("Failed to parse generated string
-// `{output_str}`: {e}");             TokenStream::from(
-//                 syn::Error::new(proc_macro2::Span::call_site(),
-// error).to_compile_error(),             )
-//         }
-//     }
-// }
+/// Collects the handlers of an inline module into a generated
+/// `pub fn router(router: Router<S>) -> Router<S>` in that module, and a
+/// `pub fn routes() -> Routes<S>` returning them unregistered.
+///
+/// Handlers are marked with `#[on(Type)]` to be routed to the typed route of
+/// `Type`, or with `#[command("name", alias = "...", prefix = "...")]` to be
+/// called for messages invoking the command, see
+/// `sithra_kit::command::CommandArgs`. A handler can have several `#[on]`
+/// routes but only one command.
+///
+/// Both take an optional `priority = 10`. If a handler of a route has one,
+/// the handlers of that route run as a `sithra_kit::server::chain`, highest
+/// priority first and `0` by default, instead of all at once.
+/// Handlers of routes with the same path are registered together, see
+/// `sithra_kit::routes::Routes`. A path can only be routed once, so modules
+/// handling the same route type have to merge their `routes()` instead of
+/// each calling `router`.
+///
+/// The router is generic over its state unless it is given with
+/// `#[handlers(state = AppState)]`, which handlers taking `State` need.
+/// Commands without a prefix take theirs from the `CommandPrefixes` of the
+/// state.
+///
+/// ```ignore
+/// #[handlers]
+/// mod handlers {
+///     #[on(Message)]
+///     async fn echo(Payload(msg): Payload<Message<CommonSegment>>) -> SendMessage { .. }
+///
+///     #[command("roll", alias = "r")]
+///     async fn roll(Payload(msg): Payload<Message<CommonSegment>>) -> SendMessage { .. }
+/// }
+///
+/// let plugin = plugin.map(handlers::router);
+/// ```
+#[proc_macro_attribute]
+pub fn handlers(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as handlers::HandlersArgs);
+    let input = parse_macro_input!(input as ItemMod);
+    handlers::expand(&args, input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,758 @@
+//! Declarative chat commands.
+//!
+//! A command is a message that starts with a prefix and a name, e.g.
+//! `/roll 2d6 --hidden`, followed by its arguments. Arguments are the words of
+//! the text segments, split on whitespace unless quoted, and the mentions in
+//! the message. Derive [`CommandArgs`] with `#[derive(Command)]` and take the
+//! arguments with the [`Command`](struct@Command) extractor:
+//!
+//! ```
+//! use sithra_kit::{
+//!     command::{Command, Mention},
+//!     types::message::SendMessage,
+//! };
+//!
+//! #[derive(Command)]
+//! #[command(name = "mute", alias = "m")]
+//! struct Mute {
+//!     user:    Mention,
+//!     seconds: u64,
+//!     /// Flags are `bool` fields, given as `--quiet`.
+//!     quiet:   bool,
+//! }
+//!
+//! async fn mute(Command(args): Command<Mute>) -> SendMessage {
+//!     format!("muting {} for {}s", args.user.0, args.seconds).into()
+//! }
+//! ```
+//!
+//! Positional fields are required, `Option<T>` fields are optional and a
+//! `Vec<T>` field takes the remaining arguments. Fields marked
+//! `#[arg(long)]`, `#[arg(long = "name")]` or `#[arg(short = 'n')]` are named
+//! options taking a value. Messages that are not the command are ignored by
+//! the extractor. Commands with invalid arguments are answered with the error
+//! and the usage of the command.
+//!
+//! Commands without a `prefix` of their own use the [`CommandPrefixes`] in the
+//! state of the router, e.g. from the plugin config. Routers without state
+//! recognize them without a prefix.
+//!
+//! Handlers that only need to be called for a command, whatever its
+//! arguments, can be marked with `#[command("name", alias = "...")]` in a
+//! [`handlers`](crate::handlers) module instead.
+
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Write as _},
+    str::FromStr,
+    sync::Arc,
+};
+
+pub use sithra_kit_macros::Command;
+use sithra_server::{
+    extract::FromRequest,
+    request::Request,
+    response::{IntoResponse, Response},
+    traits::FromRef,
+};
+use sithra_types::message::{Message, SendMessage, common::CommonSegment};
+use thiserror::Error;
+
+/// The prefixes of commands that do not declare their own, kept in the state
+/// of the router. The first one is shown in usages.
+///
+/// The default is the empty prefix, which is also used by routers without
+/// state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandPrefixes(Arc<[String]>);
+
+impl CommandPrefixes {
+    pub fn new<I>(prefixes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self(prefixes.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Default for CommandPrefixes {
+    fn default() -> Self {
+        Self(Arc::from([String::new()]))
+    }
+}
+
+impl FromRef<()> for CommandPrefixes {
+    fn from_ref((): &()) -> Self {
+        Self::default()
+    }
+}
+
+/// A single argument of a command.
+#[derive(Debug, Clone)]
+pub enum Token {
+    /// Text, `quoted` if any of it was written in quotes, so that it is never
+    /// read as a flag.
+    Word { text: String, quoted: bool },
+    /// The target of an `at` segment.
+    Mention(String),
+    /// Any other segment, e.g. an image.
+    Segment(CommonSegment),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word { text, .. } => f.write_str(text),
+            Self::Mention(target) => write!(f, "@{target}"),
+            Self::Segment(_) => f.write_str("<segment>"),
+        }
+    }
+}
+
+/// Splits a message into tokens.
+///
+/// Text is split on whitespace, and `"double quotes"` keep words together.
+/// A quote can span several text segments but never a mention.
+#[must_use]
+pub fn tokenize(content: &[CommonSegment]) -> Vec<Token> {
+    fn take_word(word: &mut Option<String>, word_quoted: &mut bool) -> Option<Token> {
+        let quoted = std::mem::take(word_quoted);
+        word.take().map(|text| Token::Word { text, quoted })
+    }
+
+    let mut tokens = Vec::new();
+    let mut word: Option<String> = None;
+    let mut word_quoted = false;
+    let mut quoted = false;
+    for segment in content {
+        let text = match segment {
+            CommonSegment::Text(text) => text,
+            CommonSegment::At(target) => {
+                tokens.extend(take_word(&mut word, &mut word_quoted));
+                quoted = false;
+                tokens.push(Token::Mention(target.clone()));
+                continue;
+            }
+            segment => {
+                tokens.extend(take_word(&mut word, &mut word_quoted));
+                quoted = false;
+                tokens.push(Token::Segment(segment.clone()));
+                continue;
+            }
+        };
+        for c in text.chars() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    word_quoted = true;
+                    word.get_or_insert_default();
+                }
+                c if c.is_whitespace() && !quoted => {
+                    tokens.extend(take_word(&mut word, &mut word_quoted));
+                }
+                c => word.get_or_insert_default().push(c),
+            }
+        }
+    }
+    tokens.extend(take_word(&mut word, &mut word_quoted));
+    tokens
+}
+
+/// Why a message could not be parsed as a command.
+#[derive(Debug, Error)]
+pub enum CommandError {
+    /// The message is not this command.
+    #[error("Not this command")]
+    NotMatched,
+    #[error("Missing argument <{0}>")]
+    Missing(&'static str),
+    #[error("Missing value for --{0}")]
+    MissingValue(&'static str),
+    #[error("Invalid argument <{name}>: {reason}")]
+    Invalid {
+        name:   &'static str,
+        reason: String,
+    },
+    #[error("Unexpected argument `{0}`")]
+    Unexpected(String),
+}
+
+/// A type that can be parsed from a single [`Token`].
+pub trait FromArg: Sized {
+    /// # Errors
+    /// Returns the reason why `token` is not a valid `Self`.
+    fn from_arg(token: Token) -> Result<Self, String>;
+}
+
+macro_rules! from_str_arg {
+    ($($ty:ty),*) => {
+        $(
+            impl FromArg for $ty {
+                fn from_arg(token: Token) -> Result<Self, String> {
+                    match token {
+                        Token::Word { text, .. } => {
+                            <$ty>::from_str(&text).map_err(|err| err.to_string())
+                        }
+                        token => Err(format!("expected text, got `{token}`")),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_str_arg!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+/// A mentioned user, given as an `at` segment or as `@<id>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mention(pub String);
+
+impl FromArg for Mention {
+    fn from_arg(token: Token) -> Result<Self, String> {
+        match token {
+            Token::Mention(target) => Ok(Self(target)),
+            Token::Word { text, .. } => match text.strip_prefix('@') {
+                Some(target) if !target.is_empty() => Ok(Self(target.to_owned())),
+                _ => Err(format!("expected a mention, got `{text}`")),
+            },
+            token @ Token::Segment(_) => Err(format!("expected a mention, got `{token}`")),
+        }
+    }
+}
+
+impl FromArg for Token {
+    fn from_arg(token: Token) -> Result<Self, String> {
+        Ok(token)
+    }
+}
+
+impl FromArg for CommonSegment {
+    fn from_arg(token: Token) -> Result<Self, String> {
+        Ok(match token {
+            Token::Word { text, .. } => Self::Text(text),
+            Token::Mention(target) => Self::At(target),
+            Token::Segment(segment) => segment,
+        })
+    }
+}
+
+/// The arguments of a command, consumed by [`CommandArgs::from_args`].
+///
+/// Named flags and options can appear anywhere and have to be taken before
+/// the positional arguments.
+#[derive(Debug, Clone)]
+pub struct Args {
+    tokens: VecDeque<Token>,
+}
+
+impl Args {
+    #[must_use]
+    pub fn new(tokens: impl IntoIterator<Item = Token>) -> Self {
+        Self {
+            tokens: tokens.into_iter().collect(),
+        }
+    }
+
+    /// Finds the flag `--<long>` or `-<short>`. Quoted words are values, not
+    /// flags.
+    fn position(&self, long: &str, short: Option<char>) -> Option<usize> {
+        self.tokens.iter().position(|token| match token {
+            Token::Word {
+                text: word,
+                quoted: false,
+            } => {
+                word.strip_prefix("--").is_some_and(|name| name == long)
+                    || short.is_some_and(|short| {
+                        word.strip_prefix('-').is_some_and(|name| name.chars().eq([short]))
+                    })
+            }
+            _ => false,
+        })
+    }
+
+    /// Takes the flag `--<long>` or `-<short>`, returning whether it was
+    /// given.
+    pub fn flag(&mut self, long: &str, short: Option<char>) -> bool {
+        let Some(index) = self.position(long, short) else {
+            return false;
+        };
+        self.tokens.remove(index);
+        true
+    }
+
+    /// Takes the option `--<long> <value>` or `-<short> <value>`.
+    ///
+    /// # Errors
+    /// Returns an error if the option has no value or the value is invalid.
+    pub fn option<T: FromArg>(
+        &mut self,
+        name: &'static str,
+        long: &str,
+        short: Option<char>,
+    ) -> Result<Option<T>, CommandError> {
+        let Some(index) = self.position(long, short) else {
+            return Ok(None);
+        };
+        self.tokens.remove(index);
+        let value = self.tokens.remove(index).ok_or(CommandError::MissingValue(name))?;
+        parse(name, value).map(Some)
+    }
+
+    /// Takes the next positional argument.
+    ///
+    /// # Errors
+    /// Returns an error if there is none or it is invalid.
+    pub fn required<T: FromArg>(&mut self, name: &'static str) -> Result<T, CommandError> {
+        self.optional(name)?.ok_or(CommandError::Missing(name))
+    }
+
+    /// Takes the next positional argument if there is one.
+    ///
+    /// # Errors
+    /// Returns an error if the argument is invalid.
+    pub fn optional<T: FromArg>(&mut self, name: &'static str) -> Result<Option<T>, CommandError> {
+        self.tokens.pop_front().map(|token| parse(name, token)).transpose()
+    }
+
+    /// Takes all remaining positional arguments.
+    ///
+    /// # Errors
+    /// Returns an error if any argument is invalid.
+    pub fn rest<T: FromArg>(&mut self, name: &'static str) -> Result<Vec<T>, CommandError> {
+        self.tokens.drain(..).map(|token| parse(name, token)).collect()
+    }
+
+    /// Checks that every argument was taken.
+    ///
+    /// # Errors
+    /// Returns [`CommandError::Unexpected`] with the first argument left.
+    pub fn finish(mut self) -> Result<(), CommandError> {
+        match self.tokens.pop_front() {
+            Some(token) => Err(CommandError::Unexpected(token.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse<T: FromArg>(name: &'static str, token: Token) -> Result<T, CommandError> {
+    T::from_arg(token).map_err(|reason| CommandError::Invalid { name, reason })
+}
+
+/// How an argument is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Required,
+    Optional,
+    /// All remaining positional arguments.
+    Rest,
+    Flag {
+        long:  &'static str,
+        short: Option<char>,
+    },
+    Option {
+        long:     &'static str,
+        short:    Option<char>,
+        required: bool,
+    },
+}
+
+/// An argument of a command, as shown in its usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+}
+
+/// The name, aliases, prefix and arguments of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name:    &'static str,
+    pub aliases: &'static [&'static str],
+    /// The prefix of the command, or `None` to use the [`CommandPrefixes`] of
+    /// the router.
+    pub prefix:  Option<&'static str>,
+    pub args:    &'static [ArgSpec],
+}
+
+impl CommandSpec {
+    /// The prefixes the command is recognized with, `defaults` unless it
+    /// declares its own.
+    #[must_use]
+    pub fn prefixes<'a>(&self, defaults: &'a CommandPrefixes) -> Vec<&'a str> {
+        match self.prefix {
+            Some(prefix) => vec![prefix],
+            None => defaults.0.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// Whether `word` is the command with one of its prefixes.
+    #[must_use]
+    pub fn matches(&self, word: &str, defaults: &CommandPrefixes) -> bool {
+        self.prefixes(defaults).iter().any(|prefix| {
+            word.strip_prefix(prefix)
+                .is_some_and(|name| name == self.name || self.aliases.contains(&name))
+        })
+    }
+
+    /// A line like `/roll <dice> [times] [--hidden]`.
+    #[must_use]
+    pub fn usage(&self, defaults: &CommandPrefixes) -> String {
+        let prefix = self.prefixes(defaults).first().copied().unwrap_or_default();
+        let mut usage = format!("{prefix}{}", self.name);
+        for ArgSpec { name, kind } in self.args {
+            let option = |short: &Option<char>, long| match short {
+                Some(short) => format!("-{short}|--{long}"),
+                None => format!("--{long}"),
+            };
+            match kind {
+                ArgKind::Required => write!(usage, " <{name}>"),
+                ArgKind::Optional => write!(usage, " [{name}]"),
+                ArgKind::Rest => write!(usage, " [{name}...]"),
+                ArgKind::Flag { long, short } => write!(usage, " [{}]", option(short, long)),
+                ArgKind::Option {
+                    long,
+                    short,
+                    required: true,
+                } => write!(usage, " {} <{name}>", option(short, long)),
+                ArgKind::Option { long, short, .. } => {
+                    write!(usage, " [{} <{name}>]", option(short, long))
+                }
+            }
+            .ok();
+        }
+        usage
+    }
+}
+
+/// The arguments of a command, usually derived with `#[derive(Command)]`.
+pub trait CommandArgs: Sized {
+    fn spec() -> &'static CommandSpec;
+
+    /// Takes the arguments of the command out of `args`.
+    ///
+    /// # Errors
+    /// Returns an error if an argument is missing or invalid.
+    fn from_args(args: &mut Args) -> Result<Self, CommandError>;
+
+    /// Parses `content` as this command, with `defaults` as the prefixes if
+    /// it has none of its own.
+    ///
+    /// # Errors
+    /// Returns [`CommandError::NotMatched`] if `content` is not this command,
+    /// or why its arguments are invalid.
+    fn parse(content: &[CommonSegment], defaults: &CommandPrefixes) -> Result<Self, CommandError> {
+        let mut tokens = tokenize(content).into_iter();
+        match tokens.next() {
+            Some(Token::Word { text, .. }) if Self::spec().matches(&text, defaults) => {}
+            _ => return Err(CommandError::NotMatched),
+        }
+        let mut args = Args::new(tokens);
+        let command = Self::from_args(&mut args)?;
+        args.finish()?;
+        Ok(command)
+    }
+}
+
+/// Extracts the arguments of the command `T` from a message.
+///
+/// Handlers taking this are skipped for messages that are not the command,
+/// and commands with invalid arguments are answered with their usage.
+///
+/// Needs [`CommandPrefixes`] in the state of the router.
+#[derive(Debug, Clone)]
+pub struct Command<T>(pub T);
+
+impl<T> Command<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Rejection of the [`Command`](struct@Command) extractor.
+#[derive(Debug)]
+pub struct CommandRejection {
+    pub error: CommandError,
+    pub usage: String,
+}
+
+impl IntoResponse for CommandRejection {
+    fn into_response(self) -> Response {
+        match self.error {
+            CommandError::NotMatched => Response::none(),
+            error => SendMessage::from(format!("{error}\nUsage: {}", self.usage)).into_response(),
+        }
+    }
+}
+
+impl<T, S> FromRequest<S> for Command<T>
+where
+    T: CommandArgs,
+    CommandPrefixes: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = CommandRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let prefixes = CommandPrefixes::from_ref(state);
+        let rejection = |error| CommandRejection {
+            error,
+            usage: T::spec().usage(&prefixes),
+        };
+        let message: Message<CommonSegment> =
+            req.payload.decode().map_err(|_| rejection(CommandError::NotMatched))?;
+        T::parse(&message.content, &prefixes).map(Self).map_err(rejection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Command)]
+    #[command(name = "roll", alias = "r", prefix = "/")]
+    struct Roll {
+        dice:   String,
+        times:  Option<u32>,
+        hidden: bool,
+        #[arg(long, short = 's')]
+        seed:   Option<u64>,
+    }
+
+    #[derive(Debug, Command)]
+    #[command(prefix = "!")]
+    struct Mute {
+        user:   Mention,
+        reason: Vec<String>,
+    }
+
+    #[crate::handlers]
+    #[allow(clippy::unused_async)]
+    mod handlers {
+        use sithra_server::extract::payload::Payload;
+        use sithra_types::message::{Message, SendMessage, common::CommonSegment};
+
+        #[on(Message)]
+        async fn log(Payload(_msg): Payload<Message<CommonSegment>>) {}
+
+        #[command("roll", alias = "r", prefix = "/")]
+        async fn roll() -> SendMessage {
+            "rolled".into()
+        }
+    }
+
+    #[crate::handlers]
+    #[allow(clippy::unused_async)]
+    mod chained {
+        use sithra_server::response::Consume;
+        use sithra_types::message::{Message, SendMessage};
+
+        #[on(Message)]
+        async fn fallback() -> SendMessage {
+            "unknown".into()
+        }
+
+        #[command("ping", prefix = "/", priority = 10)]
+        async fn ping() -> Consume<SendMessage> {
+            Consume("pong".into())
+        }
+    }
+
+    #[crate::handlers]
+    #[allow(clippy::unused_async)]
+    mod extra {
+        use sithra_types::message::SendMessage;
+
+        #[on(sithra_types::message::Message)]
+        async fn greet() -> SendMessage {
+            "hi".into()
+        }
+    }
+
+    #[derive(Debug, Command)]
+    #[command(name = "ping")]
+    struct Ping {
+        target: Option<String>,
+    }
+
+    fn text(text: &str) -> Vec<CommonSegment> {
+        vec![CommonSegment::text(text)]
+    }
+
+    fn defaults() -> CommandPrefixes {
+        CommandPrefixes::default()
+    }
+
+    #[test]
+    fn parse_typed_args() {
+        let roll = Roll::parse(&text("/r 2d6 -s 7 3 --hidden"), &defaults()).unwrap();
+        assert_eq!(roll.dice, "2d6");
+        assert_eq!(roll.times, Some(3));
+        assert!(roll.hidden);
+        assert_eq!(roll.seed, Some(7));
+
+        let roll = Roll::parse(&text(r#"/roll "1d20 + 2""#), &defaults()).unwrap();
+        assert_eq!(roll.dice, "1d20 + 2");
+        assert_eq!(roll.times, None);
+        assert!(!roll.hidden);
+
+        // Quoted words are values even if they look like flags.
+        let roll = Roll::parse(&text(r#"/roll "--hidden""#), &defaults()).unwrap();
+        assert_eq!(roll.dice, "--hidden");
+        assert!(!roll.hidden);
+        let roll = Roll::parse(&text(r#"/roll "-s" 7"#), &defaults()).unwrap();
+        assert_eq!(
+            (roll.dice.as_str(), roll.times, roll.seed),
+            ("-s", Some(7), None)
+        );
+        assert!(matches!(
+            Roll::parse(&text(r#"/roll 2d6 "-s" 7"#), &defaults()),
+            Err(CommandError::Invalid { name: "times", .. })
+        ));
+
+        assert!(matches!(
+            Roll::parse(&text("roll 2d6"), &defaults()),
+            Err(CommandError::NotMatched)
+        ));
+        assert!(matches!(
+            Roll::parse(&text("/rolling"), &defaults()),
+            Err(CommandError::NotMatched)
+        ));
+        assert!(matches!(
+            Roll::parse(&text("/roll"), &defaults()),
+            Err(CommandError::Missing("dice"))
+        ));
+        assert!(matches!(
+            Roll::parse(&text("/roll 2d6 x"), &defaults()),
+            Err(CommandError::Invalid { name: "times", .. })
+        ));
+        assert!(matches!(
+            Roll::parse(&text("/roll 2d6 1 2"), &defaults()),
+            Err(CommandError::Unexpected(_))
+        ));
+        assert_eq!(
+            Roll::spec().usage(&defaults()),
+            "/roll <dice> [times] [--hidden] [-s|--seed <seed>]"
+        );
+    }
+
+    #[tokio::test]
+    async fn handlers_route_commands() {
+        use sithra_server::{routing::router::Router, transport::datapack::RequestDataPack};
+        use sithra_types::message::Message;
+        use tower::Service;
+
+        let mut router: Router = handlers::router(Router::new());
+        let mut call = async |content: &str| {
+            let message = Message {
+                id:      String::new(),
+                content: text(content).into(),
+                role:    None,
+            };
+            let request = RequestDataPack::default().path(Message::path()).payload(message);
+            router.call(Request::new(request)).await.unwrap().data
+        };
+        let replies = call("/r 2d6").await;
+        assert_eq!(replies.len(), 1);
+        let reply: SendMessage = replies[0].payload().unwrap();
+        assert_eq!(reply.content[0].data, "rolled");
+        assert!(call("roll 2d6").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn handlers_chain_by_priority() {
+        use sithra_server::{routing::router::Router, transport::datapack::RequestDataPack};
+        use sithra_types::message::Message;
+        use tower::Service;
+
+        let mut router: Router = chained::router(Router::new());
+        let mut call = async |content: &str| {
+            let message = Message {
+                id:      String::new(),
+                content: text(content).into(),
+                role:    None,
+            };
+            let request = RequestDataPack::default().path(Message::path()).payload(message);
+            let replies = router.call(Request::new(request)).await.unwrap().data;
+            replies
+                .iter()
+                .map(|reply| reply.payload::<SendMessage>().unwrap().content[0].data.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(call("/ping").await, ["pong"]);
+        assert_eq!(call("hello").await, ["unknown"]);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::unused_async)]
+    async fn prefixes_from_state() {
+        use sithra_server::{on, routing::router::Router, transport::datapack::RequestDataPack};
+        use tower::Service;
+
+        let prefixes = CommandPrefixes::new(["!", "/"]);
+        assert_eq!(Ping::spec().usage(&prefixes), "!ping [target]");
+        let router: Router<CommandPrefixes> = Router::new().route(
+            Message::path(),
+            on(async |Command(ping): Command<Ping>| {
+                SendMessage::from(ping.target.unwrap_or_default())
+            }),
+        );
+        let mut router: Router = router.with_state(prefixes);
+        let mut call = async |content: &str| {
+            let message = Message {
+                id:      String::new(),
+                content: text(content).into(),
+                role:    None,
+            };
+            let request = RequestDataPack::default().path(Message::path()).payload(message);
+            router.call(Request::new(request)).await.unwrap().data
+        };
+        assert_eq!(call("!ping a").await.len(), 1);
+        assert_eq!(call("/ping").await.len(), 1);
+        assert!(call("ping").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn merged_modules_share_routes() {
+        use sithra_server::{routing::router::Router, transport::datapack::RequestDataPack};
+        use tower::Service;
+
+        let mut router: Router = handlers::routes().merge(extra::routes()).register(Router::new());
+        let message = Message {
+            id:      String::new(),
+            content: text("/roll").into(),
+            role:    None,
+        };
+        let request = RequestDataPack::default().path(Message::path()).payload(message);
+        let mut replies = router
+            .call(Request::new(request))
+            .await
+            .unwrap()
+            .data
+            .iter()
+            .filter_map(|reply| reply.payload::<SendMessage>().ok())
+            .map(|reply| reply.content[0].data.clone())
+            .collect::<Vec<_>>();
+        replies.sort_by_key(ToString::to_string);
+        assert_eq!(replies, ["hi", "rolled"]);
+    }
+
+    #[test]
+    fn mentions_as_args() {
+        let content = vec![
+            CommonSegment::text("!mute "),
+            CommonSegment::at("123"),
+            CommonSegment::text(" too loud"),
+        ];
+        let mute = Mute::parse(&content, &defaults()).unwrap();
+        assert_eq!(mute.user, Mention("123".to_owned()));
+        assert_eq!(mute.reason, ["too", "loud"]);
+        assert_eq!(
+            Mute::parse(&text("!mute @456"), &defaults()).unwrap().user.0,
+            "456"
+        );
+        assert_eq!(Mute::spec().usage(&defaults()), "!mute <user> [reason...]");
+    }
+}
@@ -1,17 +1,28 @@
 use std::{
+    any::Any,
+    collections::HashMap,
     convert::Infallible,
     fmt::Display,
+    panic::AssertUnwindSafe,
     pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
-use futures_util::ready;
+use futures_util::{
+    FutureExt,
+    future::{self, BoxFuture, Either},
+    ready,
+};
 use pin_project::pin_project;
 use sithra_server::{
     request::Request,
-    response::Response,
+    response::{IntoResponse, Response},
     routing::route::{Route, RouteFuture},
+    transport::error::{DataError, ErrorCode},
 };
+use sithra_types::message::{Message, SendMessage};
 use tower::{Layer, Service};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -96,3 +107,459 @@ impl Future for BotIdLayerFuture {
         }
     }
 }
+
+/// What requests share a [`RateLimit`] bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitBy {
+    /// The user sending the request, the `id` of its channel.
+    User,
+    /// The channel the request was sent in, the parent of a user's channel.
+    Channel,
+    /// The bot the request came through.
+    Bot,
+}
+
+impl RateLimitBy {
+    /// The bucket of `req`, `None` if it cannot be told apart.
+    fn key(self, req: &Request) -> Option<BucketKey> {
+        let channel = match self {
+            Self::Bot => None,
+            Self::User => Some(req.channel.as_ref()?.id.clone()),
+            Self::Channel => {
+                let channel = req.channel.as_ref()?;
+                Some(channel.parent_id.clone().unwrap_or_else(|| channel.id.clone()))
+            }
+        };
+        Some((req.bot_id(), channel))
+    }
+}
+
+/// The bot and channel a bucket belongs to.
+type BucketKey = (Option<String>, Option<String>);
+
+/// How many buckets are kept. Full buckets are forgotten first, then the
+/// least recently used ones.
+const RATE_LIMIT_BUCKETS: usize = 1024;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens:  f64,
+    updated: Instant,
+    /// Whether the slow down reply was sent since the bucket ran empty.
+    warned:  bool,
+}
+
+/// A token bucket rate limit, for a route with [`Endpoint::layer`] or for
+/// all routes of a router with [`Router::route_layer`].
+///
+/// Every answered request takes a token from its bucket, and buckets refill
+/// one token per `refill`, up to `capacity`. Requests the handler answers with
+/// nothing, like chat lines that are not a command, get their token back.
+/// Messages finding their bucket empty are dropped, and the first of them is
+/// answered with the [`reply`](Self::reply) if there is one. Other requests
+/// finding it empty are answered with an [`ErrorCode::RateLimited`] error.
+///
+/// Clones share their buckets, create a `RateLimit` per route for separate
+/// limits.
+///
+/// ```ignore
+/// router.route_layer(RateLimit::new(RateLimitBy::User, 3, Duration::from_secs(10)).reply("慢一点喵"))
+/// ```
+///
+/// [`Endpoint::layer`]: sithra_server::routing::endpoint::Endpoint::layer
+/// [`Router::route_layer`]: sithra_server::routing::router::Router::route_layer
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    by:       RateLimitBy,
+    capacity: u32,
+    refill:   Duration,
+    reply:    Option<SendMessage>,
+    buckets:  Arc<Mutex<HashMap<BucketKey, Bucket>>>,
+}
+
+impl RateLimit {
+    #[must_use]
+    pub fn new(by: RateLimitBy, capacity: u32, refill: Duration) -> Self {
+        Self {
+            by,
+            capacity,
+            refill,
+            reply: None,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Answers the first message over the limit with `reply`.
+    #[must_use]
+    pub fn reply(mut self, reply: impl Into<SendMessage>) -> Self {
+        self.reply = Some(reply.into());
+        self
+    }
+
+    /// Takes a token for `req`, returning the bucket to
+    /// [`refund`](Self::refund) it to. Returns `Err` with whether to send the
+    /// reply if the bucket is empty.
+    fn acquire(&self, req: &Request) -> Result<Option<BucketKey>, bool> {
+        let Some(key) = self.by.key(req) else {
+            return Ok(None);
+        };
+        let now = Instant::now();
+        let capacity = f64::from(self.capacity);
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= RATE_LIMIT_BUCKETS && !buckets.contains_key(&key) {
+            let refill = self.refill.as_secs_f64();
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() / refill < capacity
+            });
+            if buckets.len() >= RATE_LIMIT_BUCKETS {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens:  capacity,
+            updated: now,
+            warned:  false,
+        });
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() / self.refill.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refilled).min(capacity);
+        bucket.updated = now;
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.warned = false;
+            Ok(Some(key))
+        } else {
+            Err(!std::mem::replace(&mut bucket.warned, true))
+        };
+        drop(buckets);
+        result
+    }
+
+    /// Gives back the token taken for a request that went unanswered.
+    fn refund(&self, key: &BucketKey) {
+        let capacity = f64::from(self.capacity);
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(bucket) = buckets.get_mut(key) {
+            bucket.tokens = (bucket.tokens + 1.0).min(capacity);
+        }
+    }
+}
+
+impl Layer<Route> for RateLimit {
+    type Service = RateLimitLayer;
+
+    fn layer(&self, inner: Route) -> Self::Service {
+        RateLimitLayer {
+            limit: self.clone(),
+            svc:   inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limit: RateLimit,
+    svc:   Route,
+}
+
+impl Service<Request> for RateLimitLayer {
+    type Error = Infallible;
+    type Future = Either<future::Ready<Result<Response, Infallible>>, RateLimitFuture>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.svc.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        match self.limit.acquire(&req) {
+            Ok(key) => Either::Right(RateLimitFuture {
+                future: self.svc.call(req),
+                refund: key.map(|key| (self.limit.clone(), key)),
+            }),
+            Err(_) if req.path != Message::path() => {
+                Either::Left(future::ready(Ok(Response::error(DataError::new(
+                    ErrorCode::RateLimited,
+                    format_args!("Too many requests to {}, try again later", req.path),
+                )))))
+            }
+            Err(warn) => {
+                let reply = self.limit.reply.clone().filter(|_| warn);
+                let response = reply.map_or_else(Response::none, IntoResponse::into_response);
+                Either::Left(future::ready(Ok(response)))
+            }
+        }
+    }
+}
+
+/// Runs the handler of a [`RateLimitLayer`], refunding its token if it
+/// answers with nothing.
+#[pin_project]
+pub struct RateLimitFuture {
+    #[pin]
+    future: RouteFuture,
+    refund: Option<(RateLimit, BucketKey)>,
+}
+
+impl Future for RateLimitFuture {
+    type Output = Result<Response, Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let Ok(response) = ready!(this.future.poll(cx));
+        if response.data.is_empty()
+            && let Some((limit, key)) = this.refund.take()
+        {
+            limit.refund(&key);
+        }
+        Poll::Ready(Ok(response))
+    }
+}
+
+/// Isolates handlers: answers with an error, and logs it, if a handler
+/// panics or takes longer than the [`timeout`](Self::timeout), instead of
+/// dropping the request silently or holding on to it forever.
+///
+/// The errors name the plugin `id` and the path of the request. Timed out
+/// handlers are dropped, which only stops them at their next `.await`.
+#[derive(Debug, Clone)]
+pub struct Guard {
+    id:      Arc<str>,
+    timeout: Option<Duration>,
+}
+
+impl Guard {
+    #[must_use]
+    pub fn new(id: impl Display) -> Self {
+        Self {
+            id:      id.to_string().into(),
+            timeout: None,
+        }
+    }
+
+    /// Gives up on handlers after `timeout`.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Layer<Route> for Guard {
+    type Service = GuardLayer;
+
+    fn layer(&self, inner: Route) -> Self::Service {
+        GuardLayer {
+            guard: self.clone(),
+            svc:   inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GuardLayer {
+    guard: Guard,
+    svc:   Route,
+}
+
+/// The message of a panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+impl Service<Request> for GuardLayer {
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.svc.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let Guard { id, timeout } = self.guard.clone();
+        let path = req.path.clone();
+        let handler = AssertUnwindSafe(self.svc.call(req)).catch_unwind();
+        Box::pin(async move {
+            let result = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handler).await.map_err(|_| timeout),
+                None => Ok(handler.await),
+            };
+            let error = match result {
+                Ok(Ok(response)) => return response,
+                Ok(Err(payload)) => DataError::new(
+                    ErrorCode::Internal,
+                    format_args!(
+                        "[{id}] handler for {path} panicked: {}",
+                        panic_message(&*payload)
+                    ),
+                ),
+                Err(timeout) => DataError::new(
+                    ErrorCode::Timeout,
+                    format_args!("[{id}] handler for {path} timed out after {timeout:?}"),
+                ),
+            };
+            log::error!("{error}");
+            Ok(Response::error(error))
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unused_async)]
+mod tests {
+    use sithra_server::{
+        on,
+        routing::router::Router,
+        transport::{channel::Channel, datapack::RequestDataPack},
+    };
+    use sithra_types::message::SendMessage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn rate_limit_by_user() {
+        let limit = RateLimit::new(RateLimitBy::User, 2, Duration::from_millis(50)).reply("slow");
+        let router: Router = Router::new()
+            .route(Message::path(), on(async || SendMessage::from("ok")))
+            .route_layer(limit);
+        let call = async |user: &str| {
+            let request = RequestDataPack::default()
+                .path(Message::path())
+                .channel(Channel::Private(user.to_owned(), user.to_owned()));
+            let response = router.clone().call(Request::new(request)).await.unwrap();
+            response
+                .data
+                .iter()
+                .map(|data| data.payload::<SendMessage>().unwrap().content[0].data.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(call("alice").await, ["ok"]);
+        assert_eq!(call("alice").await, ["ok"]);
+        assert_eq!(call("alice").await, ["slow"]);
+        assert!(call("alice").await.is_empty());
+        assert_eq!(call("bob").await, ["ok"]);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(call("alice").await, ["ok"]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_skips_unanswered() {
+        use sithra_server::extract::payload::Payload;
+
+        let limit = RateLimit::new(RateLimitBy::User, 1, Duration::from_hours(1)).reply("slow");
+        let router: Router = Router::new()
+            .route(
+                Message::path(),
+                on(async |Payload(text): Payload<String>| {
+                    (text == "/ping").then(|| SendMessage::from("pong"))
+                }),
+            )
+            .route_layer(limit);
+        let call = async |text: &str| {
+            let request = RequestDataPack::default()
+                .path(Message::path())
+                .channel(Channel::Private("alice".to_owned(), "alice".to_owned()))
+                .payload(text);
+            let response = router.clone().call(Request::new(request)).await.unwrap();
+            response
+                .data
+                .iter()
+                .map(|data| data.payload::<SendMessage>().unwrap().content[0].data.clone())
+                .collect::<Vec<_>>()
+        };
+        for _ in 0..3 {
+            assert!(call("hello").await.is_empty());
+        }
+        assert_eq!(call("/ping").await, ["pong"]);
+        assert_eq!(call("/ping").await, ["slow"]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_errors_for_requests() {
+        use sithra_server::extract::payload::Payload;
+
+        let limit = RateLimit::new(RateLimitBy::User, 1, Duration::from_hours(1)).reply("slow");
+        let mut router: Router =
+            Router::new().route("/status", on(async || Payload("ok"))).route_layer(limit);
+        let request = || {
+            Request::new(
+                RequestDataPack::default()
+                    .path("/status")
+                    .channel(Channel::Private("alice".to_owned(), "alice".to_owned())),
+            )
+        };
+        let response = router.call(request()).await.unwrap();
+        assert_eq!(response.data[0].payload::<String>().unwrap(), "ok");
+        for _ in 0..2 {
+            let response = router.call(request()).await.unwrap();
+            assert_eq!(
+                response.data[0].payload::<String>().unwrap_err().code,
+                ErrorCode::RateLimited
+            );
+        }
+    }
+
+    #[test]
+    fn rate_limit_buckets_stay_bounded() {
+        let limit = RateLimit::new(RateLimitBy::User, 1, Duration::from_hours(1));
+        let request = |user: &str| {
+            Request::new(
+                RequestDataPack::default()
+                    .path(Message::path())
+                    .channel(Channel::Private(user.to_owned(), user.to_owned())),
+            )
+        };
+        for user in 0..RATE_LIMIT_BUCKETS {
+            assert!(limit.acquire(&request(&user.to_string())).is_ok());
+        }
+        assert_eq!(limit.acquire(&request("0")), Err(true));
+        assert!(limit.acquire(&request("new")).is_ok());
+        assert_eq!(limit.buckets.lock().unwrap().len(), RATE_LIMIT_BUCKETS);
+        // The least recently used bucket is the one forgotten.
+        assert!(limit.acquire(&request("1")).is_ok());
+        assert_eq!(limit.acquire(&request("0")), Err(false));
+    }
+
+    #[tokio::test]
+    async fn guard_catches_panics_and_timeouts() {
+        use sithra_server::{extract::payload::Payload, transport::datapack::DataResult};
+
+        let router: Router = Router::new()
+            .route("/ok", on(async || Payload("ok")))
+            .route("/panic", on(async || -> Payload<()> { panic!("boom") }))
+            .route(
+                "/hang",
+                on(async || tokio::time::sleep(Duration::from_secs(10)).await),
+            )
+            .layer(Guard::new("test").timeout(Duration::from_millis(20)));
+        let call = async |path: &str| {
+            let request = RequestDataPack::default().path(path);
+            let response = router.clone().call(Request::new(request)).await.unwrap();
+            response.data.into_iter().next().map(|data| data.result)
+        };
+        assert!(matches!(call("/ok").await, Some(DataResult::Payload(_))));
+        let Some(DataResult::Error(error)) = call("/panic").await else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.code, ErrorCode::Internal);
+        assert_eq!(error.message, "[test] handler for /panic panicked: boom");
+        let Some(DataResult::Error(error)) = call("/hang").await else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.code, ErrorCode::Timeout);
+        assert_eq!(
+            error.message,
+            "[test] handler for /hang timed out after 20ms"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(test)]
+extern crate self as sithra_kit;
+
 pub use sithra_server as server;
 pub use sithra_server::transport;
 pub use sithra_types as types;
@@ -14,5 +17,17 @@ pub mod initialize;
 #[cfg(feature = "plugin")]
 pub mod plugin;
 
+#[cfg(feature = "command")]
+pub mod command;
+
+#[cfg(feature = "permission")]
+pub mod permission;
+
+#[cfg(feature = "macros")]
+pub use sithra_kit_macros::handlers;
+
+#[cfg(feature = "macros")]
+pub mod routes;
+
 #[doc(hidden)]
 pub mod __private {}
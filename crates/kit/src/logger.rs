@@ -1,6 +1,6 @@
 use log::Log;
 use once_cell::sync::OnceCell;
-use sithra_server::{server::ClientSink, transport::datapack::RequestDataPack};
+use sithra_server::{server::ClientSink, trace, transport::datapack::RequestDataPack};
 use sithra_types::log::Log as LogRequest;
 
 pub static LOGGER: OnceCell<ClientLogger> = OnceCell::new();
@@ -14,7 +14,9 @@ impl Log for ClientLogger {
 
     fn log(&self, record: &log::Record) {
         let log_request = LogRequest::from(record);
-        self.0.send(RequestDataPack::from(log_request)).ok();
+        let mut datapack = RequestDataPack::from(log_request);
+        datapack.trace = trace::current();
+        self.0.send(datapack).ok();
     }
 
     fn flush(&self) {}
@@ -0,0 +1,281 @@
+//! Roles of the users sending requests, see [`Role`].
+//!
+//! Roles come from the [`Roles`] the host gives a plugin in
+//! [`Initialize`](sithra_types::initialize::Initialize), and from the
+//! [`Message::role`](sithra_types::message::Message::role) reported by the
+//! adapter, which is [`Role::Moderator`] at most. Keep them as [`Permissions`]
+//! in the state of the router, then restrict routes with [`RequireRole`] or
+//! check the [`Permission`] of a request in a handler.
+//!
+//! ```ignore
+//! let (plugin, Initialize { roles, .. }) = plugin!();
+//! let permissions = Permissions::new(roles);
+//! router.route("/mc/reload", on(reload).layer(RequireRole::new(Role::Admin, permissions.clone())))
+//! ```
+
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use futures_util::future::{self, Either};
+use serde::Deserialize;
+use sithra_server::{
+    extract::FromRequest,
+    request::Request,
+    response::{IntoResponse, Response},
+    routing::route::{Route, RouteFuture},
+    sync::Arc,
+    traits::FromRef,
+    transport::error::{DataError, ErrorCode},
+};
+use sithra_types::{
+    message::{Message, SendMessage},
+    role::{Role, Roles},
+};
+use tower::{Layer, Service};
+
+/// The [`Roles`] of a plugin, cheap to clone.
+#[derive(Clone, Debug, Default)]
+pub struct Permissions(Arc<Roles>);
+
+/// The part of a [`Message`] reporting the role of the sender.
+#[derive(Deserialize)]
+struct Reported {
+    #[serde(default)]
+    role: Option<Role>,
+}
+
+impl Permissions {
+    #[must_use]
+    pub fn new(roles: Roles) -> Self {
+        Self(Arc::new(roles))
+    }
+
+    /// The role of the user sending `req`, [`Role::Member`] for requests
+    /// without a channel. Only [`Message`]s are trusted to report a role.
+    #[must_use]
+    pub fn role(&self, req: &Request) -> Role {
+        let Some(channel) = &req.channel else {
+            return Role::Member;
+        };
+        let reported = if req.path == Message::path() {
+            req.payload::<Reported>().ok().and_then(|reported| reported.role)
+        } else {
+            None
+        };
+        self.0.role_of(channel, reported)
+    }
+}
+
+impl From<Roles> for Permissions {
+    fn from(roles: Roles) -> Self {
+        Self::new(roles)
+    }
+}
+
+/// The role of the user sending the request.
+///
+/// Needs [`Permissions`] in the state of the router.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permission(pub Role);
+
+impl Permission {
+    /// Whether the user holds `role` or a higher one.
+    #[must_use]
+    pub fn has(self, role: Role) -> bool {
+        self.0 >= role
+    }
+}
+
+impl<S> FromRequest<S> for Permission
+where
+    Permissions: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(Permissions::from_ref(state).role(&req)))
+    }
+}
+
+/// Drops messages from users below `role`, and answers them with the
+/// [`reply`](Self::reply) if there is one. Other requests are answered with an
+/// [`ErrorCode::PermissionDenied`] error.
+#[derive(Clone, Debug)]
+pub struct RequireRole {
+    role:        Role,
+    permissions: Permissions,
+    reply:       Option<SendMessage>,
+}
+
+impl RequireRole {
+    #[must_use]
+    pub const fn new(role: Role, permissions: Permissions) -> Self {
+        Self {
+            role,
+            permissions,
+            reply: None,
+        }
+    }
+
+    /// Answers messages from users below the role with `reply`.
+    #[must_use]
+    pub fn reply(mut self, reply: impl Into<SendMessage>) -> Self {
+        self.reply = Some(reply.into());
+        self
+    }
+}
+
+impl Layer<Route> for RequireRole {
+    type Service = RequireRoleLayer;
+
+    fn layer(&self, inner: Route) -> Self::Service {
+        RequireRoleLayer {
+            require: self.clone(),
+            svc:     inner,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RequireRoleLayer {
+    require: RequireRole,
+    svc:     Route,
+}
+
+impl Service<Request> for RequireRoleLayer {
+    type Error = Infallible;
+    type Future = Either<future::Ready<Result<Response, Infallible>>, RouteFuture>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.svc.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if self.require.permissions.role(&req) >= self.require.role {
+            return Either::Right(self.svc.call(req));
+        }
+        let response = if req.path == Message::path() {
+            self.require
+                .reply
+                .clone()
+                .map_or_else(Response::none, IntoResponse::into_response)
+        } else {
+            Response::error(DataError::new(
+                ErrorCode::PermissionDenied,
+                format_args!("{} requires the {:?} role", req.path, self.require.role),
+            ))
+        };
+        Either::Left(future::ready(Ok(response)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unused_async)]
+mod tests {
+    use sithra_server::{
+        extract::payload::Payload,
+        on,
+        routing::router::Router,
+        transport::{channel::Channel, datapack::RequestDataPack},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn require_role() {
+        let permissions = Permissions::new(Roles {
+            admins: vec!["2".to_owned()],
+            ..Roles::default()
+        });
+        let router: Router<Permissions> = Router::new()
+            .route(
+                "/admin",
+                on(async || Payload("ok"))
+                    .layer(RequireRole::new(Role::Admin, permissions.clone()).reply("denied")),
+            )
+            .route(
+                Message::path(),
+                on(async |Permission(role): Permission| Payload(role)),
+            )
+            .route(
+                "/role",
+                on(async |Permission(role): Permission| Payload(role)),
+            );
+        let router: Router = router.with_state(permissions);
+        let call = async |path: &str, user: &str, role: Option<Role>| {
+            let message: Message = Message {
+                id: String::new(),
+                content: sithra_types::smallvec::SmallVec::new(),
+                role,
+            };
+            let request = RequestDataPack::default()
+                .path(path)
+                .channel(Channel::DirectFromGroup(
+                    "100".to_owned(),
+                    user.to_owned(),
+                    user.to_owned(),
+                ))
+                .payload(message);
+            let response = router.clone().call(Request::new(request)).await.unwrap();
+            response.data.into_iter().next().unwrap()
+        };
+        assert_eq!(
+            call("/admin", "2", None).await.payload::<String>().unwrap(),
+            "ok"
+        );
+        assert_eq!(
+            call("/admin", "3", Some(Role::Moderator))
+                .await
+                .payload::<String>()
+                .unwrap_err()
+                .code,
+            ErrorCode::PermissionDenied
+        );
+        assert_eq!(
+            call(Message::path(), "3", Some(Role::Moderator))
+                .await
+                .payload::<Role>()
+                .unwrap(),
+            Role::Moderator
+        );
+        assert_eq!(
+            call(Message::path(), "3", Some(Role::Owner)).await.payload::<Role>().unwrap(),
+            Role::Moderator
+        );
+        assert_eq!(
+            call("/role", "3", Some(Role::Moderator)).await.payload::<Role>().unwrap(),
+            Role::Member
+        );
+        assert_eq!(
+            call("/role", "2", None).await.payload::<Role>().unwrap(),
+            Role::Admin
+        );
+        assert_eq!(
+            call("/role", "3", None).await.payload::<Role>().unwrap(),
+            Role::Member
+        );
+
+        let mut router: Router = Router::new().route(
+            Message::path(),
+            on(async || Payload("ok"))
+                .layer(RequireRole::new(Role::Admin, Permissions::default()).reply("denied")),
+        );
+        let request = RequestDataPack::default()
+            .path(Message::path())
+            .channel(Channel::Private("3".to_owned(), "3".to_owned()))
+            .payload(Message::<sithra_types::message::Segment> {
+                id:      String::new(),
+                content: sithra_types::smallvec::SmallVec::new(),
+                role:    Some(Role::Moderator),
+            });
+        let response = router.call(Request::new(request)).await.unwrap();
+        assert_eq!(
+            response.data[0].payload::<SendMessage>().unwrap().content[0].data,
+            "denied"
+        );
+    }
+}
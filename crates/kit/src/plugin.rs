@@ -1,21 +1,32 @@
-use std::{env, process};
+use std::{env, process, time::Duration};
 
 use futures_util::{SinkExt as _, StreamExt};
 use serde::Deserialize;
 use sithra_server::{
     routing::router::Router,
-    server::{Server, ServerError},
+    server::{QueueMonitor, Server, ServerError},
     transport::{
+        compression::Compression,
         datapack::{DataPack, RequestDataPack},
-        peer::Peer,
+        peer::{Peer, PeerAddr, PeerListener},
+        security::Role,
         util::FramedPeer,
     },
 };
-use sithra_types::initialize::{Initialize, InitializeResult, PluginInitError};
+use sithra_types::initialize::{
+    Initialize, InitializeAck, InitializeResult, PluginInitError, is_compatible,
+};
 use tokio::task::JoinSet;
 
 use crate::logger::init_log;
 
+/// How long a host connecting to `--listen` may take to prove it knows the
+/// key.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often [`Plugin::run`] checks the server queues.
+const QUEUE_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct Plugin {
     peer:       Peer,
     pub server: Server,
@@ -44,15 +55,66 @@ fn handle_name(mut args: impl Iterator<Item = String>, name: &str) -> bool {
     false
 }
 
+/// Environment variable holding the pre-shared key used with `--connect` and
+/// `--listen`.
+pub const PEER_KEY_VAR: &str = "SITHRA_PEER_KEY";
+
+/// Address given with `--connect <addr>`, for plugins attaching themselves to
+/// a host listening on a socket instead of being spawned by it.
+fn connect_addr(mut args: impl Iterator<Item = String>) -> Option<String> {
+    args.find(|arg| arg.trim().eq("--connect"))?;
+    args.next()
+}
+
+/// Address given with `--listen <addr>`, for plugins waiting for a host
+/// configured to `connect` to them.
+fn listen_addr(mut args: impl Iterator<Item = String>) -> Option<String> {
+    args.find(|arg| arg.trim().eq("--listen"))?;
+    args.next()
+}
+
+fn parse_addr(addr: &str) -> PeerAddr {
+    addr.parse::<PeerAddr>().unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Waits for the host to connect to `listener`. With a key in
+/// [`PEER_KEY_VAR`], peers that do not know it are dropped. Logging is not
+/// set up yet, so failures go to stderr.
+async fn accept_host(listener: &PeerListener) -> Peer {
+    let key = env::var(PEER_KEY_VAR).ok().map(Into::into);
+    loop {
+        let peer = match listener.accept().await {
+            Ok(peer) => peer,
+            Err(err) => {
+                eprintln!("Failed to accept the host: {err}");
+                continue;
+            }
+        };
+        let Some(key) = &key else {
+            return peer;
+        };
+        match tokio::time::timeout(HANDSHAKE_TIMEOUT, peer.secure(key, Role::Plugin)).await {
+            Ok(Ok(peer)) => return peer,
+            Ok(Err(err)) => eprintln!("Rejected a peer: {err}"),
+            Err(_) => eprintln!("Rejected a peer: the key handshake timed out"),
+        }
+    }
+}
+
 impl Plugin {
     /// # Errors
     /// - [`PluginInitError::DeserializationError`] if the config could not be
     ///   deserialized.
     /// - [`PluginInitError::ConnectionClosed`] if the connection was closed
     ///   before the config was received.
+    /// - [`PluginInitError::IncompatibleProtocol`] if the host speaks a
+    ///   protocol revision this plugin does not support.
     ///
     /// # Panics
     /// - If the initialization response fails to send.
+    /// - If `--connect <addr>` is given but the host cannot be reached, or
+    ///   rejects the key in [`PEER_KEY_VAR`].
+    /// - If `--listen <addr>` is given but `addr` cannot be bound.
     pub async fn new<Config>(version: &str, name: &str) -> (Self, Initialize<Config>)
     where
         Config: for<'de> Deserialize<'de>,
@@ -60,8 +122,27 @@ impl Plugin {
         if handle_options(version, name) {
             process::exit(0);
         }
-        let peer = Peer::new();
-        let server = Server::new();
+        let peer = if let Some(addr) = connect_addr(env::args()) {
+            let addr = parse_addr(&addr);
+            let peer = Peer::connect(&addr)
+                .await
+                .unwrap_or_else(|err| panic!("Failed to connect to {addr}: {err}"));
+            match env::var(PEER_KEY_VAR) {
+                Ok(key) => peer
+                    .secure(&key.into(), Role::Plugin)
+                    .await
+                    .unwrap_or_else(|err| panic!("Failed to authenticate to {addr}: {err}")),
+                Err(_) => peer,
+            }
+        } else if let Some(addr) = listen_addr(env::args()) {
+            let addr = parse_addr(&addr);
+            let listener = PeerListener::bind(&addr)
+                .await
+                .unwrap_or_else(|err| panic!("Failed to listen on {addr}: {err}"));
+            accept_host(&listener).await
+        } else {
+            Peer::new()
+        };
         let router = Router::new();
         let mut framed = crate::transport::util::framed(peer);
 
@@ -73,10 +154,18 @@ impl Plugin {
                 let is_init = msg.path.as_ref().is_some_and(|p| p == Initialize::<Config>::path());
                 if is_init {
                     let config = msg.payload::<Initialize<Config>>();
-                    break config.map_err(PluginInitError::ConfigDeserializeError);
+                    break config
+                        .map_err(|err| PluginInitError::ConfigDeserializeError(err.message));
                 }
             }
         };
+        let init = init.and_then(|init| {
+            if is_compatible(init.protocol_version) {
+                Ok(init)
+            } else {
+                Err(PluginInitError::incompatible_protocol(init.protocol_version))
+            }
+        });
 
         let init = match init {
             Ok(init) => init,
@@ -93,6 +182,8 @@ impl Plugin {
             }
         };
 
+        let compression = Compression::negotiate(&init.compression);
+        let server = Server::with_queues(init.queues).with_compression(compression);
         init_log(server.client().sink());
 
         server
@@ -100,7 +191,7 @@ impl Plugin {
             .send(
                 RequestDataPack::default()
                     .path(Initialize::<Config>::path())
-                    .payload(InitializeResult::Ok(())),
+                    .payload(InitializeResult::Ok(InitializeAck::new(compression))),
             )
             .unwrap_or_else(|_| panic!("Failed to send initialization response: [{name}]"));
 
@@ -149,6 +240,10 @@ impl Plugin {
         }
     }
 
+    /// Starts serving the host.
+    ///
+    /// If any server queue is bounded, a warning is logged whenever a queue
+    /// is close to its capacity or lost entries to overflow.
     #[must_use]
     pub fn run(self) -> JoinSet<Result<(), ServerError>> {
         let Self {
@@ -157,9 +252,37 @@ impl Plugin {
             router,
         } = self;
         let (write, read) = peer.split();
+        let monitor = server.monitor();
 
-        server.service(router).serve(write, read)
+        let mut join_set = server.service(router).serve(write, read);
+        if monitor.stats().named().iter().any(|(_, stats)| stats.capacity.is_some()) {
+            join_set.spawn(watch_queues(monitor));
+        }
+        join_set
+    }
+}
+
+async fn watch_queues(monitor: QueueMonitor) -> Result<(), ServerError> {
+    let mut interval = tokio::time::interval(QUEUE_WATCH_INTERVAL);
+    let mut previous = monitor.stats();
+    while !monitor.is_closed() {
+        interval.tick().await;
+        let stats = monitor.stats();
+        for ((name, now), (_, before)) in stats.named().into_iter().zip(previous.named()) {
+            let Some(capacity) = now.capacity else {
+                continue;
+            };
+            let lost = (now.dropped - before.dropped) + (now.rejected - before.rejected);
+            if now.is_near_full() || lost > 0 {
+                log::warn!(
+                    "{name} queue holds {}/{capacity} entries, {lost} lost to overflow",
+                    now.depth
+                );
+            }
+        }
+        previous = stats;
     }
+    Ok(())
 }
 
 #[macro_export]
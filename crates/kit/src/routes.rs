@@ -0,0 +1,86 @@
+//! Handlers collected by [`handlers`](macro@crate::handlers) modules, see
+//! [`Routes`].
+
+use std::cmp::Reverse;
+
+use sithra_server::{
+    chain, multi,
+    routing::{endpoint::Endpoint, router::Router},
+};
+
+/// Handlers by path, registered together so that several handlers of a path
+/// share one route.
+///
+/// Every `#[handlers]` module has a `routes()` returning its handlers. Modules
+/// handling the same route type are merged before registering them:
+///
+/// ```ignore
+/// let plugin = plugin.map(|router| commands::routes().merge(chat::routes()).register(router));
+/// ```
+pub struct Routes<S = ()> {
+    handlers: Vec<(String, Option<i32>, Endpoint<S>)>,
+}
+
+impl<S> Default for Routes<S> {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+        }
+    }
+}
+
+impl<S> Routes<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a handler of `path`, with the priority it runs with in a chain.
+    #[must_use]
+    pub fn on(mut self, path: &str, priority: Option<i32>, endpoint: Endpoint<S>) -> Self {
+        self.handlers.push((path.to_owned(), priority, endpoint));
+        self
+    }
+
+    /// Adds the handlers of `other`.
+    #[must_use]
+    pub fn merge(mut self, other: Self) -> Self {
+        self.handlers.extend(other.handlers);
+        self
+    }
+
+    /// Routes every path to its handlers.
+    ///
+    /// The handlers of a path run as a [`chain`], highest priority first and
+    /// `0` by default, if any of them has a priority, and all at once with
+    /// [`multi`] otherwise.
+    ///
+    /// # Panics
+    /// Panics if `router` already has a route for one of the paths.
+    #[track_caller]
+    #[must_use]
+    pub fn register(self, mut router: Router<S>) -> Router<S> {
+        let mut paths: Vec<(String, Vec<(Option<i32>, Endpoint<S>)>)> = Vec::new();
+        for (path, priority, endpoint) in self.handlers {
+            match paths.iter_mut().find(|(known, _)| *known == path) {
+                Some((_, handlers)) => handlers.push((priority, endpoint)),
+                None => paths.push((path, vec![(priority, endpoint)])),
+            }
+        }
+        for (path, mut handlers) in paths {
+            let endpoint = if handlers.len() == 1 {
+                handlers.remove(0).1
+            } else if handlers.iter().any(|(priority, _)| priority.is_some()) {
+                handlers.sort_by_key(|(priority, _)| Reverse(priority.unwrap_or_default()));
+                chain(handlers.into_iter().map(|(_, endpoint)| endpoint))
+            } else {
+                multi(handlers.into_iter().map(|(_, endpoint)| endpoint))
+            };
+            router = router.route(&path, endpoint);
+        }
+        router
+    }
+}
@@ -25,7 +25,7 @@ async fn hello_world(State(client): State<Client>) -> Result<Payload<()>, String
         return Err("Failed to send request".to_owned());
     };
     let response = response.await.map_err(|_| "Failed to receive response".to_owned())?;
-    let response = response.payload::<String>()?;
+    let response = response.payload::<String>().map_err(|err| err.message)?;
     assert_eq!(response, "hello world!");
     Ok(Payload(()))
 }
@@ -10,7 +10,7 @@ use crate::{
         route::{Route, RouteFuture},
         router::Router,
     },
-    service::JoinAllService,
+    service::{ChainService, JoinAllService},
 };
 
 pub struct BoxedIntoRoute<S, E>(Box<dyn ErasedIntoRoute<S, E>>);
@@ -31,11 +31,26 @@ where
     }
 
     #[must_use]
-    pub fn from_multi<const N: usize>(endpoints: [Endpoint<S, Infallible>; N]) -> Self {
+    pub fn from_multi(endpoints: impl IntoIterator<Item = Endpoint<S, Infallible>>) -> Self {
         Self(Box::new(MakeErasedHandler {
-            handler:    endpoints,
+            handler:    endpoints.into_iter().collect::<Vec<_>>(),
             into_route: |endpoints, state: S| {
-                Route::new(JoinAllService::new(endpoints.map(|h| match h {
+                Route::new(JoinAllService::new(endpoints.into_iter().map(
+                    |h| match h {
+                        Endpoint::Route(r) => r,
+                        Endpoint::BoxedHandler(s) => s.into_route(state.clone()),
+                    },
+                )))
+            },
+        }))
+    }
+
+    #[must_use]
+    pub fn from_chain(endpoints: impl IntoIterator<Item = Endpoint<S, Infallible>>) -> Self {
+        Self(Box::new(MakeErasedHandler {
+            handler:    endpoints.into_iter().collect::<Vec<_>>(),
+            into_route: |endpoints, state: S| {
+                Route::new(ChainService::new(endpoints.into_iter().map(|h| match h {
                     Endpoint::Route(r) => r,
                     Endpoint::BoxedHandler(s) => s.into_route(state.clone()),
                 })))
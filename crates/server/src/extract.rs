@@ -1,18 +1,18 @@
+pub mod attachments;
 pub mod botid;
+pub mod budget;
 pub mod channel;
 pub mod client;
 pub mod context;
 pub mod correlation;
 pub mod from_ref;
+pub mod path;
 pub mod payload;
 pub mod state;
 
 use std::convert::Infallible;
 
-use sithra_transport::datapack::RequestDataPack;
-use triomphe::Arc;
-
-use crate::response::IntoResponse;
+use crate::{request::Request, response::IntoResponse};
 
 pub trait FromRequest<S>: Sized {
     /// If the extractor fails it'll use this "rejection" type. A rejection is
@@ -21,7 +21,7 @@ pub trait FromRequest<S>: Sized {
 
     /// Perform the extraction.
     fn from_request(
-        req: Arc<RequestDataPack>,
+        req: Request,
         state: &S,
     ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send;
 }
@@ -29,7 +29,15 @@ pub trait FromRequest<S>: Sized {
 impl<S: Sync> FromRequest<S> for () {
     type Rejection = Infallible;
 
-    async fn from_request(_req: Arc<RequestDataPack>, _state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request(_req: Request, _state: &S) -> Result<Self, Self::Rejection> {
         Ok(())
     }
 }
+
+impl<S: Sync> FromRequest<S> for Request {
+    type Rejection = Infallible;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(req)
+    }
+}
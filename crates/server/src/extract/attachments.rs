@@ -0,0 +1,45 @@
+use std::{
+    convert::Infallible,
+    ops::{Deref, DerefMut},
+};
+
+use bytes::Bytes;
+
+use crate::{extract::FromRequest, request::Request};
+
+/// The binary attachments carried by the request, in index order.
+pub struct Attachments(pub Vec<Bytes>);
+
+impl Deref for Attachments {
+    type Target = Vec<Bytes>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Attachments {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Attachments> for Vec<Bytes> {
+    fn from(value: Attachments) -> Self {
+        value.0
+    }
+}
+
+impl From<Vec<Bytes>> for Attachments {
+    fn from(value: Vec<Bytes>) -> Self {
+        Self(value)
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for Attachments {
+    type Rejection = Infallible;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(req.attachments.clone()))
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use crate::extract::FromRequest;
+use crate::{extract::FromRequest, request::Request};
 
 pub struct BotId(pub Option<String>);
 
@@ -36,10 +36,7 @@ impl From<Option<String>> for BotId {
 impl<S: Send + Sync> FromRequest<S> for BotId {
     type Rejection = Infallible;
 
-    async fn from_request(
-        req: triomphe::Arc<sithra_transport::datapack::RequestDataPack>,
-        _state: &S,
-    ) -> Result<Self, Self::Rejection> {
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
         Ok(Self(req.bot_id.clone()))
     }
 }
@@ -0,0 +1,53 @@
+use std::{
+    convert::Infallible,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+use crate::{extract::FromRequest, request::Request};
+
+/// The time left until the caller stops waiting for the response. `None` if
+/// the request carries no deadline.
+pub struct Budget(pub Option<Duration>);
+
+impl Budget {
+    /// Returns `true` if the request carries a deadline that has passed.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.0.is_some_and(|r| r.is_zero())
+    }
+}
+
+impl Deref for Budget {
+    type Target = Option<Duration>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Budget {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Budget> for Option<Duration> {
+    fn from(value: Budget) -> Self {
+        value.0
+    }
+}
+
+impl From<Option<Duration>> for Budget {
+    fn from(value: Option<Duration>) -> Self {
+        Self(value)
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for Budget {
+    type Rejection = Infallible;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(req.remaining()))
+    }
+}
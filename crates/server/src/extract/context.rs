@@ -6,10 +6,9 @@ use std::{
 use futures_util::FutureExt;
 use serde::Deserialize;
 use sithra_transport::{
-    ValueError,
+    DecodeError,
     datapack::{DataPack, RequestDataPack},
 };
-use triomphe::Arc;
 
 use crate::{
     extract::FromRequest,
@@ -64,13 +63,9 @@ where
     OuterState: Send + Sync,
     T: for<'de> Deserialize<'de>,
 {
-    type Rejection = Error<ValueError>;
+    type Rejection = Error<DecodeError>;
 
-    async fn from_request(
-        parts: Arc<RequestDataPack>,
-        state: &OuterState,
-    ) -> Result<Self, Self::Rejection> {
-        let request = Request::from(parts);
+    async fn from_request(request: Request, state: &OuterState) -> Result<Self, Self::Rejection> {
         let payload_cache = request.payload()?;
         Ok(Self {
             state: InnerState::from_ref(state),
@@ -108,9 +103,8 @@ where
 {
     /// Sends a request to the server and returns a future for the response.
     ///
-    /// This method sends a `RequestDataPack` to the server and returns a
-    /// `ReceiverGuard`. The `ReceiverGuard` is a future that resolves to
-    /// the `DataPack` response from the server.
+    /// This method sends a `RequestDataPack` to the server and waits for the
+    /// `DataPack` response from the server.
     ///
     /// # Arguments
     ///
@@ -138,7 +132,7 @@ where
         result
             .map(|fut| {
                 fut.map(|rs| match rs {
-                    Err(err) => Err(err.into()),
+                    Err(err) => Err(err),
                     Ok(dp) => Ok(dp.payload::<TR::Response>()?),
                 })
             })?
@@ -147,9 +141,8 @@ where
 
     /// Sends a request to the server and returns a future for the response.
     ///
-    /// This method sends a `RequestDataPack` to the server and returns a
-    /// `ReceiverGuard`. The `ReceiverGuard` is a future that resolves to
-    /// the `DataPack` response from the server.
+    /// This method sends a `RequestDataPack` to the server and waits for the
+    /// `DataPack` response from the server.
     ///
     /// # Arguments
     ///
@@ -173,6 +166,6 @@ where
     ) -> Result<DataPack, PostError> {
         let datapack: RequestDataPack = datapack.into();
         let datapack = datapack.link(self.request.raw());
-        self.state.client().post(datapack)?.await.map_err(PostError::RecvError)
+        self.state.client().post(datapack)?.await
     }
 }
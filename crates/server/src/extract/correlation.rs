@@ -5,7 +5,7 @@ use std::{
 
 use ulid::Ulid;
 
-use crate::extract::FromRequest;
+use crate::{extract::FromRequest, request::Request};
 
 pub struct Correlation(pub Ulid);
 
@@ -38,10 +38,7 @@ impl From<Ulid> for Correlation {
 impl<S: Send + Sync> FromRequest<S> for Correlation {
     type Rejection = Infallible;
 
-    async fn from_request(
-        req: triomphe::Arc<sithra_transport::datapack::RequestDataPack>,
-        _state: &S,
-    ) -> Result<Self, Self::Rejection> {
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
         Ok(Self(req.correlation()))
     }
 }
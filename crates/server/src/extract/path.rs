@@ -0,0 +1,433 @@
+use std::{
+    fmt::Display,
+    ops::{Deref, DerefMut},
+};
+
+use serde::{
+    de::{
+        self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+use thiserror::Error;
+
+use crate::{
+    extract::FromRequest,
+    request::{PathParams, Request},
+    response,
+};
+
+/// Extracts the parameters captured by the route, e.g. `{plugin}` and
+/// `{action}` in `/command/{plugin}/{action}`.
+///
+/// A single parameter can be taken as a value, several as a tuple in the
+/// order of the route or as a struct by name.
+///
+/// ```
+/// use serde::Deserialize;
+/// use sithra_server::extract::path::Path;
+///
+/// #[derive(Deserialize)]
+/// struct Command {
+///     plugin: String,
+///     action: String,
+/// }
+///
+/// async fn command(Path(Command { plugin, action }): Path<Command>) {}
+/// async fn notice(Path(kind): Path<String>) {}
+/// async fn item(Path((plugin, id)): Path<(String, u64)>) {}
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Path<T>(pub T);
+
+impl<T> Path<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned> Path<T> {
+    /// # Errors
+    /// Returns an error if the parameters do not fit `T`.
+    pub fn from_params(params: &PathParams) -> Result<Self, PathError> {
+        Ok(Self(T::deserialize(ParamsDeserializer(params))?))
+    }
+}
+
+impl<T, S> FromRequest<S> for Path<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = response::Error<PathError>;
+
+    async fn from_request(req: Request, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_params(req.params())?)
+    }
+}
+
+impl<T> Deref for Path<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Path<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PathError {
+    #[error("Expected {expected} path parameters, got {got}")]
+    WrongCount { expected: usize, got: usize },
+    #[error("Invalid path parameter `{name}`: {reason}")]
+    Invalid { name: String, reason: String },
+    #[error("Invalid path parameters: {0}")]
+    Custom(String),
+}
+
+impl de::Error for PathError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes all parameters, as a single value, a sequence or a map.
+struct ParamsDeserializer<'a>(&'a PathParams);
+
+impl<'a> ParamsDeserializer<'a> {
+    fn single(&self) -> Result<ParamDeserializer<'a>, PathError> {
+        let mut params = self.0.iter();
+        match (params.next(), params.len()) {
+            (Some((name, value)), 0) => Ok(ParamDeserializer { name, value }),
+            _ => Err(PathError::WrongCount {
+                expected: 1,
+                got:      self.0.len(),
+            }),
+        }
+    }
+
+    fn seq(&self, len: Option<usize>) -> Result<Params<'a>, PathError> {
+        match len {
+            Some(len) if len != self.0.len() => Err(PathError::WrongCount {
+                expected: len,
+                got:      self.0.len(),
+            }),
+            _ => Ok(Params {
+                params: self.0.iter().collect(),
+                next:   0,
+            }),
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ParamsDeserializer<'de> {
+    type Error = PathError;
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_identifier
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.0.len() == 1 {
+            self.single()?.deserialize_any(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(self.seq(None)?)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(self.seq(Some(len))?)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(self.seq(Some(len))?)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self.seq(None)?)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The parameters as a sequence of values or a map of names to values.
+struct Params<'a> {
+    params: Vec<(&'a str, &'a str)>,
+    next:   usize,
+}
+
+impl Params<'_> {
+    const fn remaining(&self) -> usize {
+        self.params.len() - self.next
+    }
+}
+
+impl<'de> SeqAccess<'de> for Params<'de> {
+    type Error = PathError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some(&(name, value)) = self.params.get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        seed.deserialize(ParamDeserializer { name, value }).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining())
+    }
+}
+
+impl<'de> MapAccess<'de> for Params<'de> {
+    type Error = PathError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(&(name, _)) = self.params.get(self.next) else {
+            return Ok(None);
+        };
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (name, value) = self.params[self.next];
+        self.next += 1;
+        seed.deserialize(ParamDeserializer { name, value })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining())
+    }
+}
+
+/// Deserializes a single parameter, parsing it as whatever is asked for.
+struct ParamDeserializer<'a> {
+    name:  &'a str,
+    value: &'a str,
+}
+
+impl ParamDeserializer<'_> {
+    fn invalid(&self, reason: impl Display) -> PathError {
+        PathError::Invalid {
+            name:   self.name.to_owned(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.value.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(err) => Err(self.invalid(err)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ParamDeserializer<'de> {
+    type Error = PathError;
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let name = self.name;
+        visitor
+            .visit_enum(self.value.into_deserializer())
+            .map_err(|err: de::value::Error| PathError::Invalid {
+                name:   name.to_owned(),
+                reason: err.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn params(params: &[(&str, &str)]) -> PathParams {
+        params.iter().copied().collect()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        GroupIncrease,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    struct Command {
+        plugin: String,
+        id:     u64,
+    }
+
+    #[test]
+    fn deserializes_params() {
+        let one = params(&[("kind", "group_increase")]);
+        assert_eq!(
+            Path::<String>::from_params(&one).unwrap().0,
+            "group_increase"
+        );
+        assert_eq!(
+            Path::<Kind>::from_params(&one).unwrap().0,
+            Kind::GroupIncrease
+        );
+
+        let two = params(&[("plugin", "echo"), ("id", "42")]);
+        let Path((plugin, id)) = Path::<(String, u64)>::from_params(&two).unwrap();
+        assert_eq!((plugin.as_str(), id), ("echo", 42));
+        assert_eq!(
+            Path::<Command>::from_params(&two).unwrap().0,
+            Command {
+                plugin: "echo".to_owned(),
+                id:     42,
+            }
+        );
+
+        assert!(matches!(
+            Path::<String>::from_params(&two),
+            Err(PathError::WrongCount {
+                expected: 1,
+                got:      2,
+            })
+        ));
+        assert!(matches!(
+            Path::<(String, u8)>::from_params(&params(&[("plugin", "echo"), ("id", "x")])),
+            Err(PathError::Invalid { name, .. }) if name == "id"
+        ));
+        assert!(matches!(
+            Path::<Kind>::from_params(&params(&[("kind", "other")])),
+            Err(PathError::Invalid { .. })
+        ));
+    }
+}
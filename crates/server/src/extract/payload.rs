@@ -1,10 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
 use serde::de::DeserializeOwned;
-use sithra_transport::{Value, ValueError, datapack::RequestDataPack};
-use triomphe::Arc;
+use sithra_transport::{DecodeError, Value, ValueError, payload::RawPayload};
 
-use crate::{extract::FromRequest, response};
+use crate::{extract::FromRequest, request::Request, response};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Payload<T>(pub T);
@@ -24,6 +23,13 @@ impl<T> From<T> for Payload<T> {
 impl<T: DeserializeOwned> Payload<T> {
     /// # Errors
     /// Returns an error if the value cannot be deserialized.
+    pub fn from_raw(payload: &RawPayload) -> Result<Self, DecodeError> {
+        Ok(Self(payload.decode()?))
+    }
+
+    /// # Errors
+    /// Returns an error if the value cannot be deserialized.
+    #[deprecated = "payloads are kept as raw msgpack, use `Payload::from_raw`"]
     pub fn from_value(value: &Value) -> Result<Self, ValueError> {
         Ok(Self(sithra_transport::from_value(value.clone())?))
     }
@@ -34,10 +40,10 @@ where
     T: DeserializeOwned,
     S: Send + Sync,
 {
-    type Rejection = response::Error<ValueError>;
+    type Rejection = response::Error<DecodeError>;
 
-    async fn from_request(req: Arc<RequestDataPack>, _: &S) -> Result<Self, Self::Rejection> {
-        Ok(Self::from_value(&req.payload)?)
+    async fn from_request(req: Request, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_raw(&req.payload)?)
     }
 }
 
@@ -3,10 +3,7 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use sithra_transport::datapack::RequestDataPack;
-use triomphe::Arc;
-
-use crate::{extract::FromRequest, traits::FromRef};
+use crate::{extract::FromRequest, request::Request, traits::FromRef};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct State<S>(pub S);
@@ -18,10 +15,7 @@ where
 {
     type Rejection = Infallible;
 
-    async fn from_request(
-        _parts: Arc<RequestDataPack>,
-        state: &OuterState,
-    ) -> Result<Self, Self::Rejection> {
+    async fn from_request(_parts: Request, state: &OuterState) -> Result<Self, Self::Rejection> {
         let inner_state = InnerState::from_ref(state);
         Ok(Self(inner_state))
     }
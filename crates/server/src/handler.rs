@@ -278,10 +278,9 @@ macro_rules! handler_for_tuple {
             type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
 
             fn call(self, req: Request, state: Sta) -> Self::Future {
-                let raw = req.into_raw();
                 Box::pin(async move {
                     $(
-                        let $T = match $T::from_request(raw.clone(), &state).await {
+                        let $T = match $T::from_request(req.clone(), &state).await {
                             Ok(value) => value,
                             Err(rejection) => return rejection.into_response(),
                         };
@@ -6,11 +6,14 @@ pub mod boxed;
 pub mod extract;
 pub mod handler;
 pub mod multi;
+pub mod queue;
 pub mod request;
 pub mod response;
 pub mod routing;
 pub mod server;
+pub mod session;
 pub mod shared;
+pub mod trace;
 pub mod traits;
 pub use sithra_transport as transport;
 pub mod sync {
@@ -45,13 +48,33 @@ where
 }
 
 #[must_use]
-pub fn multi<S, const N: usize>(endpoints: [Endpoint<S, Infallible>; N]) -> Endpoint<S, Infallible>
+pub fn multi<S>(
+    endpoints: impl IntoIterator<Item = Endpoint<S, Infallible>>,
+) -> Endpoint<S, Infallible>
 where
     S: Clone + Send + Sync + 'static,
 {
     Endpoint::BoxedHandler(BoxedIntoRoute::from_multi(endpoints))
 }
 
+/// Calls the handlers one after another, in order, until one consumes.
+///
+/// A handler consumes by replying or by returning
+/// [`Consume`](response::Consume). It can reply and still let the next ones
+/// run with [`Continue`](response::Continue).
+///
+/// Unlike [`multi`], this makes the handler answering a request
+/// deterministic when several of them could.
+#[must_use]
+pub fn chain<S>(
+    endpoints: impl IntoIterator<Item = Endpoint<S, Infallible>>,
+) -> Endpoint<S, Infallible>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Endpoint::BoxedHandler(BoxedIntoRoute::from_chain(endpoints))
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -59,7 +82,7 @@ mod tests {
         sync::atomic::{AtomicUsize, Ordering},
     };
 
-    use sithra_transport::{Value, datapack::RequestDataPack};
+    use sithra_transport::{Value, datapack::RequestDataPack, peer::Peer, trace::TraceContext};
     use tokio::sync::Mutex;
     use tower::Service;
     use triomphe::Arc;
@@ -69,6 +92,7 @@ mod tests {
         multi, on,
         request::Request,
         routing::router::Router,
+        server::Server,
     };
 
     #[derive(Default, Clone)]
@@ -132,4 +156,435 @@ mod tests {
             Some(correlation)
         );
     }
+    #[tokio::test]
+    async fn path_params() {
+        use crate::extract::path::Path;
+
+        let mut router: Router = Router::new()
+            .route(
+                "/command/{plugin}/{action}",
+                on(async |Path((plugin, action)): Path<(String, String)>| {
+                    Payload(format!("{plugin} {action}"))
+                }),
+            )
+            .route(
+                "/event/notice.{kind}",
+                on(async |Path(kind): Path<String>| Payload(kind)),
+            )
+            .route(
+                "/files/{*path}",
+                on(async |Path(path): Path<String>| Payload(path)),
+            )
+            .route("/count/{n}", on(async |Path(n): Path<u32>| Payload(n + 1)));
+
+        let mut call = async |path: &str| {
+            let response = router.call(Request::new(test_data(path))).await.unwrap();
+            response.data[0].payload::<Value>()
+        };
+        assert_eq!(call("/command/echo/reload").await.unwrap(), "echo reload");
+        assert_eq!(call("/event/notice.poke").await.unwrap(), "poke");
+        assert_eq!(call("/files/a/b.txt").await.unwrap(), "a/b.txt");
+        assert_eq!(call("/count/41").await.unwrap(), 42);
+        assert!(call("/count/x").await.is_err());
+    }
+    #[tokio::test]
+    async fn nest_and_merge() {
+        use crate::extract::path::Path;
+
+        let state = AppState::default();
+        let counter = Router::new()
+            .route(
+                "/count",
+                on(async |State(state): State<AppState>| {
+                    state.counter.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .with_state(state.clone());
+        let layered = Arc::new(AtomicUsize::new(0));
+        let layered_ = layered.clone();
+        let status = Router::new()
+            .route("/status/{id}", on(async |Path(id): Path<u32>| Payload(id)))
+            .fallback(async || Payload("unknown"))
+            .layer(tower::util::MapRequestLayer::new(move |req: Request| {
+                layered_.fetch_add(1, Ordering::SeqCst);
+                req
+            }));
+        let mut router: Router = Router::new()
+            .route("/ping", on(async || Payload("pong")))
+            .merge(counter)
+            .nest("/mc", status);
+
+        let mut call = async |path: &str| {
+            let response = router.call(Request::new(test_data(path))).await.unwrap();
+            response.data.first().map(|data| data.payload::<Value>().unwrap())
+        };
+        assert_eq!(call("/ping").await.unwrap(), "pong");
+        assert_eq!(call("/count").await, None);
+        assert_eq!(state.counter.load(Ordering::SeqCst), 1);
+        assert_eq!(layered.load(Ordering::SeqCst), 0);
+        assert_eq!(call("/mc/status/7").await.unwrap(), 7);
+        assert_eq!(call("/mc/other").await.unwrap(), "unknown");
+        assert_eq!(layered.load(Ordering::SeqCst), 2);
+        assert_eq!(call("/other").await, None);
+    }
+    #[tokio::test]
+    async fn nested_fallback_params() {
+        use crate::extract::path::Path;
+
+        let servers = Router::new()
+            .route(
+                "/status/{id}",
+                on(async |Path((server, id)): Path<(String, u32)>| {
+                    Payload(format!("{server} {id}"))
+                }),
+            )
+            .fallback(async |Path(server): Path<String>| Payload(server));
+        let mut router: Router = Router::new().nest("/mc/{server}", servers);
+
+        let mut call = async |path: &str| {
+            let response = router.call(Request::new(test_data(path))).await.unwrap();
+            response.data[0].payload::<Value>()
+        };
+        assert_eq!(call("/mc/lobby/status/7").await.unwrap(), "lobby 7");
+        assert_eq!(call("/mc/lobby/other/path").await.unwrap(), "lobby");
+    }
+    #[tokio::test]
+    async fn chain_stops_when_consumed() {
+        use crate::{
+            chain,
+            response::{Consume, Continue},
+        };
+
+        let state = AppState::default();
+        let mut router: Router = Router::new()
+            .route(
+                "/message",
+                chain([
+                    on(async |State(state): State<AppState>| {
+                        state.counter.fetch_add(1, Ordering::SeqCst);
+                    }),
+                    on(async || Continue(Payload("first"))),
+                    on(async |Payload(text): Payload<String>| {
+                        (text == "roll").then_some(Payload("rolled"))
+                    }),
+                    on(async |Payload(text): Payload<String>| {
+                        (text == "quiet").then_some(Consume(()))
+                    }),
+                    on(async || Payload("last")),
+                ]),
+            )
+            .with_state(state.clone());
+
+        let mut call = async |text: &str| {
+            let request = Request::new(test_data("/message").payload(text));
+            let response = router.call(request).await.unwrap();
+            response
+                .data
+                .iter()
+                .map(|data| data.payload::<String>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(call("roll").await, ["first", "rolled"]);
+        assert_eq!(call("quiet").await, ["first"]);
+        assert_eq!(call("other").await, ["first", "last"]);
+        assert_eq!(state.counter.load(Ordering::SeqCst), 3);
+    }
+    #[tokio::test]
+    async fn sessions_route_follow_ups() {
+        use std::time::Duration;
+
+        use sithra_transport::channel::Channel;
+
+        use crate::session::Sessions;
+
+        let sessions = Sessions::new();
+        let router: Router = Router::new()
+            .route(
+                "/message",
+                on(
+                    async |State(sessions): State<Sessions>,
+                           req: Request,
+                           Payload(text): Payload<String>| {
+                        if text != "ask" {
+                            return Payload(format!("echo {text}"));
+                        }
+                        let waiter =
+                            sessions.wait(req.bot_id(), "/message", req.channel().unwrap());
+                        let answer = waiter.recv(Duration::from_millis(100)).await;
+                        let answer = answer.map(|req| req.payload::<String>().unwrap());
+                        Payload(answer.unwrap_or_else(|| "timeout".to_owned()))
+                    },
+                ),
+            )
+            .layer(sessions.clone())
+            .with_state(sessions.clone());
+
+        let call = |user: &str, text: &str| {
+            let request = test_data("/message")
+                .channel(Channel::Private(user.to_owned(), user.to_owned()))
+                .payload(text);
+            let mut router = router.clone();
+            async move {
+                let response = router.call(Request::new(request)).await.unwrap();
+                response
+                    .data
+                    .iter()
+                    .map(|data| data.payload::<String>().unwrap())
+                    .collect::<Vec<_>>()
+            }
+        };
+        let asking = tokio::spawn(call("alice", "ask"));
+        while sessions.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(call("bob", "hi").await, ["echo hi"]);
+        assert!(call("alice", "yes").await.is_empty());
+        assert_eq!(asking.await.unwrap(), ["yes"]);
+        assert!(sessions.is_empty());
+
+        assert_eq!(call("alice", "ask").await, ["timeout"]);
+        assert!(sessions.is_empty());
+        assert_eq!(call("alice", "hi").await, ["echo hi"]);
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn cancel_aborts_handler() {
+        struct DropFlag(Arc<AtomicUsize>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let state = AppState::default();
+        let router = Router::new()
+            .route(
+                "/slow",
+                on(async |State(state): State<AppState>| {
+                    let _flag = DropFlag(state.counter);
+                    tokio::time::sleep(std::time::Duration::from_hours(1)).await;
+                }),
+            )
+            .with_state(state.clone());
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        let (writer, reader) = Peer::from_unix(a).split();
+        let _serving = Server::new().service(router).serve(writer, reader);
+        let caller = Server::new().service(Router::new());
+        let client = caller.client();
+        let (writer, reader) = Peer::from_unix(b).split();
+        let _calling = caller.serve(writer, reader);
+
+        let response = client.post(test_data("/slow")).unwrap();
+        let timeout = tokio::time::timeout(std::time::Duration::from_millis(50), response).await;
+        assert!(timeout.is_err());
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while state.counter.load(Ordering::SeqCst) == 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("handler was not aborted");
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn post_times_out() {
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        let (writer, reader) = Peer::from_unix(a).split();
+        let _serving = Server::new().service(Router::new()).serve(writer, reader);
+        let caller = Server::new().service(Router::new());
+        let client = caller.client();
+        let (writer, reader) = Peer::from_unix(b).split();
+        let _calling = caller.serve(writer, reader);
+
+        let response = client
+            .post_with_timeout(test_data("/unrouted"), std::time::Duration::from_millis(20))
+            .unwrap()
+            .await;
+        assert!(matches!(response, Err(crate::server::PostError::Timeout)));
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn fallback_answers_unmatched() {
+        let router = Router::new().route("/known", on(async || Payload("known"))).fallback(
+            async |req: Request| {
+                crate::response::Response::error(format!("Unsupported request: {}", req.path))
+            },
+        );
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        let (writer, reader) = Peer::from_unix(a).split();
+        let _serving = Server::new().service(router).serve(writer, reader);
+        let caller = Server::new().service(Router::new());
+        let client = caller.client();
+        let (writer, reader) = Peer::from_unix(b).split();
+        let _calling = caller.serve(writer, reader);
+
+        let response = client.post(test_data("/known")).unwrap().await.unwrap();
+        assert_eq!(response.payload::<String>().unwrap(), "known");
+        let response = client
+            .post_with_timeout(
+                test_data("/command/unknown"),
+                std::time::Duration::from_secs(5),
+            )
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(matches!(
+            response.result,
+            sithra_transport::datapack::DataResult::Error(error)
+                if error.message == "Unsupported request: /command/unknown"
+        ));
+    }
+    #[test]
+    fn post_error_from_data_error() {
+        use sithra_transport::error::{DataError, ErrorCode};
+
+        use crate::server::PostError;
+
+        let error = PostError::from(DataError::new(ErrorCode::NotSupported, "no"));
+        assert!(matches!(&error, PostError::NotSupported(inner) if inner.message == "no"));
+        assert_eq!(
+            error.error().map(|error| error.code),
+            Some(ErrorCode::NotSupported)
+        );
+        let error = PostError::from(DataError::new(ErrorCode::Internal, "boom"));
+        assert!(matches!(error, PostError::RequestError(_)));
+        assert!(PostError::from(String::from("plain")).error().is_some());
+        assert!(PostError::Timeout.error().is_none());
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn payload_from_value() {
+        let payload = Payload::<String>::from_value(&Value::from("hi")).unwrap();
+        assert_eq!(payload.0, "hi");
+        let request = Request::new(test_data("/").payload("hi"));
+        assert_eq!(request.payload::<String>().unwrap(), "hi");
+        assert_eq!(request.payload_ref::<&str>().unwrap(), "hi");
+    }
+    #[test]
+    fn result_keeps_data_error() {
+        use sithra_transport::{
+            datapack::DataResult,
+            error::{DataError, ErrorCode},
+        };
+
+        use crate::{
+            response::{Error, IntoResponse},
+            server::PostError,
+        };
+
+        let error = |response: crate::response::Response| match &response.data[0].result {
+            DataResult::Error(error) => error.clone(),
+            DataResult::Payload(_) => panic!("not an error"),
+        };
+        let denied = DataError::new(ErrorCode::PermissionDenied, "no").with_details(42.into());
+        let result: Result<(), _> = Err(denied.clone());
+        assert_eq!(error(result.into_response()), denied);
+        let result: Result<(), _> = Err(PostError::from(denied.clone()));
+        assert_eq!(error(result.into_response()), denied);
+        let result: Result<(), PostError> = Err(PostError::Timeout);
+        assert_eq!(error(result.into_response()).code, ErrorCode::Timeout);
+        let result: Result<(), _> = Err(Error::from(1.5));
+        assert_eq!(error(result.into_response()).message, "1.5");
+    }
+    #[test]
+    fn send_rejects_when_full() {
+        use crate::{
+            queue::{OverflowPolicy, QueueConfig, ServerQueues},
+            server::PostError,
+        };
+
+        let queues = ServerQueues {
+            writer: QueueConfig::bounded(1, OverflowPolicy::Reject),
+            ..ServerQueues::default()
+        };
+        let server = Server::with_queues(queues);
+        let client = server.client();
+        client.send(test_data("/first")).unwrap();
+        assert!(matches!(
+            client.send(test_data("/second")),
+            Err(PostError::QueueFull(_))
+        ));
+        assert!(matches!(
+            client.post(test_data("/third")),
+            Err(PostError::QueueFull(_))
+        ));
+        let stats = server.monitor().stats();
+        assert_eq!((stats.writer.depth, stats.writer.rejected), (1, 2));
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn busy_handlers_fill_request_queue() {
+        use std::time::Duration;
+
+        use sithra_transport::error::ErrorCode;
+
+        use crate::queue::{OverflowPolicy, QueueConfig, ServerQueues};
+
+        for overflow in [OverflowPolicy::Reject, OverflowPolicy::DropOldest] {
+            let state = AppState::default();
+            let router = Router::new()
+                .route(
+                    "/slow",
+                    on(async |State(state): State<AppState>| {
+                        state.counter.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Payload("done")
+                    }),
+                )
+                .with_state(state.clone());
+            let queues = ServerQueues {
+                request: QueueConfig::bounded(1, overflow),
+                ..ServerQueues::default()
+            };
+            let (a, b) = tokio::net::UnixStream::pair().unwrap();
+            let (writer, reader) = Peer::from_unix(a).split();
+            let _serving = Server::with_queues(queues).service(router).serve(writer, reader);
+            let caller = Server::new().service(Router::new());
+            let client = caller.client();
+            let (writer, reader) = Peer::from_unix(b).split();
+            let _calling = caller.serve(writer, reader);
+
+            let first = client.post(test_data("/slow")).unwrap();
+            while state.counter.load(Ordering::SeqCst) == 0 {
+                tokio::task::yield_now().await;
+            }
+            // The only handler slot is taken, so the second request waits in
+            // the queue and the third one overflows it.
+            let second = client.post(test_data("/slow")).unwrap();
+            let third = client.post(test_data("/slow")).unwrap();
+            let (overflowed, served) = match overflow {
+                OverflowPolicy::DropOldest => (second.await, third.await),
+                _ => (third.await, second.await),
+            };
+            let busy = overflowed.unwrap().payload::<String>().unwrap_err();
+            assert_eq!(busy.code, ErrorCode::Busy);
+            assert_eq!(first.await.unwrap().payload::<String>().unwrap(), "done");
+            assert_eq!(served.unwrap().payload::<String>().unwrap(), "done");
+            assert_eq!(state.counter.load(Ordering::SeqCst), 2);
+        }
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn trace_is_propagated() {
+        let router = Router::new().route("/trace", on(async || Payload(crate::trace::current())));
+        let (a, b) = tokio::net::UnixStream::pair().unwrap();
+        let (writer, reader) = Peer::from_unix(a).split();
+        let _serving = Server::new().service(router).serve(writer, reader);
+        let caller = Server::new().service(Router::new());
+        let client = caller.client();
+        let (writer, reader) = Peer::from_unix(b).split();
+        let _calling = caller.serve(writer, reader);
+
+        let root = TraceContext::root();
+        let response = crate::trace::scope(root, async {
+            client.post(test_data("/trace")).unwrap().await.unwrap()
+        })
+        .await;
+        let seen: TraceContext = response.payload::<Option<_>>().unwrap().unwrap();
+        assert_eq!(seen.trace_id, root.trace_id);
+        assert_eq!(seen.parent_id, Some(root.span_id));
+        assert_eq!(response.trace, Some(seen));
+    }
 }
@@ -8,7 +8,7 @@ use std::{
 
 use pin_project::pin_project;
 use smallvec::SmallVec;
-use tokio::{pin, task::JoinSet};
+use tokio::task::JoinSet;
 use tower::Service;
 
 /// A service that wraps multiple services of the same type and dispatches
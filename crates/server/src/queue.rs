@@ -0,0 +1,455 @@
+//! Queues between the tasks of a [`Server`](crate::server::Server).
+//!
+//! Every queue can be bounded with a [`QueueConfig`]. Once a bounded queue is
+//! full, its [`OverflowPolicy`] decides whether producers wait for room, the
+//! oldest entry is dropped, or the new entry is rejected. [`QueueStats`] report
+//! the depth of a queue together with how much it lost to overflow.
+
+use std::{collections::VecDeque, pin::pin, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+/// What a full queue does with a new entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Producers wait until the consumer made room. Producers that cannot
+    /// wait get the entry back as [`QueueError::Full`].
+    #[default]
+    Block,
+    /// The oldest queued entry is dropped to make room.
+    DropOldest,
+    /// The new entry is handed back as [`QueueError::Full`].
+    Reject,
+}
+
+/// The capacity and overflow policy of a queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QueueConfig {
+    /// The maximum number of queued entries, or `None` for no limit.
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+impl QueueConfig {
+    /// A queue without a limit.
+    #[must_use]
+    pub const fn unbounded() -> Self {
+        Self {
+            capacity: None,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+
+    /// A queue holding at most `capacity` entries.
+    #[must_use]
+    pub const fn bounded(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity: Some(capacity),
+            overflow,
+        }
+    }
+}
+
+/// The queue configuration of a [`Server`](crate::server::Server).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ServerQueues {
+    /// Outgoing datapacks waiting to be written to the peer.
+    #[serde(default)]
+    pub writer:   QueueConfig,
+    /// Incoming requests waiting for a handler.
+    #[serde(default)]
+    pub request:  QueueConfig,
+    /// Incoming responses waiting to be matched with their request.
+    #[serde(default)]
+    pub response: QueueConfig,
+}
+
+impl ServerQueues {
+    /// Applies `config` to every queue.
+    #[must_use]
+    pub const fn all(config: QueueConfig) -> Self {
+        Self {
+            writer:   config,
+            request:  config,
+            response: config,
+        }
+    }
+
+    /// Whether any queue has a capacity.
+    #[must_use]
+    pub const fn is_bounded(&self) -> bool {
+        self.writer.capacity.is_some()
+            || self.request.capacity.is_some()
+            || self.response.capacity.is_some()
+    }
+}
+
+/// A snapshot of a queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// The number of queued entries.
+    pub depth:    usize,
+    pub capacity: Option<usize>,
+    /// Entries dropped by [`OverflowPolicy::DropOldest`].
+    pub dropped:  u64,
+    /// Entries refused because the queue was full.
+    pub rejected: u64,
+}
+
+impl QueueStats {
+    /// Whether the queue is filled to at least 80% of its capacity.
+    #[must_use]
+    pub const fn is_near_full(&self) -> bool {
+        match self.capacity {
+            Some(capacity) => self.depth.saturating_mul(5) >= capacity.saturating_mul(4),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum QueueError<T> {
+    #[error("Queue full")]
+    Full(T),
+    #[error("Queue closed")]
+    Closed(T),
+}
+
+impl<T> QueueError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) | Self::Closed(value) => value,
+        }
+    }
+}
+
+struct State<T> {
+    items:    VecDeque<T>,
+    senders:  usize,
+    closed:   bool,
+    dropped:  u64,
+    rejected: u64,
+}
+
+struct Shared<T> {
+    state:    Mutex<State<T>>,
+    config:   QueueConfig,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl<T> Shared<T> {
+    fn stats(&self) -> QueueStats {
+        let state = self.state.lock();
+        QueueStats {
+            depth:    state.items.len(),
+            capacity: self.config.capacity,
+            dropped:  state.dropped,
+            rejected: state.rejected,
+        }
+    }
+}
+
+/// Creates a queue with the given configuration.
+///
+/// A capacity of zero is treated as one.
+#[must_use]
+pub fn queue<T>(mut config: QueueConfig) -> (QueueSender<T>, QueueReceiver<T>) {
+    config.capacity = config.capacity.map(|capacity| capacity.max(1));
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            items:    VecDeque::new(),
+            senders:  1,
+            closed:   false,
+            dropped:  0,
+            rejected: 0,
+        }),
+        config,
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+    (
+        QueueSender {
+            shared: Arc::clone(&shared),
+        },
+        QueueReceiver { shared },
+    )
+}
+
+/// The sending half of a queue.
+pub struct QueueSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for QueueSender<T> {
+    fn drop(&mut self) {
+        let last = {
+            let mut state = self.shared.state.lock();
+            state.senders -= 1;
+            state.senders == 0
+        };
+        if last {
+            self.shared.readable.notify_one();
+        }
+    }
+}
+
+impl<T> QueueSender<T> {
+    /// Queues `value` without waiting.
+    ///
+    /// Returns the entry dropped to make room, if any.
+    ///
+    /// # Errors
+    /// Returns [`QueueError::Full`] if the queue is full and its policy is not
+    /// [`OverflowPolicy::DropOldest`], and [`QueueError::Closed`] if the
+    /// receiver is gone.
+    pub fn try_send(&self, value: T) -> Result<Option<T>, QueueError<T>> {
+        self.push(value, false)
+    }
+
+    /// Like [`Self::try_send`], but under [`OverflowPolicy::Block`] a full
+    /// queue is not counted as a rejection, because the caller is going to
+    /// wait with [`Self::send`].
+    pub(crate) fn start_send(&self, value: T) -> Result<Option<T>, QueueError<T>> {
+        self.push(value, true)
+    }
+
+    /// Queues `value`, waiting for room under [`OverflowPolicy::Block`].
+    ///
+    /// Returns the entry dropped to make room, if any.
+    ///
+    /// # Errors
+    /// Returns [`QueueError::Full`] if the queue is full and its policy is
+    /// [`OverflowPolicy::Reject`], and [`QueueError::Closed`] if the receiver
+    /// is gone.
+    pub async fn send(&self, mut value: T) -> Result<Option<T>, QueueError<T>> {
+        loop {
+            let mut writable = pin!(self.shared.writable.notified());
+            writable.as_mut().enable();
+            match self.push(value, true) {
+                Err(QueueError::Full(rest))
+                    if self.shared.config.overflow == OverflowPolicy::Block =>
+                {
+                    value = rest;
+                }
+                result => return result,
+            }
+            writable.await;
+        }
+    }
+
+    /// Queues `value` even if the queue is full, for control messages such
+    /// as cancellations that must not be lost.
+    ///
+    /// # Errors
+    /// Returns [`QueueError::Closed`] if the receiver is gone.
+    pub(crate) fn force_send(&self, value: T) -> Result<(), QueueError<T>> {
+        {
+            let mut state = self.shared.state.lock();
+            if state.closed {
+                return Err(QueueError::Closed(value));
+            }
+            state.items.push_back(value);
+        }
+        self.shared.readable.notify_one();
+        Ok(())
+    }
+
+    fn push(&self, value: T, wait: bool) -> Result<Option<T>, QueueError<T>> {
+        let evicted = {
+            let mut state = self.shared.state.lock();
+            if state.closed {
+                return Err(QueueError::Closed(value));
+            }
+            let full = self
+                .shared
+                .config
+                .capacity
+                .is_some_and(|capacity| state.items.len() >= capacity);
+            let evicted = if full {
+                match self.shared.config.overflow {
+                    OverflowPolicy::DropOldest => {
+                        state.dropped += 1;
+                        state.items.pop_front()
+                    }
+                    OverflowPolicy::Block if wait => return Err(QueueError::Full(value)),
+                    OverflowPolicy::Block | OverflowPolicy::Reject => {
+                        state.rejected += 1;
+                        return Err(QueueError::Full(value));
+                    }
+                }
+            } else {
+                None
+            };
+            state.items.push_back(value);
+            evicted
+        };
+        self.shared.readable.notify_one();
+        Ok(evicted)
+    }
+
+    #[must_use]
+    pub fn config(&self) -> QueueConfig {
+        self.shared.config
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+
+    /// A handle that reports the stats of this queue without keeping it open.
+    #[must_use]
+    pub fn probe(&self) -> QueueProbe<T> {
+        QueueProbe {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+/// The receiving half of a queue.
+pub struct QueueReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for QueueReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().closed = true;
+        self.shared.writable.notify_waiters();
+    }
+}
+
+impl<T> QueueReceiver<T> {
+    /// Receives the next entry, or `None` once the queue is empty and every
+    /// sender is gone.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let readable = self.shared.readable.notified();
+            {
+                let mut state = self.shared.state.lock();
+                if let Some(value) = state.items.pop_front() {
+                    drop(state);
+                    self.shared.writable.notify_waiters();
+                    return Some(value);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+            readable.await;
+        }
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+
+    /// Takes the first queued entry matching `f` out of the queue.
+    pub(crate) fn remove_first(&self, f: impl FnMut(&T) -> bool) -> Option<T> {
+        let mut state = self.shared.state.lock();
+        let index = state.items.iter().position(f)?;
+        let value = state.items.remove(index);
+        drop(state);
+        self.shared.writable.notify_waiters();
+        value
+    }
+}
+
+/// Reports the stats of a queue.
+pub struct QueueProbe<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for QueueProbe<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> QueueProbe<T> {
+    #[must_use]
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+
+    /// Whether every sender of the queue is gone.
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().senders == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn overflow_policies() {
+        let (tx, mut rx) = queue(QueueConfig::bounded(2, OverflowPolicy::DropOldest));
+        for i in 0..3 {
+            tx.try_send(i).unwrap();
+        }
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(tx.stats().dropped, 1);
+
+        let (tx, _rx) = queue(QueueConfig::bounded(1, OverflowPolicy::Reject));
+        tx.try_send(0).unwrap();
+        assert!(matches!(tx.send(1).await, Err(QueueError::Full(1))));
+        assert_eq!(
+            tx.stats(),
+            QueueStats {
+                depth:    1,
+                capacity: Some(1),
+                dropped:  0,
+                rejected: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn force_send_ignores_capacity() {
+        let (tx, mut rx) = queue(QueueConfig::bounded(1, OverflowPolicy::Reject));
+        tx.try_send(0).unwrap();
+        tx.force_send(1).unwrap();
+        assert_eq!(tx.stats().rejected, 0);
+        assert_eq!(rx.recv().await, Some(0));
+        assert_eq!(rx.recv().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_room() {
+        let (tx, mut rx) = queue(QueueConfig::bounded(1, OverflowPolicy::Block));
+        tx.send(0).await.unwrap();
+        let blocked = tokio::time::timeout(Duration::from_millis(20), tx.send(1)).await;
+        assert!(blocked.is_err());
+
+        let sender = tx.clone();
+        let pending = tokio::spawn(async move { sender.send(1).await.is_ok() });
+        assert_eq!(rx.recv().await, Some(0));
+        assert!(pending.await.unwrap());
+        drop(tx);
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(rx.stats().rejected, 0);
+    }
+}
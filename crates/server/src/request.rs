@@ -1,11 +1,52 @@
-use serde::Deserialize;
-use sithra_transport::{ValueError, channel::Channel, datapack::RequestDataPack};
+use std::ops::Deref;
+
+use serde::{Deserialize, de::DeserializeOwned};
+use sithra_transport::{Bytes, DecodeError, channel::Channel, datapack::RequestDataPack};
 use triomphe::Arc;
 use ulid::Ulid;
 
+/// The segments captured by the parameters of the route a request matched,
+/// in the order they appear in the route.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathParams(std::sync::Arc<[(String, String)]>);
+
+impl PathParams {
+    /// The segment captured by the parameter `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter().find(|(key, _)| *key == name).map(|(_, value)| value)
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'k, 'v> FromIterator<(&'k str, &'v str)> for PathParams {
+    fn from_iter<I: IntoIterator<Item = (&'k str, &'v str)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Request {
     pub data: Arc<RequestDataPack>,
+    params:   PathParams,
 }
 
 impl From<RequestDataPack> for Request {
@@ -16,7 +57,15 @@ impl From<RequestDataPack> for Request {
 
 impl From<Arc<RequestDataPack>> for Request {
     fn from(value: Arc<RequestDataPack>) -> Self {
-        Self { data: value }
+        Self::from_raw(value)
+    }
+}
+
+impl Deref for Request {
+    type Target = RequestDataPack;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
     }
 }
 
@@ -37,15 +86,28 @@ impl Request {
     }
 
     #[must_use]
-    pub const fn from_raw(data: Arc<RequestDataPack>) -> Self {
-        Self { data }
+    pub fn from_raw(data: Arc<RequestDataPack>) -> Self {
+        Self {
+            data,
+            params: PathParams::default(),
+        }
     }
 
     #[must_use]
     pub fn new(data: RequestDataPack) -> Self {
-        Self {
-            data: Arc::new(data),
-        }
+        Self::from_raw(Arc::new(data))
+    }
+
+    /// The parameters captured by the route this request was matched to.
+    #[must_use]
+    pub const fn params(&self) -> &PathParams {
+        &self.params
+    }
+
+    #[must_use]
+    pub fn with_params(mut self, params: PathParams) -> Self {
+        self.params = params;
+        self
     }
 
     #[must_use]
@@ -60,12 +122,26 @@ impl Request {
 
     /// # Errors
     /// Returns an error if the payload cannot be deserialized.
-    pub fn payload<T: for<'de> Deserialize<'de>>(&self) -> Result<T, ValueError> {
-        sithra_transport::from_value(self.data.payload.clone())
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, DecodeError> {
+        self.data.decode_payload()
+    }
+
+    /// Like [`payload`](Self::payload), but `T` may borrow from the request,
+    /// e.g. a `&str`.
+    ///
+    /// # Errors
+    /// Returns an error if the payload cannot be deserialized.
+    pub fn payload_ref<'de, T: Deserialize<'de>>(&'de self) -> Result<T, DecodeError> {
+        self.data.decode_payload()
     }
 
     #[must_use]
     pub fn channel(&self) -> Option<Channel> {
         self.data.channel.clone()
     }
+
+    #[must_use]
+    pub fn attachment(&self, index: usize) -> Option<&Bytes> {
+        self.data.attachment(index)
+    }
 }
@@ -10,7 +10,9 @@ use pin_project::pin_project;
 use serde::Serialize;
 use sithra_transport::{
     channel::Channel,
-    datapack::{DataPack, RequestDataPack},
+    datapack::{DataPack, DataResult, RequestDataPack},
+    error::{DataError, ErrorCode},
+    payload::RawPayload,
 };
 use smallvec::SmallVec;
 use tower::Service;
@@ -20,8 +22,32 @@ use crate::{extract::payload::Payload, request::Request};
 
 pub struct Response {
     pub data: SmallVec<[DataPack; 1]>,
+    pub flow: Flow,
 }
 
+/// How the response of a handler in a [`chain`](crate::chain) affects the
+/// handlers after it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Flow {
+    /// Stops the chain if the response is not empty.
+    #[default]
+    Auto,
+    /// Stops the chain, even if the response is empty.
+    Consume,
+    /// Lets the chain continue, even if the response is not empty.
+    Continue,
+}
+
+/// Marks the request as handled, so the handlers after this one in a
+/// [`chain`](crate::chain) are not called.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Consume<R = ()>(pub R);
+
+/// Lets the handlers after this one in a [`chain`](crate::chain) be called,
+/// even though this one replied.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Continue<R = ()>(pub R);
+
 pub struct Error<E: Display>(E);
 
 impl<S> From<S> for Error<S>
@@ -36,15 +62,18 @@ where
 impl Response {
     #[must_use]
     pub fn new(data: impl Into<DataPack>) -> Self {
-        Self {
-            data: SmallVec::from([data.into()]),
-        }
+        Self::from_data(SmallVec::from([data.into()]))
     }
 
     #[must_use]
     pub fn none() -> Self {
+        Self::from_data(SmallVec::new())
+    }
+
+    const fn from_data(data: SmallVec<[DataPack; 1]>) -> Self {
         Self {
-            data: SmallVec::new(),
+            data,
+            flow: Flow::Auto,
         }
     }
 
@@ -53,6 +82,17 @@ impl Response {
         self.data.is_empty()
     }
 
+    /// Whether the handlers after this one in a [`chain`](crate::chain)
+    /// should be skipped.
+    #[must_use]
+    pub fn consumes(&self) -> bool {
+        match self.flow {
+            Flow::Auto => !self.is_none(),
+            Flow::Consume => true,
+            Flow::Continue => false,
+        }
+    }
+
     pub fn correlate(&mut self, id: Ulid) {
         for data in &mut self.data {
             data.correlate(id);
@@ -71,10 +111,10 @@ impl Response {
         }
     }
 
-    pub fn error(error: impl Display) -> Self {
-        Self {
-            data: SmallVec::from([DataPack::builder().build_with_error(error)]),
-        }
+    /// An error response, see [`DataError`]. Plain messages are sent with
+    /// [`ErrorCode::Other`].
+    pub fn error(error: impl Into<DataError>) -> Self {
+        Self::new(DataPack::builder().build_with_error(error))
     }
 }
 
@@ -96,7 +136,7 @@ impl<const N: usize, R: IntoResponse> IntoResponse for [R; N] {
         for response in self {
             result.append(&mut response.into_response().data);
         }
-        Response { data: result }
+        Response::from_data(result)
     }
 }
 
@@ -106,7 +146,7 @@ impl<const N: usize, R: IntoResponse> IntoResponse for SmallVec<[R; N]> {
         for response in self {
             result.append(&mut response.into_response().data);
         }
-        Response { data: result }
+        Response::from_data(result)
     }
 }
 
@@ -116,7 +156,7 @@ impl<R: IntoResponse> IntoResponse for Vec<R> {
         for response in self {
             result.append(&mut response.into_response().data);
         }
-        Response { data: result }
+        Response::from_data(result)
     }
 }
 
@@ -127,17 +167,31 @@ impl IntoResponse for RequestDataPack {
 }
 
 impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        Response::none()
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for Option<R> {
+    fn into_response(self) -> Response {
+        self.map_or_else(Response::none, IntoResponse::into_response)
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for Consume<R> {
     fn into_response(self) -> Response {
         Response {
-            data: SmallVec::new(),
+            flow: Flow::Consume,
+            ..self.0.into_response()
         }
     }
 }
 
-impl<R: IntoResponse> IntoResponse for Option<R> {
+impl<R: IntoResponse> IntoResponse for Continue<R> {
     fn into_response(self) -> Response {
         Response {
-            data: self.map_or_else(SmallVec::new, |v| v.into_response().data),
+            flow: Flow::Continue,
+            ..self.0.into_response()
         }
     }
 }
@@ -150,9 +204,22 @@ impl IntoResponse for Infallible {
 
 impl IntoResponse for DataPack {
     fn into_response(self) -> Response {
-        Response {
-            data: SmallVec::from([self]),
-        }
+        Response::new(self)
+    }
+}
+
+impl IntoResponse for DataError {
+    fn into_response(self) -> Response {
+        Response::error(self)
+    }
+}
+
+impl<S> From<Error<S>> for DataError
+where
+    S: Display,
+{
+    fn from(value: Error<S>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, value.0)
     }
 }
 
@@ -161,14 +228,16 @@ where
     S: Display,
 {
     fn into_response(self) -> Response {
-        Response::error(self.0)
+        Response::error(self)
     }
 }
 
+/// Answers with the error as it is, keeping its code and details. Errors that
+/// only implement [`Display`] can be answered by wrapping them in [`Error`].
 impl<V, E> IntoResponse for Result<V, E>
 where
     V: IntoResponse,
-    E: Display,
+    E: Into<DataError>,
 {
     fn into_response(self) -> Response {
         match self {
@@ -181,11 +250,13 @@ where
 impl<V: Serialize> IntoResponse for Payload<V> {
     fn into_response(self) -> Response {
         let Self(payload) = self;
-        let value = sithra_transport::to_value(payload);
-        let Ok(value) = value else {
-            return Response::error("Failed to serialize payload");
+        let Ok(payload) = RawPayload::encode(&payload) else {
+            return Response::error(DataError::new(
+                ErrorCode::InvalidPayload,
+                "Failed to serialize payload",
+            ));
         };
-        DataPack::builder().build_with_payload(value).into_response()
+        DataPack::builder().result(DataResult::Payload(payload)).build().into_response()
     }
 }
 
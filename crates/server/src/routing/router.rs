@@ -12,6 +12,8 @@ use tower::{Layer, Service};
 use triomphe::Arc;
 
 use crate::{
+    boxed::BoxedIntoRoute,
+    handler::Handler,
     request::Request,
     response::{IntoResponse, Response},
     routing::{
@@ -24,11 +26,17 @@ use crate::{
 #[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RouteId(u32);
 
+/// The parameter capturing the rest of the path for the fallback of a
+/// nested router.
+pub const NEST_FALLBACK_PARAM: &str = "__nest_rest";
+
 #[derive(Debug)]
 pub struct RouterInner<S> {
     routes:        HashMap<RouteId, Endpoint<S>>,
+    paths:         HashMap<RouteId, String>,
     route_router:  RouteRouter<RouteId>,
     prev_route_id: RouteId,
+    fallback:      Option<Endpoint<S>>,
 }
 
 impl<S> Default for RouterInner<S> {
@@ -42,8 +50,10 @@ impl<S> RouterInner<S> {
     pub fn new() -> Self {
         Self {
             routes:        HashMap::new(),
+            paths:         HashMap::new(),
             route_router:  RouteRouter::new(),
             prev_route_id: RouteId(0),
+            fallback:      None,
         }
     }
 }
@@ -111,8 +121,10 @@ where
             Ok(inner) => inner,
             Err(arc) => RouterInner {
                 routes:        arc.routes.clone(),
+                paths:         arc.paths.clone(),
                 route_router:  arc.route_router.clone(),
                 prev_route_id: arc.prev_route_id,
+                fallback:      arc.fallback.clone(),
             },
         }
     }
@@ -131,6 +143,65 @@ where
         self.route(path, method_router)
     }
 
+    /// Adds the routes and the fallback of `other`, which can be a router
+    /// with its own state already given with [`Router::with_state`].
+    ///
+    /// # Panics
+    /// Panics if a route of `other` already exists, or both routers have a
+    /// fallback.
+    #[track_caller]
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        let other = other.into_inner();
+        tap_inner!(self, mut this => {
+            panic_on_err!(this.merge(other));
+        })
+    }
+
+    /// Adds the routes of `router` under `prefix`, e.g. `/mc` and `/status`
+    /// to `/mc/status`. The prefix can contain parameters.
+    ///
+    /// The fallback of `router` answers the requests under `prefix` that
+    /// match none of its routes.
+    ///
+    /// # Panics
+    /// Panics if `prefix` does not start with `/`, ends with `/`, or a route
+    /// of `router` already exists under it.
+    #[track_caller]
+    #[must_use]
+    pub fn nest(self, prefix: &str, router: Self) -> Self {
+        let router = router.into_inner();
+        tap_inner!(self, mut this => {
+            panic_on_err!(this.nest(prefix, router));
+        })
+    }
+
+    /// Sets the handler called for requests that match no route, instead of
+    /// leaving them unanswered.
+    #[must_use]
+    pub fn fallback<H, T>(self, handler: H) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        tap_inner!(self, mut this => {
+            this.fallback = Some(Endpoint::BoxedHandler(BoxedIntoRoute::from_handler(handler)));
+        })
+    }
+
+    /// Like [`Router::fallback`], but with a service.
+    #[must_use]
+    pub fn fallback_service<T>(self, service: T) -> Self
+    where
+        T: Service<Request, Error = Infallible> + Clone + Send + Sync + 'static,
+        T::Response: IntoResponse,
+        T::Future: Send + 'static,
+    {
+        tap_inner!(self, mut this => {
+            this.fallback = Some(Endpoint::Route(Route::new(service)));
+        })
+    }
+
     /// # Panics
     /// Panics if the service is a `Router`.
     #[must_use]
@@ -213,7 +284,62 @@ where
     fn set_node(&mut self, path: &str, id: RouteId) -> Result<(), String> {
         self.route_router
             .insert(path, id)
-            .map_err(|err| format!("Invalid route {path:?}: {err}"))
+            .map_err(|err| format!("Invalid route {path:?}: {err}"))?;
+        self.paths.insert(id, path.to_owned());
+        Ok(())
+    }
+
+    /// The routes by path, and the fallback.
+    fn into_parts(self) -> (Vec<(String, Endpoint<S>)>, Option<Endpoint<S>>) {
+        let Self {
+            mut routes,
+            paths,
+            fallback,
+            ..
+        } = self;
+        let routes = paths
+            .into_iter()
+            .filter_map(|(id, path)| Some((path, routes.remove(&id)?)))
+            .collect();
+        (routes, fallback)
+    }
+
+    /// # Errors
+    /// Returns an error if a route of `other` already exists, or both have a
+    /// fallback.
+    pub fn merge(&mut self, other: Self) -> Result<(), Cow<'static, str>> {
+        let (routes, fallback) = other.into_parts();
+        for (path, endpoint) in routes {
+            self.route_endpoint(&path, endpoint)?;
+        }
+        match (&self.fallback, fallback) {
+            (Some(_), Some(_)) => {
+                return Err("Cannot merge two routers that both have a fallback".into());
+            }
+            (None, fallback) => self.fallback = fallback,
+            (Some(_), None) => {}
+        }
+        Ok(())
+    }
+
+    /// # Errors
+    /// Returns an error if `prefix` is not a path or a route of `other`
+    /// already exists under it.
+    pub fn nest(&mut self, prefix: &str, other: Self) -> Result<(), Cow<'static, str>> {
+        if !prefix.starts_with('/') || prefix.ends_with('/') {
+            return Err(format!(
+                "Invalid nesting prefix {prefix:?}: it must start and must not end with `/`"
+            )
+            .into());
+        }
+        let (routes, fallback) = other.into_parts();
+        for (path, endpoint) in routes {
+            self.route_endpoint(&format!("{prefix}{path}"), endpoint)?;
+        }
+        if let Some(fallback) = fallback {
+            self.route_endpoint(&format!("{prefix}/{{*{NEST_FALLBACK_PARAM}}}"), fallback)?;
+        }
+        Ok(())
     }
 
     /// # Errors
@@ -269,11 +395,16 @@ where
 
         Self {
             routes,
+            paths: self.paths,
             route_router: self.route_router,
             prev_route_id: self.prev_route_id,
+            fallback: self.fallback.map(|fallback| fallback.layer(layer)),
         }
     }
 
+    /// Unlike [`RouterInner::layer`], the layer is not applied to the
+    /// fallback.
+    ///
     /// # Panics
     /// Panics if no routes have been added yet.
     #[track_caller]
@@ -303,8 +434,10 @@ where
 
         Self {
             routes,
+            paths: self.paths,
             route_router: self.route_router,
             prev_route_id: self.prev_route_id,
+            fallback: self.fallback,
         }
     }
 
@@ -329,8 +462,13 @@ where
 
         RouterInner {
             routes,
+            paths: self.paths,
             route_router: self.route_router,
             prev_route_id: self.prev_route_id,
+            fallback: self.fallback.map(|fallback| match fallback {
+                Endpoint::BoxedHandler(handler) => Endpoint::Route(handler.into_route(state)),
+                Endpoint::Route(route) => Endpoint::Route(route),
+            }),
         }
     }
 
@@ -340,27 +478,29 @@ where
         req: Request,
         state: S,
     ) -> Result<RouteFuture<Infallible>, (Request, S)> {
-        let raw = req.into_raw();
-        match self.route_router.at(&raw.path) {
+        let (endpoint, req) = match self.route_router.at(&req.path) {
             Ok(match_) => {
-                let id = *match_.value;
-
                 let endpoint = self
                     .routes
-                    .get(&id)
+                    .get(match_.value)
                     .expect("no route for id. This is a bug in sithra. Please file an issue");
-
-                let req = Request::from_raw(raw);
-                match endpoint {
-                    Endpoint::BoxedHandler(handler) => {
-                        let route = handler.clone().into_route(state);
-                        Ok(route.oneshot_inner_owned(req))
-                    }
-                    Endpoint::Route(route) => Ok(route.clone().call_owned(req)),
-                }
+                // The rest captured by a nested fallback is routing detail, not a parameter the
+                // fallback asked for.
+                let params =
+                    match_.params.iter().filter(|(key, _)| *key != NEST_FALLBACK_PARAM).collect();
+                (endpoint, req.with_params(params))
             }
-
-            Err(MatchError::NotFound) => Err((Request::from_raw(raw), state)),
+            Err(MatchError::NotFound) => match &self.fallback {
+                Some(fallback) => (fallback, req),
+                None => return Err((req, state)),
+            },
+        };
+        match endpoint {
+            Endpoint::BoxedHandler(handler) => {
+                let route = handler.clone().into_route(state);
+                Ok(route.oneshot_inner_owned(req))
+            }
+            Endpoint::Route(route) => Ok(route.clone().call_owned(req)),
         }
     }
 
@@ -6,31 +6,42 @@
 //!
 //! The `Client` provides a simple way to send requests to the `Server` and
 //! receive responses.
+//!
+//! The tasks of a `Server` talk through queues, which can be bounded with
+//! [`ServerQueues`]; [`Server::monitor`] reports how full they are.
 
-use std::convert::Infallible;
+use std::{collections::HashMap, convert::Infallible, time::Duration};
 
 use either::Either;
-use futures_util::{FutureExt, SinkExt, StreamExt, future::Map};
+use futures_util::{FutureExt, SinkExt, StreamExt};
 use sithra_transport::{
+    compression::Compression,
     datapack::{DataPack, DataPackCodec, DataPackCodecError, RequestDataPack},
+    error::{DataError, ErrorCode},
     peer::{Reader, Writer},
+    trace::TraceContext,
 };
 use thiserror::Error;
 use tokio::{
     sync::{
-        mpsc::{UnboundedReceiver, UnboundedSender, error::SendError},
+        mpsc::{self, error::SendError},
         oneshot,
     },
-    task::JoinSet,
+    task::{AbortHandle, JoinSet},
 };
 use tokio_util::codec::{FramedRead, FramedWrite};
 use tower::Service;
 use ulid::Ulid;
 
 use crate::{
+    queue::{
+        OverflowPolicy, QueueError, QueueProbe, QueueReceiver, QueueSender, QueueStats,
+        ServerQueues, queue,
+    },
     request::Request,
     response::Response,
-    shared::{ReceiverGuard, SharedOneshotMap},
+    shared::SharedOneshotMap,
+    trace,
     traits::TypedRequest,
 };
 
@@ -47,13 +58,14 @@ use crate::{
 /// implementation.
 pub struct Server<S = ()> {
     service:            S,
-    writer_rx:          UnboundedReceiver<DataPack>,
-    writer_tx:          UnboundedSender<DataPack>,
-    request_rx:         UnboundedReceiver<Request>,
-    request_tx:         UnboundedSender<Request>,
-    response_rx:        UnboundedReceiver<DataPack>,
-    response_tx:        UnboundedSender<DataPack>,
+    writer_rx:          QueueReceiver<DataPack>,
+    writer_tx:          QueueSender<DataPack>,
+    request_rx:         QueueReceiver<Request>,
+    request_tx:         QueueSender<Request>,
+    response_rx:        QueueReceiver<DataPack>,
+    response_tx:        QueueSender<DataPack>,
     shared_oneshot_map: SharedOneshotMap<Ulid, DataPack>,
+    compression:        Option<Compression>,
 }
 
 /// A client for communicating with a `Server`.
@@ -62,12 +74,56 @@ pub struct Server<S = ()> {
 /// instance. It allows sending `RequestDataPack`s to the server and receiving
 /// responses asynchronously.
 pub struct Client {
-    writer_tx:          UnboundedSender<DataPack>,
+    writer_tx:          QueueSender<DataPack>,
     shared_oneshot_map: SharedOneshotMap<Ulid, DataPack>,
 }
 
 pub struct ClientSink {
-    writer_tx: UnboundedSender<DataPack>,
+    writer_tx: QueueSender<DataPack>,
+}
+
+/// Reports how full the queues of a [`Server`] are.
+///
+/// A `QueueMonitor` is obtained by calling [`Server::monitor`]. It does not
+/// keep the server alive.
+#[derive(Clone)]
+pub struct QueueMonitor {
+    writer:   QueueProbe<DataPack>,
+    request:  QueueProbe<Request>,
+    response: QueueProbe<DataPack>,
+}
+
+/// A snapshot of the queues of a [`Server`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServerQueueStats {
+    pub writer:   QueueStats,
+    pub request:  QueueStats,
+    pub response: QueueStats,
+}
+
+impl QueueMonitor {
+    #[must_use]
+    pub fn stats(&self) -> ServerQueueStats {
+        ServerQueueStats {
+            writer:   self.writer.stats(),
+            request:  self.request.stats(),
+            response: self.response.stats(),
+        }
+    }
+
+    /// Whether the connection of the server is gone.
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.request.is_closed()
+    }
+}
+
+impl ServerQueueStats {
+    /// The queues with their names, for reporting.
+    #[must_use]
+    pub const fn named(&self) -> [(&'static str, QueueStats); 3] {
+        [("writer", self.writer), ("request", self.request), ("response", self.response)]
+    }
 }
 
 impl Clone for Client {
@@ -90,11 +146,25 @@ impl Server<()> {
     ///
     /// The initial server is created without a service. The `service` method
     /// must be called to provide a `tower::Service` that will handle requests.
+    /// Its queues are unbounded.
     #[must_use]
     pub fn new() -> Self {
-        let (writer_tx, writer_rx) = tokio::sync::mpsc::unbounded_channel();
-        let (request_tx, request_rx) = tokio::sync::mpsc::unbounded_channel();
-        let (response_tx, response_rx) = tokio::sync::mpsc::unbounded_channel();
+        Self::with_queues(ServerQueues::default())
+    }
+
+    /// Creates a new `Server` whose queues are bounded by `queues`.
+    ///
+    /// When the writer queue is full, [`Client::post`] waits for room under
+    /// [`OverflowPolicy::Block`], while [`Client::send`] and
+    /// [`ClientSink::send`], which cannot wait, fail with
+    /// [`PostError::QueueFull`]. Incoming requests that are rejected or
+    /// dropped from a full request queue are answered with an error, so the
+    /// peer does not wait for them.
+    #[must_use]
+    pub fn with_queues(queues: ServerQueues) -> Self {
+        let (writer_tx, writer_rx) = queue(queues.writer);
+        let (request_tx, request_rx) = queue(queues.request);
+        let (response_tx, response_rx) = queue(queues.response);
 
         Self {
             service: (),
@@ -105,6 +175,7 @@ impl Server<()> {
             response_rx,
             response_tx,
             shared_oneshot_map: SharedOneshotMap::new(),
+            compression: None,
         }
     }
 }
@@ -137,6 +208,7 @@ impl<S> Server<S> {
             response_rx,
             response_tx,
             shared_oneshot_map,
+            compression,
         } = self;
         Server {
             service: svc,
@@ -147,9 +219,20 @@ impl<S> Server<S> {
             response_rx,
             response_tx,
             shared_oneshot_map,
+            compression,
         }
     }
 
+    /// Sets the compression applied to outgoing frames.
+    ///
+    /// Incoming frames are decompressed regardless of this setting, so it
+    /// only needs to match what the peer agreed to decode.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
     /// Creates a new `Client` connected to this server.
     ///
     /// The returned `Client` can be used to send requests to the server.
@@ -161,6 +244,17 @@ impl<S> Server<S> {
             shared_oneshot_map: self.shared_oneshot_map.clone(),
         }
     }
+
+    /// Creates a [`QueueMonitor`] reporting the depths of the server's
+    /// queues.
+    #[must_use]
+    pub fn monitor(&self) -> QueueMonitor {
+        QueueMonitor {
+            writer:   self.writer_tx.probe(),
+            request:  self.request_tx.probe(),
+            response: self.response_tx.probe(),
+        }
+    }
 }
 
 impl<S> Server<S>
@@ -177,7 +271,11 @@ where
     /// 3. Reading data from the `Reader` and dispatching it as requests or
     ///    responses.
     /// 4. Processing requests with the `tower::Service` and sending back
-    ///    responses.
+    ///    responses. Every request is handled in its own task, which is aborted
+    ///    when a cancellation for its correlation arrives, and runs inside the
+    ///    request's trace context (see [`crate::trace`]). At most as many
+    ///    handlers as the request queue holds run at once, further requests
+    ///    wait in the queue.
     ///
     /// # Arguments
     ///
@@ -199,9 +297,14 @@ where
             response_rx,
             response_tx,
             shared_oneshot_map,
+            compression,
         } = self;
-        let framed_writer = FramedWrite::new(writer, DataPackCodec::default());
+        let framed_writer = FramedWrite::new(
+            writer,
+            DataPackCodec::default().with_compression(compression),
+        );
         let framed_reader = FramedRead::new(reader, DataPackCodec::default());
+        let overflow_map = shared_oneshot_map.clone();
         let mut join_set = JoinSet::new();
         join_set.spawn(async move {
             let mut response_rx = response_rx;
@@ -220,6 +323,8 @@ where
             }
             Ok(())
         });
+        let overload_tx = writer_tx.clone();
+        let (cancel_tx, cancel_rx) = mpsc::unbounded_channel();
         join_set.spawn(async move {
             let mut framed_reader = framed_reader;
             let request_tx = request_tx;
@@ -228,38 +333,152 @@ where
                 let data = data?;
                 match data.either_request() {
                     Either::Left(response) => {
-                        response_tx.send(response)?;
+                        let overflowed = match response_tx.send(response).await {
+                            Ok(evicted) => evicted,
+                            Err(QueueError::Full(response)) => Some(response),
+                            Err(QueueError::Closed(_)) => return Err(ServerError::SendError),
+                        };
+                        if let Some(response) = overflowed {
+                            fail_overflowed(&overflow_map, &response);
+                        }
+                    }
+                    Either::Right(request_datapack) if request_datapack.is_cancel() => {
+                        // Cancellations bypass the request queue, so they are
+                        // never dropped and are handled even when every handler
+                        // is busy.
+                        cancel_tx.send(request_datapack.correlation()).ok();
                     }
                     Either::Right(request_datapack) => {
                         let request = Request::new(request_datapack);
-                        request_tx.send(request)?;
+                        let overflowed = match request_tx.send(request).await {
+                            Ok(evicted) => evicted,
+                            Err(QueueError::Full(request)) => Some(request),
+                            Err(QueueError::Closed(_)) => return Err(ServerError::SendError),
+                        };
+                        if let Some(request) = overflowed {
+                            reject_overflowed(&overload_tx, &request);
+                        }
                     }
                 }
             }
             Ok(())
         });
-        join_set.spawn(async move {
-            let writer_tx = writer_tx;
-            let mut request_rx = request_rx;
-            let mut service = service;
-            while let Some(request) = request_rx.recv().await {
-                let response = service.call(request).await?;
-                for response_datapack in response.data {
-                    writer_tx.send(response_datapack)?;
+        join_set.spawn(handle_requests(service, request_rx, cancel_rx, writer_tx));
+        join_set
+    }
+}
+
+/// Fails the `post` waiting for a response that did not fit into the
+/// response queue, so it does not wait forever.
+fn fail_overflowed(shared_oneshot_map: &SharedOneshotMap<Ulid, DataPack>, response: &DataPack) {
+    let key = response.correlation();
+    shared_oneshot_map.complete(
+        &key,
+        DataPack::builder()
+            .correlate(key)
+            .build_with_error(DataError::new(ErrorCode::Busy, "Response queue is full")),
+    );
+}
+
+/// Answers a request that did not fit into the request queue.
+fn reject_overflowed(writer_tx: &QueueSender<DataPack>, request: &Request) {
+    let raw = request.raw();
+    let mut response = DataPack::builder().correlate(raw.correlation());
+    if let Some(trace) = raw.trace {
+        response = response.trace(trace);
+    }
+    writer_tx
+        .try_send(
+            response.build_with_error(DataError::new(ErrorCode::Busy, "Request queue is full")),
+        )
+        .ok();
+}
+
+/// Handles every request in its own task and aborts the task when a
+/// cancellation for its correlation arrives.
+///
+/// No more handlers run at once than the request queue holds, so that a
+/// flood of requests fills the queue and meets its overflow policy.
+async fn handle_requests<S>(
+    mut service: S,
+    mut request_rx: QueueReceiver<Request>,
+    mut cancel_rx: mpsc::UnboundedReceiver<Ulid>,
+    writer_tx: QueueSender<DataPack>,
+) -> Result<(), ServerError>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let max_handlers = request_rx.stats().capacity.unwrap_or(usize::MAX);
+    let mut handlers = JoinSet::new();
+    let mut running = HashMap::<Ulid, AbortHandle>::new();
+    loop {
+        tokio::select! {
+            Some(correlation) = cancel_rx.recv() => {
+                if let Some(handler) = running.remove(&correlation) {
+                    handler.abort();
+                } else {
+                    request_rx.remove_first(|request| request.correlation() == correlation);
                 }
             }
-            Ok(())
-        });
-        join_set
+            request = request_rx.recv(), if handlers.len() < max_handlers => {
+                let Some(request) = request else {
+                    break;
+                };
+                let correlation = request.correlation();
+                if request.raw().is_expired() {
+                    continue;
+                }
+                let trace = request.raw().trace.unwrap_or_else(TraceContext::root);
+                let future = service.call(request);
+                let writer_tx = writer_tx.clone();
+                let handler = handlers.spawn(trace::scope(trace, async move {
+                    let response = future.await?;
+                    for mut response_datapack in response.data {
+                        response_datapack.trace.get_or_insert(trace);
+                        if let Err(QueueError::Closed(_)) = writer_tx.send(response_datapack).await {
+                            return Err(ServerError::SendError);
+                        }
+                    }
+                    Ok::<_, ServerError>(correlation)
+                }));
+                running.insert(correlation, handler);
+            }
+            Some(result) = handlers.join_next(), if !handlers.is_empty() => {
+                match result {
+                    Ok(correlation) => {
+                        running.remove(&correlation?);
+                    }
+                    Err(err) => {
+                        running.retain(|_, handler| handler.id() != err.id());
+                    }
+                }
+            }
+        }
     }
+    while let Some(result) = handlers.join_next().await {
+        if let Ok(result) = result {
+            result?;
+        }
+    }
+    Ok(())
 }
 
 impl Client {
     /// Sends a request to the server and returns a future for the response.
     ///
     /// This method sends a `RequestDataPack` to the server and returns a
-    /// `ReceiverGuard`. The `ReceiverGuard` is a future that resolves to
-    /// the `DataPack` response from the server.
+    /// future that resolves to the `DataPack` response from the server.
+    ///
+    /// If the writer queue is full and its policy is
+    /// [`OverflowPolicy::Block`], the request is sent once the future is
+    /// polled and there is room again.
+    ///
+    /// Dropping the future before the response arrived sends a cancellation
+    /// for the request, so the peer can stop working on it.
+    ///
+    /// A response that does not fit into the response queue is replaced by an
+    /// [`ErrorCode::Busy`] error.
     ///
     /// # Arguments
     ///
@@ -268,33 +487,94 @@ impl Client {
     ///
     /// # Errors
     ///
-    /// Returns an `Err(DataPack)` if the connection to the server is closed
-    /// before the request can be sent. The `DataPack` inside the `Err` is
-    /// the original request that failed to be sent.
+    /// Returns [`PostError::ChannelClosed`] with the original request if the
+    /// connection to the server is closed before the request can be sent,
+    /// and [`PostError::QueueFull`] if the writer queue is full and its
+    /// policy is [`OverflowPolicy::Reject`].
     ///
     /// # Panics
     ///
     /// This method panics if there is a `Ulid` conflict for the request's
     /// correlation ID. This is extremely unlikely to happen in practice.
     #[allow(clippy::result_large_err)]
-    pub fn post(
+    pub fn post<D: Into<RequestDataPack>>(
         &self,
-        datapack: impl Into<RequestDataPack>,
-    ) -> Result<ReceiverGuard<Ulid, DataPack>, PostError> {
-        let datapack = datapack.into();
+        datapack: D,
+    ) -> Result<impl Future<Output = Result<DataPack, PostError>> + Send + Sync + use<D>, PostError>
+    {
+        let mut datapack = datapack.into();
+        datapack.trace.get_or_insert_with(trace::next);
         let key = datapack.correlation();
-        let guard = self.shared_oneshot_map.register(key).expect("Ulid Conflict");
-        self.writer_tx
-            .send(datapack.into())
-            .map_err(|err| PostError::ChannelClosed(err.0))?;
-        Ok(guard)
+        let writer_tx = self.writer_tx.clone();
+        let mut guard = self
+            .shared_oneshot_map
+            .register_with_cancel(key, move |key| {
+                writer_tx.force_send(RequestDataPack::cancel(*key).into()).ok();
+            })
+            .expect("Ulid Conflict");
+        let pending = match self.writer_tx.start_send(datapack.into()) {
+            Ok(_) => None,
+            Err(QueueError::Full(datapack))
+                if self.writer_tx.config().overflow == OverflowPolicy::Block =>
+            {
+                Some(datapack)
+            }
+            Err(err) => {
+                guard.disarm();
+                return Err(err.into());
+            }
+        };
+        let writer_tx = self.writer_tx.clone();
+        Ok(async move {
+            if let Some(datapack) = pending
+                && let Err(err) = writer_tx.send(datapack).await
+            {
+                guard.disarm();
+                return Err(err.into());
+            }
+            Ok(guard.await?)
+        })
+    }
+
+    /// Sends a request to the server and returns a future for the response
+    /// that gives up after `timeout`.
+    ///
+    /// The deadline is sent along with the request, so the peer can drop it
+    /// instead of handling it once nobody waits for the response anymore.
+    /// Giving up cancels the request like dropping the future of
+    /// [`Client::post`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection to the server is closed before the
+    /// request can be sent. The returned future resolves to
+    /// [`PostError::Timeout`] if no response arrived in time.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if there is a `Ulid` conflict for the request's
+    /// correlation ID. This is extremely unlikely to happen in practice.
+    #[allow(clippy::result_large_err)]
+    pub fn post_with_timeout<D: Into<RequestDataPack>>(
+        &self,
+        datapack: D,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<DataPack, PostError>> + Send + use<D>, PostError> {
+        let datapack = datapack.into().timeout(timeout);
+        let remaining = datapack.remaining().unwrap_or(timeout);
+        let guard = self.post(datapack)?;
+        Ok(async move {
+            match tokio::time::timeout(remaining, guard).await {
+                Ok(response) => Ok(response?),
+                Err(_) => Err(PostError::Timeout),
+            }
+        })
     }
 
     /// Sends a request to the server and returns a future for the response.
     ///
-    /// This method sends a `RequestDataPack` to the server and returns a
-    /// `ReceiverGuard`. The `ReceiverGuard` is a future that resolves to
-    /// the `DataPack` response from the server.
+    /// Like [`Client::post`], but the future resolves to the decoded
+    /// response payload.
     ///
     /// # Arguments
     ///
@@ -303,9 +583,7 @@ impl Client {
     ///
     /// # Errors
     ///
-    /// Returns an `Err(DataPack)` if the connection to the server is closed
-    /// before the request can be sent. The `DataPack` inside the `Err` is
-    /// the original request that failed to be sent.
+    /// Returns the same errors as [`Client::post`].
     ///
     /// # Panics
     ///
@@ -316,18 +594,13 @@ impl Client {
         &self,
         datapack: T,
     ) -> Result<
-        Map<
-            ReceiverGuard<Ulid, DataPack>,
-            impl FnOnce(
-                Result<DataPack, oneshot::error::RecvError>,
-            ) -> Result<<T as TypedRequest>::Response, PostError>,
-        >,
+        impl Future<Output = Result<<T as TypedRequest>::Response, PostError>> + Send + Sync + use<T>,
         PostError,
     > {
         let result = self.post(datapack);
         result.map(|fut| {
             fut.map(|rs| match rs {
-                Err(err) => Err(err.into()),
+                Err(err) => Err(err),
                 Ok(dp) => Ok(dp.payload::<T::Response>()?),
             })
         })
@@ -344,7 +617,9 @@ impl Client {
     ///
     /// Returns an `Err(DataPack)` if the connection to the server is closed
     /// before the request can be sent. The `DataPack` inside the `Err` is
-    /// the original request that failed to be sent.
+    /// the original request that failed to be sent. Returns
+    /// [`PostError::QueueFull`] if the writer queue is full, unless its policy
+    /// is [`OverflowPolicy::DropOldest`]; this method never waits for room.
     ///
     /// # Panics
     ///
@@ -352,10 +627,9 @@ impl Client {
     /// correlation ID. This is extremely unlikely to happen in practice.
     #[allow(clippy::result_large_err)]
     pub fn send(&self, datapack: impl Into<RequestDataPack>) -> Result<(), PostError> {
-        let datapack = datapack.into();
-        self.writer_tx
-            .send(datapack.into())
-            .map_err(|err| PostError::ChannelClosed(err.0))?;
+        let mut datapack = datapack.into();
+        datapack.trace.get_or_insert_with(trace::next);
+        self.writer_tx.try_send(datapack.into())?;
         Ok(())
     }
 
@@ -379,7 +653,9 @@ impl ClientSink {
     ///
     /// Returns an `Err(DataPack)` if the connection to the server is closed
     /// before the request can be sent. The `DataPack` inside the `Err` is
-    /// the original request that failed to be sent.
+    /// the original request that failed to be sent. Returns
+    /// [`PostError::QueueFull`] if the writer queue is full, unless its policy
+    /// is [`OverflowPolicy::DropOldest`]; this method never waits for room.
     ///
     /// # Panics
     ///
@@ -388,24 +664,95 @@ impl ClientSink {
     #[allow(clippy::result_large_err)]
     pub fn send(&self, datapack: impl Into<DataPack>) -> Result<(), PostError> {
         let datapack = datapack.into();
-        self.writer_tx.send(datapack).map_err(|err| PostError::ChannelClosed(err.0))?;
+        self.writer_tx.try_send(datapack)?;
         Ok(())
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Error)]
 pub enum PostError {
     #[error("Channel closed")]
     ChannelClosed(DataPack),
     #[error("Recv error: {0}")]
     RecvError(#[from] oneshot::error::RecvError),
+    /// The peer does not implement the request.
+    #[error("Not supported: {0}")]
+    NotSupported(DataError),
+    /// The sender of the request is not allowed to make it.
+    #[error("Permission denied: {0}")]
+    PermissionDenied(DataError),
+    #[error("Rate limited: {0}")]
+    RateLimited(DataError),
+    /// The peer the request is meant for is not connected.
+    #[error("Peer offline: {0}")]
+    PeerOffline(DataError),
+    /// Any other error the peer answered with, or a response that could not
+    /// be decoded.
     #[error("Request error: {0}")]
-    RequestError(String),
+    RequestError(DataError),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Queue full")]
+    QueueFull(DataPack),
+}
+
+impl PostError {
+    /// The error the peer answered with, if it answered with one.
+    #[must_use]
+    pub const fn error(&self) -> Option<&DataError> {
+        match self {
+            Self::NotSupported(error)
+            | Self::PermissionDenied(error)
+            | Self::RateLimited(error)
+            | Self::PeerOffline(error)
+            | Self::RequestError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<DataError> for PostError {
+    fn from(value: DataError) -> Self {
+        match value.code {
+            ErrorCode::NotSupported => Self::NotSupported(value),
+            ErrorCode::PermissionDenied => Self::PermissionDenied(value),
+            ErrorCode::RateLimited => Self::RateLimited(value),
+            ErrorCode::PeerOffline => Self::PeerOffline(value),
+            _ => Self::RequestError(value),
+        }
+    }
+}
+
+impl From<PostError> for DataError {
+    /// Keeps the error the peer answered with, so that handlers forward it.
+    fn from(value: PostError) -> Self {
+        let code = match value {
+            PostError::NotSupported(error)
+            | PostError::PermissionDenied(error)
+            | PostError::RateLimited(error)
+            | PostError::PeerOffline(error)
+            | PostError::RequestError(error) => return error,
+            PostError::Timeout => ErrorCode::Timeout,
+            PostError::QueueFull(_) => ErrorCode::Busy,
+            PostError::ChannelClosed(_) | PostError::RecvError(_) => ErrorCode::Internal,
+        };
+        Self::new(code, value)
+    }
+}
+
+impl From<QueueError<DataPack>> for PostError {
+    fn from(value: QueueError<DataPack>) -> Self {
+        match value {
+            QueueError::Full(datapack) => Self::QueueFull(datapack),
+            QueueError::Closed(datapack) => Self::ChannelClosed(datapack),
+        }
+    }
 }
 
 impl From<String> for PostError {
     fn from(value: String) -> Self {
-        Self::RequestError(value)
+        Self::RequestError(value.into())
     }
 }
 
@@ -1,23 +1,28 @@
-use std::task::{Context, Poll};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use futures_util::future::{JoinAll, join_all};
 use pin_project::pin_project;
-use tower::Service;
+use tower::{Service, ServiceExt};
 
 use crate::response::{IntoResponse, Response};
 
 #[derive(Clone, Debug)]
-pub struct JoinAllService<S, const N: usize> {
-    inner: [S; N],
+pub struct JoinAllService<S> {
+    inner: Vec<S>,
 }
 
-impl<S, const N: usize> JoinAllService<S, N> {
-    pub const fn new(inner: [S; N]) -> Self {
-        Self { inner }
+impl<S> JoinAllService<S> {
+    pub fn new(inner: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            inner: inner.into_iter().collect(),
+        }
     }
 }
 
-impl<S, const N: usize, Request, Error, Fut> Service<Request> for JoinAllService<S, N>
+impl<S, Request, Error, Fut> Service<Request> for JoinAllService<S>
 where
     Request: Clone,
     Error: Send + 'static,
@@ -40,8 +45,7 @@ where
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
-        let futures: [_; N] = std::array::from_fn(|i| self.inner[i].call(req.clone()));
-        JoinAllServiceFuture::new(futures)
+        JoinAllServiceFuture::new(self.inner.iter_mut().map(|service| service.call(req.clone())))
     }
 }
 
@@ -93,3 +97,55 @@ where
         })
     }
 }
+
+/// A service that calls its inner services one after another, in order,
+/// until one of them consumes the request. See
+/// [`Flow`](crate::response::Flow).
+///
+/// The responses of all called services are returned together.
+#[derive(Clone, Debug)]
+pub struct ChainService<S> {
+    inner: Vec<S>,
+}
+
+impl<S> ChainService<S> {
+    pub fn new(inner: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            inner: inner.into_iter().collect(),
+        }
+    }
+}
+
+impl<S, Request, Error> Service<Request> for ChainService<S>
+where
+    Request: Clone + Send + 'static,
+    Error: Send + 'static,
+    S: Service<Request, Response = Response, Error = Error> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Every service is driven to readiness with `oneshot` when its turn
+        // comes.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let services = self.inner.clone();
+        Box::pin(async move {
+            let mut data = Vec::new();
+            for service in services {
+                let response = service.oneshot(req.clone()).await?;
+                let consumed = response.consumes();
+                data.extend(response.data);
+                if consumed {
+                    break;
+                }
+            }
+            Ok(data.into_response())
+        })
+    }
+}
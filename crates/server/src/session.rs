@@ -0,0 +1,178 @@
+//! Sessions: handlers waiting for the next request from the same channel.
+//!
+//! A handler calls [`Sessions::wait`] to be handed the next request of a path
+//! sent by the bot and channel it is talking to. [`Sessions`] is also a layer:
+//! applied to a router, it routes such requests to the waiting handler instead
+//! of the normal ones.
+//!
+//! ```ignore
+//! let sessions = Sessions::new();
+//! let router = router.layer(sessions.clone()).with_state(AppState { sessions, .. });
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::future::{Either, Ready, ready};
+use parking_lot::Mutex;
+use sithra_transport::channel::Channel;
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+use triomphe::Arc;
+
+use crate::{request::Request, response::Response};
+
+/// The bot, path and channel a handler waits on.
+type Key = (Option<String>, String, Channel);
+
+/// The handlers waiting for requests, in the order they started waiting.
+#[derive(Clone, Default)]
+pub struct Sessions {
+    waiters: Arc<Mutex<HashMap<Key, VecDeque<oneshot::Sender<Request>>>>>,
+}
+
+impl Sessions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts waiting for the next request to `path` from `channel` of the
+    /// bot `bot_id`.
+    ///
+    /// Requests are routed to the [`Waiter`] from now on, even before it is
+    /// awaited, so nothing sent in between is missed.
+    #[must_use]
+    pub fn wait(&self, bot_id: Option<String>, path: &str, channel: Channel) -> Waiter {
+        let (tx, rx) = oneshot::channel();
+        let key = (bot_id, path.to_owned(), channel);
+        self.waiters.lock().entry(key.clone()).or_default().push_back(tx);
+        Waiter {
+            sessions: self.clone(),
+            key,
+            rx,
+        }
+    }
+
+    /// Hands `request` to the first handler waiting for it.
+    ///
+    /// # Errors
+    /// Returns the request back if no handler waits for it.
+    pub fn deliver(&self, mut request: Request) -> Result<(), Request> {
+        let Some(channel) = request.channel() else {
+            return Err(request);
+        };
+        let key = (request.bot_id(), request.path.clone(), channel);
+        let mut waiters = self.waiters.lock();
+        let Some(queue) = waiters.get_mut(&key) else {
+            return Err(request);
+        };
+        let result = loop {
+            let Some(tx) = queue.pop_front() else {
+                break Err(request);
+            };
+            match tx.send(request) {
+                Ok(()) => break Ok(()),
+                Err(returned) => request = returned,
+            }
+        };
+        if queue.is_empty() {
+            waiters.remove(&key);
+        }
+        drop(waiters);
+        result
+    }
+
+    /// Returns `true` if no handler waits for a request.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.waiters.lock().is_empty()
+    }
+
+    fn prune(&self, key: &Key) {
+        let mut waiters = self.waiters.lock();
+        if let Some(queue) = waiters.get_mut(key) {
+            queue.retain(|tx| !tx.is_closed());
+            if queue.is_empty() {
+                waiters.remove(key);
+            }
+        }
+    }
+}
+
+/// A handler waiting for a request, see [`Sessions::wait`].
+///
+/// Dropping it stops waiting.
+pub struct Waiter {
+    sessions: Sessions,
+    key:      Key,
+    rx:       oneshot::Receiver<Request>,
+}
+
+impl Waiter {
+    /// Waits for the request, `None` if none arrived within `timeout`.
+    pub async fn recv(mut self, timeout: Duration) -> Option<Request> {
+        tokio::time::timeout(timeout, &mut self.rx).await.ok()?.ok()
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        self.rx.close();
+        self.sessions.prune(&self.key);
+    }
+}
+
+/// A state holding the [`Sessions`] of a router.
+pub trait Sessionful {
+    fn sessions(&self) -> &Sessions;
+}
+
+impl Sessionful for Sessions {
+    fn sessions(&self) -> &Sessions {
+        self
+    }
+}
+
+impl<S> Layer<S> for Sessions {
+    type Service = SessionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SessionService {
+            sessions: self.clone(),
+            inner,
+        }
+    }
+}
+
+/// Routes requests a handler waits for to it, and all others to the inner
+/// service.
+#[derive(Clone)]
+pub struct SessionService<S> {
+    sessions: Sessions,
+    inner:    S,
+}
+
+impl<S> Service<Request> for SessionService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+{
+    type Error = Infallible;
+    type Future = Either<Ready<Result<Response, Infallible>>, S::Future>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        match self.sessions.deliver(req) {
+            Ok(()) => Either::Left(ready(Ok(Response::none()))),
+            Err(req) => Either::Right(self.inner.call(req)),
+        }
+    }
+}
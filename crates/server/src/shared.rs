@@ -13,6 +13,7 @@ use parking_lot::Mutex;
 use tokio::sync::oneshot;
 
 type OneshotMapInner<K, V> = Mutex<HashMap<K, Entry<V>, RandomState>>;
+type CancelHook<K> = Box<dyn FnOnce(&K) + Send + Sync>;
 
 pub struct SharedOneshotMap<K, V>
 where
@@ -53,6 +54,20 @@ where
     }
 
     pub fn register(&self, key: K) -> Option<ReceiverGuard<K, V>> {
+        Some(self.register_inner(key, None))
+    }
+
+    /// Like [`Self::register`], but `on_cancel` runs if the returned guard is
+    /// dropped before a value was completed for `key`.
+    pub fn register_with_cancel(
+        &self,
+        key: K,
+        on_cancel: impl FnOnce(&K) + Send + Sync + 'static,
+    ) -> Option<ReceiverGuard<K, V>> {
+        Some(self.register_inner(key, Some(Box::new(on_cancel))))
+    }
+
+    fn register_inner(&self, key: K, on_cancel: Option<CancelHook<K>>) -> ReceiverGuard<K, V> {
         let (tx, rx) = oneshot::channel();
         let entry = Entry {
             tx,
@@ -62,11 +77,12 @@ where
             let mut map = self.inner.lock();
             map.insert(key.clone(), entry);
         }
-        Some(ReceiverGuard {
+        ReceiverGuard {
             key,
             rx,
             map: Arc::downgrade(&self.inner),
-        })
+            on_cancel,
+        }
     }
 
     pub fn complete(&self, key: &K, value: V) -> Option<V> {
@@ -91,9 +107,20 @@ pub struct ReceiverGuard<K, V>
 where
     K: Eq + Hash + Send + Unpin + 'static,
 {
-    key: K,
-    rx:  oneshot::Receiver<V>,
-    map: Weak<OneshotMapInner<K, V>>,
+    key:       K,
+    rx:        oneshot::Receiver<V>,
+    map:       Weak<OneshotMapInner<K, V>>,
+    on_cancel: Option<CancelHook<K>>,
+}
+
+impl<K, V> ReceiverGuard<K, V>
+where
+    K: Eq + Hash + Send + Unpin + 'static,
+{
+    /// Drops the cancel hook, for requests that never left.
+    pub(crate) fn disarm(&mut self) {
+        self.on_cancel = None;
+    }
 }
 
 impl<K, V> Future for ReceiverGuard<K, V>
@@ -113,8 +140,12 @@ where
     K: Eq + Hash + Send + Unpin + 'static,
 {
     fn drop(&mut self) {
-        if let Some(map) = self.map.upgrade() {
-            map.lock().remove(&self.key);
+        let Some(map) = self.map.upgrade() else {
+            return;
+        };
+        let pending = map.lock().remove(&self.key).is_some();
+        if pending && let Some(on_cancel) = self.on_cancel.take() {
+            on_cancel(&self.key);
         }
     }
 }
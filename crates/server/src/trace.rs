@@ -0,0 +1,29 @@
+//! The trace context of the request being handled.
+//!
+//! [`Server`](crate::server::Server) runs every handler inside the trace
+//! context of its request. Requests posted from there continue that trace, see
+//! [`TraceContext`].
+
+use sithra_transport::trace::TraceContext;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// Returns the trace context of the current task, if it runs inside one.
+#[must_use]
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|trace| *trace).ok()
+}
+
+/// Runs `future` inside `trace`.
+pub async fn scope<F: Future>(trace: TraceContext, future: F) -> F::Output {
+    CURRENT.scope(trace, future).await
+}
+
+/// The trace context for a request sent from the current task: a child span
+/// of the current context, or a new trace outside of one.
+#[must_use]
+pub fn next() -> TraceContext {
+    current().map_or_else(TraceContext::root, |trace| trace.child())
+}
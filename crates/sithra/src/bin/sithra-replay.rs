@@ -0,0 +1,338 @@
+//! Feeds a capture recorded by the host into a single plugin and reports
+//! where the plugin's output differs from the recording.
+//!
+//! ```text
+//! sithra-replay <capture> [--plugin <id>] [--wait <ms>] (--connect <addr> | -- <path> [args...])
+//! ```
+//!
+//! The datapacks the host sent to the plugin are sent again in their recorded
+//! order. Requests made by the plugin are paired with the recorded ones by
+//! path and order, and recorded responses to them are rewritten to the new
+//! correlations, so the plugin sees the same conversation as before.
+//!
+//! With `--connect`, the key in `SITHRA_PEER_KEY` is used to authenticate to
+//! the plugin if it is set.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    env,
+    fmt::Write as _,
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use sithra::{
+    capture::{Direction, Records},
+    loader,
+};
+use sithra_kit::{
+    plugin::PEER_KEY_VAR,
+    transport::{
+        datapack::{DataPack, DataPackCodec, DataPackCodecError, DataResult},
+        peer::{Peer, PeerAddr, Writer},
+        security::Role,
+    },
+};
+use tokio::sync::mpsc;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use ulid::Ulid;
+
+const USAGE: &str = "usage: sithra-replay <capture> [--plugin <id>] [--wait <ms>] (--connect \
+                     <addr> | -- <path> [args...])";
+
+const INITIALIZE_PATH: &str = "/initialize";
+
+/// Paths whose datapacks differ between runs by nature.
+const IGNORED_PATHS: [&str; 1] = ["/log.create"];
+
+/// How long to wait for the plugin by default.
+const DEFAULT_WAIT: Duration = Duration::from_secs(1);
+
+enum Target {
+    Spawn { path: String, args: Vec<String> },
+    Connect(PeerAddr),
+}
+
+struct Options {
+    capture: PathBuf,
+    plugin:  Option<String>,
+    wait:    Duration,
+    target:  Target,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut capture = None;
+    let mut plugin = None;
+    let mut wait = DEFAULT_WAIT;
+    let mut target = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plugin" => plugin = Some(args.next().ok_or("--plugin needs an id")?),
+            "--wait" => {
+                let ms = args.next().ok_or("--wait needs milliseconds")?;
+                wait = Duration::from_millis(ms.parse().map_err(|err| format!("--wait: {err}"))?);
+            }
+            "--connect" => {
+                let addr = args.next().ok_or("--connect needs an address")?;
+                target = Some(Target::Connect(
+                    addr.parse().map_err(|err| format!("{err}"))?,
+                ));
+            }
+            "--" => {
+                let path = args.next().ok_or("-- needs the plugin executable")?;
+                target = Some(Target::Spawn {
+                    path,
+                    args: args.by_ref().collect(),
+                });
+            }
+            _ if capture.is_none() => capture = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument: {arg}")),
+        }
+    }
+    Ok(Options {
+        capture: capture.ok_or("missing capture file")?,
+        plugin,
+        wait,
+        target: target.ok_or("missing plugin, give --connect or -- <path>")?,
+    })
+}
+
+fn is_ignored(datapack: &DataPack) -> bool {
+    datapack.path.as_deref().is_some_and(|path| IGNORED_PATHS.contains(&path))
+}
+
+/// The result of a datapack, compared regardless of map key order.
+fn outcome(datapack: &DataPack) -> Value {
+    match &datapack.result {
+        DataResult::Payload(payload) => {
+            payload.to_value().unwrap_or_else(|_| Value::String(format!("{payload:?}")))
+        }
+        DataResult::Error(error) => json!({ "error": error }),
+    }
+}
+
+fn lines(outcomes: &[Value], sign: char) -> String {
+    outcomes.iter().fold(String::new(), |mut lines, outcome| {
+        write!(lines, "\n{sign} {outcome}").ok();
+        lines
+    })
+}
+
+struct Replay {
+    outputs:  mpsc::UnboundedReceiver<DataPack>,
+    wait:     Duration,
+    /// What the plugin sent during recording.
+    expected: Vec<DataPack>,
+    /// What the plugin sent now.
+    actual:   Vec<DataPack>,
+    /// Paths of the requests sent to the plugin.
+    fed:      HashMap<Ulid, String>,
+}
+
+impl Replay {
+    async fn next_output(&mut self) -> bool {
+        match tokio::time::timeout(self.wait, self.outputs.recv()).await {
+            Ok(Some(datapack)) => {
+                self.actual.push(datapack);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pairs the n-th recorded request to a path with the n-th replayed
+    /// request to the same path.
+    fn request_pairs(&self) -> HashMap<Ulid, Ulid> {
+        let mut replayed = HashMap::<&str, VecDeque<Ulid>>::new();
+        for datapack in self.actual.iter().filter(|dp| dp.is_request()) {
+            let path = datapack.path.as_deref().unwrap_or_default();
+            replayed.entry(path).or_default().push_back(datapack.correlation);
+        }
+        let mut pairs = HashMap::new();
+        for datapack in self.expected.iter().filter(|dp| dp.is_request()) {
+            let path = datapack.path.as_deref().unwrap_or_default();
+            if let Some(correlation) = replayed.get_mut(path).and_then(VecDeque::pop_front) {
+                pairs.insert(datapack.correlation, correlation);
+            }
+        }
+        pairs
+    }
+
+    async fn feed(
+        &mut self,
+        write: &mut FramedWrite<Writer, DataPackCodec>,
+        mut datapack: DataPack,
+    ) -> Result<(), DataPackCodecError> {
+        datapack.deadline = None;
+        let answers_plugin = self
+            .expected
+            .iter()
+            .any(|dp| dp.is_request() && dp.correlation == datapack.correlation);
+        if answers_plugin {
+            loop {
+                if let Some(correlation) = self.request_pairs().get(&datapack.correlation) {
+                    datapack.correlate(*correlation);
+                    break;
+                }
+                if !self.next_output().await {
+                    break;
+                }
+            }
+        } else if let Some(path) = &datapack.path {
+            self.fed.insert(datapack.correlation, path.clone());
+        }
+        let is_init = datapack.path.as_deref() == Some(INITIALIZE_PATH);
+        write.send(datapack).await?;
+        if is_init {
+            while !self.actual.iter().any(|dp| dp.path.as_deref() == Some(INITIALIZE_PATH)) {
+                if !self.next_output().await {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn diff(&self) -> Vec<String> {
+        let pairs = self.request_pairs();
+        let paired: HashSet<Ulid> = pairs.values().copied().collect();
+        let mut diffs = Vec::new();
+
+        for expected in self.expected.iter().filter(|dp| dp.is_request() && !is_ignored(dp)) {
+            let path = expected.path.as_deref().unwrap_or_default();
+            let actual = pairs.get(&expected.correlation).and_then(|correlation| {
+                self.actual.iter().find(|dp| dp.is_request() && dp.correlation == *correlation)
+            });
+            match actual {
+                None => diffs.push(format!(
+                    "missing request {path}{}",
+                    lines(&[outcome(expected)], '-')
+                )),
+                Some(actual) if outcome(actual) != outcome(expected) => diffs.push(format!(
+                    "request {path} differs{}{}",
+                    lines(&[outcome(expected)], '-'),
+                    lines(&[outcome(actual)], '+')
+                )),
+                Some(_) => {}
+            }
+        }
+        for actual in self.actual.iter().filter(|dp| dp.is_request() && !is_ignored(dp)) {
+            if !paired.contains(&actual.correlation) {
+                let path = actual.path.as_deref().unwrap_or_default();
+                diffs.push(format!(
+                    "unexpected request {path}{}",
+                    lines(&[outcome(actual)], '+')
+                ));
+            }
+        }
+
+        let responses = |datapacks: &[DataPack]| {
+            let mut responses = BTreeMap::<Ulid, Vec<Value>>::new();
+            for datapack in datapacks.iter().filter(|dp| !dp.is_request()) {
+                responses.entry(datapack.correlation).or_default().push(outcome(datapack));
+            }
+            responses
+        };
+        let expected = responses(&self.expected);
+        let mut actual = responses(&self.actual);
+        for (correlation, expected) in expected {
+            let actual = actual.remove(&correlation).unwrap_or_default();
+            if actual != expected {
+                let path = self.fed.get(&correlation).map_or("?", String::as_str);
+                diffs.push(format!(
+                    "response to {path} ({correlation}) differs{}{}",
+                    lines(&expected, '-'),
+                    lines(&actual, '+')
+                ));
+            }
+        }
+        for (correlation, actual) in actual {
+            let path = self.fed.get(&correlation).map_or("?", String::as_str);
+            diffs.push(format!(
+                "unexpected response to {path} ({correlation}){}",
+                lines(&actual, '+')
+            ));
+        }
+        diffs
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    tracing_subscriber::fmt::init();
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return Ok(ExitCode::from(2));
+        }
+    };
+
+    let mut records = Vec::new();
+    for record in Records::new(BufReader::new(File::open(&options.capture)?))? {
+        records.push(record?);
+    }
+    let Some(plugin) = options.plugin.or_else(|| records.first().map(|r| r.plugin.clone())) else {
+        eprintln!("capture is empty");
+        return Ok(ExitCode::from(2));
+    };
+    records.retain(|record| record.plugin == plugin);
+
+    let peer = match options.target {
+        Target::Spawn { path, args } => loader::run(path, args)?,
+        Target::Connect(addr) => {
+            let peer = Peer::connect(&addr).await?;
+            match env::var(PEER_KEY_VAR) {
+                Ok(key) => peer.secure(&key.into(), Role::Host).await?,
+                Err(_) => peer,
+            }
+        }
+    };
+    let (write, read) = peer.split();
+    let mut write = FramedWrite::new(write, DataPackCodec::new());
+    let mut read = FramedRead::new(read, DataPackCodec::new());
+    let (output_tx, outputs) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(Ok(datapack)) = read.next().await {
+            if output_tx.send(datapack).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut replay = Replay {
+        outputs,
+        wait: options.wait,
+        expected: Vec::new(),
+        actual: Vec::new(),
+        fed: HashMap::new(),
+    };
+    for record in &records {
+        let datapack = record.datapack()?;
+        match record.direction {
+            Direction::ToPlugin => replay.feed(&mut write, datapack).await?,
+            Direction::FromPlugin => replay.expected.push(datapack),
+        }
+    }
+    while replay.next_output().await {}
+
+    let diffs = replay.diff();
+    for diff in &diffs {
+        println!("{diff}\n");
+    }
+    println!(
+        "replayed {} datapacks into [{plugin}], {} differences",
+        records.len(),
+        diffs.len()
+    );
+    Ok(if diffs.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,271 @@
+//! Recording of the traffic between the host and its plugins.
+//!
+//! A capture file starts with [`MAGIC`] and is followed by records, each a
+//! `u32` big-endian length and a msgpack encoded [`Record`]. Records hold the
+//! datapack exactly as it was encoded on the wire, so a capture can be fed
+//! back into a plugin with the `sithra-replay` binary.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sithra_kit::transport::{DecodeError, datapack::DataPack};
+use thiserror::Error;
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{Mutex, mpsc},
+};
+
+/// The first bytes of every capture file.
+pub const MAGIC: &[u8; 8] = b"SITHCAP1";
+
+/// Which way a datapack travelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Sent by the host to the plugin.
+    ToPlugin,
+    /// Sent by the plugin to the host.
+    FromPlugin,
+}
+
+/// A single captured datapack.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Record {
+    /// Unix time in milliseconds.
+    pub timestamp: u64,
+    pub plugin:    String,
+    pub direction: Direction,
+    /// The encoded datapack, without the frame header.
+    pub datapack:  Bytes,
+}
+
+impl Record {
+    /// Decodes the captured datapack.
+    ///
+    /// # Errors
+    /// Returns an error if the captured bytes are not a valid datapack.
+    pub fn datapack(&self) -> Result<DataPack, DecodeError> {
+        DataPack::from_bytes(&self.datapack)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Capture I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a capture file")]
+    BadMagic,
+    #[error("Failed to decode capture record: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+/// The capture files open in the host.
+///
+/// Each file has a single writer, shared by every plugin capturing to it, so
+/// their records never interleave.
+#[derive(Clone, Default)]
+pub struct Captures {
+    writers: Arc<Mutex<HashMap<PathBuf, mpsc::UnboundedSender<Record>>>>,
+}
+
+impl Captures {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Captures the traffic of `plugin` to `path`, opening the file for
+    /// appending and writing [`MAGIC`] if it is new.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened or written.
+    pub async fn open(&self, path: impl AsRef<Path>, plugin: &str) -> Result<Capture, io::Error> {
+        let path = std::path::absolute(path)?;
+        let mut writers = self.writers.lock().await;
+        let tx = match writers.get(&path) {
+            Some(tx) if !tx.is_closed() => tx.clone(),
+            _ => {
+                let tx = open_writer(&path).await?;
+                writers.insert(path, tx.clone());
+                tx
+            }
+        };
+        drop(writers);
+        Ok(Capture {
+            plugin: plugin.into(),
+            tx,
+        })
+    }
+}
+
+async fn open_writer(path: &Path) -> Result<mpsc::UnboundedSender<Record>, io::Error> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path).await?;
+    if file.metadata().await?.len() == 0 {
+        file.write_all(MAGIC).await?;
+        file.flush().await?;
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(write_records(BufWriter::new(file), rx));
+    Ok(tx)
+}
+
+/// Appends the traffic of one plugin to a capture file, see [`Captures`].
+///
+/// Records are written by a background task, so recording never waits for the
+/// disk. Cloned handles write to the same file.
+#[derive(Clone)]
+pub struct Capture {
+    plugin: Arc<str>,
+    tx:     mpsc::UnboundedSender<Record>,
+}
+
+impl Capture {
+    /// Records `datapack`, logging instead of failing if it cannot be
+    /// encoded.
+    pub fn record(&self, direction: Direction, datapack: &DataPack) {
+        let datapack = match datapack.serialize() {
+            Ok(datapack) => datapack,
+            Err(err) => {
+                log::warn!("[{}] failed to capture datapack: {err}", self.plugin);
+                return;
+            }
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX));
+        let record = Record {
+            timestamp,
+            plugin: self.plugin.to_string(),
+            direction,
+            datapack,
+        };
+        self.tx.send(record).ok();
+    }
+}
+
+async fn write_records(
+    mut file: BufWriter<tokio::fs::File>,
+    mut rx: mpsc::UnboundedReceiver<Record>,
+) {
+    while let Some(record) = rx.recv().await {
+        if let Err(err) = write_record(&mut file, &record).await {
+            log::error!("[{}] failed to write capture: {err}", record.plugin);
+            return;
+        }
+        if rx.is_empty()
+            && let Err(err) = file.flush().await
+        {
+            log::error!("[{}] failed to write capture: {err}", record.plugin);
+            return;
+        }
+    }
+}
+
+async fn write_record(
+    file: &mut BufWriter<tokio::fs::File>,
+    record: &Record,
+) -> Result<(), io::Error> {
+    let encoded = rmp_serde::to_vec_named(record).map_err(io::Error::other)?;
+    let len = u32::try_from(encoded.len()).map_err(io::Error::other)?;
+    file.write_u32(len).await?;
+    file.write_all(&encoded).await
+}
+
+/// Reads the records of a capture file in order.
+pub struct Records<R> {
+    reader: R,
+}
+
+impl<R: Read> Records<R> {
+    /// Checks the header of a capture and returns its records.
+    ///
+    /// # Errors
+    /// Returns [`CaptureError::BadMagic`] if `reader` does not start with
+    /// [`MAGIC`].
+    pub fn new(mut reader: R) -> Result<Self, CaptureError> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CaptureError::BadMagic);
+        }
+        Ok(Self { reader })
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, CaptureError> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let mut buf = vec![0; u32::from_be_bytes(len) as usize];
+        self.reader.read_exact(&mut buf)?;
+        Ok(Some(rmp_serde::from_slice(&buf)?))
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Result<Record, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sithra_kit::transport::datapack::RequestDataPack;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn records_round_trip() {
+        let path = std::env::temp_dir().join(format!("sithra-capture-{}.cap", ulid::Ulid::new()));
+        let captures = Captures::new();
+        let capture = captures.open(&path, "echo").await.unwrap();
+        let other = captures.open(&path, "dice").await.unwrap();
+        let request: DataPack = RequestDataPack::default().path("/message").payload("hi").into();
+        capture.record(Direction::ToPlugin, &request);
+        other.record(Direction::ToPlugin, &request);
+        capture.record(
+            Direction::FromPlugin,
+            &DataPack::builder().build_with_error("no"),
+        );
+        drop((captures, capture, other));
+
+        let mut records = Vec::new();
+        for _ in 0..100 {
+            let file = std::fs::File::open(&path).unwrap();
+            let read = Records::new(io::BufReader::new(file))
+                .and_then(Iterator::collect::<Result<Vec<_>, _>>);
+            if let Ok(read) = read
+                && read.len() == 3
+            {
+                records = read;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].plugin, "echo");
+        assert_eq!(records[1].plugin, "dice");
+        assert_eq!(records[0].direction, Direction::ToPlugin);
+        let decoded = records[0].datapack().unwrap();
+        assert_eq!(decoded.correlation, request.correlation);
+        assert_eq!(decoded.payload::<String>().unwrap(), "hi");
+        assert_eq!(records[2].direction, Direction::FromPlugin);
+    }
+}
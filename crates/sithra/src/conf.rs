@@ -2,6 +2,11 @@ use std::path::{Path, PathBuf};
 
 use ahash::HashMap;
 use serde::{Deserialize, Serialize};
+use sithra_kit::{
+    server::queue::ServerQueues,
+    transport::{datapack::OversizePolicy, peer::PeerAddr, security::PresharedKey},
+    types::role::Roles,
+};
 use thiserror::Error;
 use toml_edit::DocumentMut;
 
@@ -30,16 +35,49 @@ pub enum LoadConfigError {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BaseConfig {
-    pub path:       String,
+    /// Executable spawned as the plugin. Unused if `connect` or `accept` is
+    /// set.
+    #[serde(default)]
+    pub path:          String,
+    /// Connect to a plugin already listening on this address, started with
+    /// `--listen <addr>`, instead of spawning `path`.
+    #[serde(default)]
+    pub connect:       Option<PeerAddr>,
+    /// Listen on this address and wait for the plugin to attach itself
+    /// instead of spawning `path`.
+    #[serde(default)]
+    pub accept:        Option<PeerAddr>,
+    /// Pre-shared key for `connect` and `accept`. When set, the plugin has to
+    /// prove it knows the key before it is attached, and all traffic is
+    /// encrypted. Never written back out.
+    #[serde(default, skip_serializing)]
+    pub key:           Option<PresharedKey>,
     #[serde(default = "true_")]
-    pub enable:     bool,
+    pub enable:        bool,
     #[serde(default)]
-    pub args:       Vec<String>,
+    pub args:          Vec<String>,
     #[serde(rename = "$")]
-    pub ref_:       Option<String>,
-    pub config:     Option<toml::Value>,
+    pub ref_:          Option<String>,
+    /// Maximum payload length of a single frame sent by the plugin.
+    #[serde(default)]
+    pub max_frame_len: Option<u32>,
+    /// What to do when the plugin sends a frame above `max_frame_len`.
+    #[serde(default)]
+    pub oversize:      OversizePolicy,
+    /// Bounds for the queues inside the plugin, so an overloaded plugin
+    /// pushes back instead of growing without limit.
+    #[serde(default)]
+    pub queues:        ServerQueues,
+    /// The owners, admins and channel moderators the plugin should respect.
+    #[serde(default, skip_serializing_if = "Roles::is_empty")]
+    pub roles:         Roles,
+    /// Record all traffic of the plugin to this file, see
+    /// [`crate::capture`]. Plugins may share a file.
+    #[serde(default)]
+    pub capture:       Option<PathBuf>,
+    pub config:        Option<toml::Value>,
     #[serde(skip)]
-    pub raw_config: Option<toml_edit::DocumentMut>,
+    pub raw_config:    Option<toml_edit::DocumentMut>,
 }
 
 const fn true_() -> bool {
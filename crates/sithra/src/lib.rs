@@ -1,3 +1,4 @@
+pub mod capture;
 pub mod conf;
 pub mod loader;
 
@@ -25,4 +26,23 @@ mod test {
         let init: Initialize<A> = data.payload().unwrap();
         assert_eq!(init.config.value, "hello");
     }
+
+    /// The `/initialize` reply of the `sithra-js` peer, as its msgpack
+    /// encoder writes it.
+    #[test]
+    fn js_init_reply() {
+        use sithra_kit::types::initialize::{InitializeAck, PluginInitError, is_compatible};
+
+        let reply = serde_json::json!({
+            "path": "/initialize",
+            "correlation": ulid::Ulid::new().to_string(),
+            "payload": { "Ok": { "protocol_version": 2, "capabilities": [] } },
+        });
+        let bytes = rmp_serde::to_vec_named(&reply).unwrap();
+        let data = DataPack::deserialize(&bytes).unwrap();
+        let result: Result<Option<InitializeAck>, PluginInitError> = data.payload().unwrap();
+        let ack = result.unwrap().unwrap_or_default();
+        assert!(is_compatible(ack.protocol_version));
+        assert!(ack.compression.is_none());
+    }
 }
@@ -4,30 +4,45 @@ use std::{
     fs, io,
     process::Stdio,
     sync::{Arc, RwLock, Weak},
+    time::Duration,
 };
 
 use ahash::HashMap;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use sithra_kit::{
+    server::queue::ServerQueues,
     transport::{
         self, ValueError,
-        datapack::{DataPack, DataPackCodec, DataPackCodecError},
-        peer::{Peer, Reader, Writer},
+        compression::Compression,
+        datapack::{DEFAULT_MAX_FRAME_LEN, DataPack, DataPackCodec, DataPackCodecError},
+        peer::{Peer, PeerListener, Reader, Writer},
+        security::{PresharedKey, Role, SecurityError},
     },
     types::{
-        initialize::{Initialize, InitializeResult, PluginInitError},
+        initialize::{
+            Capability, Initialize, InitializeAck, InitializeResult, MIN_PROTOCOL_VERSION,
+            PROTOCOL_VERSION, PluginInitError, is_compatible,
+        },
         log::Log,
+        role::Roles,
     },
 };
 use thiserror::Error;
-use tokio::{process::Command, sync::broadcast, task::JoinHandle};
+use tokio::{process::Command, sync::broadcast, task::AbortHandle};
 use tokio_util::codec::{FramedRead, FramedWrite};
 
-use crate::conf::{BaseConfig, Config};
+use crate::{
+    capture::{Capture, Captures, Direction},
+    conf::{BaseConfig, Config},
+};
+
+/// How long a socket peer may take to complete the key handshake, and a
+/// plugin attaching itself to answer `/initialize`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
-type JoinMap = Arc<RwLock<HashMap<String, (JoinHandle<()>, JoinHandle<()>)>>>;
-type JoinMapWeak = Weak<RwLock<HashMap<String, (JoinHandle<()>, JoinHandle<()>)>>>;
+type JoinMap = Arc<RwLock<HashMap<String, Vec<AbortHandle>>>>;
+type JoinMapWeak = Weak<RwLock<HashMap<String, Vec<AbortHandle>>>>;
 
 pub struct Loader {
     // dirty:         watch::Sender<bool>,
@@ -36,6 +51,7 @@ pub struct Loader {
     broadcast_tx:  broadcast::Sender<DataPack>,
     _broadcast_rx: broadcast::Receiver<DataPack>,
     join_map:      JoinMap,
+    captures:      Captures,
 }
 
 #[derive(Clone)]
@@ -53,11 +69,12 @@ impl Entry {
         let Some(map) = self.join_map.upgrade() else {
             return;
         };
-        let Some((jh1, jh2)) = map.write().unwrap().remove(&self.key) else {
+        let Some(handles) = map.write().unwrap().remove(&self.key) else {
             return;
         };
-        jh1.abort();
-        jh2.abort();
+        for handle in handles {
+            handle.abort();
+        }
     }
 }
 
@@ -92,6 +109,7 @@ impl Loader {
             broadcast_tx,
             _broadcast_rx: broadcast_rx,
             join_map,
+            captures: Captures::new(),
         }
     }
 
@@ -182,12 +200,96 @@ impl Loader {
         if self.join_map.read().unwrap().contains_key(id) {
             return Ok(true);
         }
-        let path = std::env::current_dir()?.join("data");
         log::info!("loading [{id}]");
-        let broadcast_tx = self.broadcast_tx.clone();
+        if let Some(addr) = &config.accept {
+            let listener = PeerListener::bind(addr).await?;
+            log::info!("[{id}] waiting for the plugin to attach on {addr}");
+            let mut join_map = self.join_map.write().unwrap();
+            let handle = tokio::spawn(Self::accept(
+                listener,
+                id.to_owned(),
+                config.clone(),
+                self.broadcast_tx.clone(),
+                self.join_map.clone(),
+                self.captures.clone(),
+            ));
+            join_map.insert(id.to_owned(), vec![handle.abort_handle()]);
+            drop(join_map);
+            return Ok(true);
+        }
+        let peer = if let Some(addr) = &config.connect {
+            secure(Peer::connect(addr).await?, config.key.as_ref()).await?
+        } else {
+            run(&config.path, &config.args)?
+        };
+        let serving = Self::attach(id, config, peer, &self.broadcast_tx, &self.captures).await?;
+        let entry = Entry::new(Arc::downgrade(&self.join_map), id.to_owned());
+        let mut join_map = self.join_map.write().unwrap();
+        let handle = tokio::spawn(async move {
+            serving.await;
+            entry.abort();
+        });
+        join_map.insert(id.to_owned(), vec![handle.abort_handle()]);
+        drop(join_map);
+        Ok(true)
+    }
+
+    /// Waits for a plugin to connect to `listener` and attaches it, again
+    /// whenever it disconnects.
+    ///
+    /// Peers failing the key handshake or not answering `/initialize` in time
+    /// are dropped and the listener keeps waiting.
+    async fn accept(
+        listener: PeerListener,
+        id: String,
+        config: BaseConfig,
+        broadcast_tx: broadcast::Sender<DataPack>,
+        join_map: JoinMap,
+        captures: Captures,
+    ) {
+        loop {
+            let peer = match listener.accept().await {
+                Ok(peer) => peer,
+                Err(err) => {
+                    log::error!("[{id}] stopped waiting for the plugin: {err}");
+                    break;
+                }
+            };
+            let peer = match secure(peer, config.key.as_ref()).await {
+                Ok(peer) => peer,
+                Err(err) => {
+                    log::warn!("[{id}] rejected peer: {err}");
+                    continue;
+                }
+            };
+            let attach = Self::attach(&id, &config, peer, &broadcast_tx, &captures);
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, attach).await {
+                Ok(Ok(serving)) => {
+                    serving.await;
+                    log::info!("[{id}] detached, waiting for the plugin to attach again");
+                }
+                Ok(Err(err)) => log::error!("Failed to attach plugin {id}: {err}"),
+                Err(_) => log::warn!("[{id}] rejected peer: {}", LoaderError::InitTimeout),
+            }
+        }
+        join_map.write().unwrap().remove(&id);
+    }
+
+    /// Performs the `/initialize` handshake with `peer`, then returns the
+    /// future forwarding its traffic until it disconnects.
+    async fn attach(
+        id: &str,
+        config: &BaseConfig,
+        peer: Peer,
+        broadcast_tx: &broadcast::Sender<DataPack>,
+        captures: &Captures,
+    ) -> Result<impl Future<Output = ()> + Send + use<>, LoaderError> {
+        let path = std::env::current_dir()?.join("data");
         let broadcast_rx = broadcast_tx.subscribe();
-        let peer = run(&config.path, &config.args)?;
-        let (mut write, mut read) = split_peer(peer);
+        let codec = DataPackCodec::new()
+            .with_max_frame_len(config.max_frame_len.unwrap_or(DEFAULT_MAX_FRAME_LEN))
+            .with_oversize_policy(config.oversize);
+        let (mut write, mut read) = split_peer(peer, codec);
         let config_data = transport::to_value(config.config.clone())?;
         let data_path = path.join(id);
         fs::create_dir_all(&data_path)?;
@@ -196,26 +298,70 @@ impl Loader {
                 "Failed to convert data path to string for {id}"
             )));
         };
-        let init_package = init_datapack(config_data, id, data_path);
+        let capture = match &config.capture {
+            Some(path) => {
+                log::info!("[{id}] capturing traffic to {}", path.display());
+                Some(captures.open(path, id).await?)
+            }
+            None => None,
+        };
+        let init_package = init_datapack(
+            config_data,
+            id,
+            data_path,
+            config.queues,
+            config.roles.clone(),
+        );
+        if let Some(capture) = &capture {
+            capture.record(Direction::ToPlugin, &init_package);
+        }
         let raw = init_package.serialize_to_raw()?;
         write.send(raw).await?;
-        Self::next_init_pack(&mut read).await?;
-        let entry = Entry::new(Arc::downgrade(&self.join_map), id.to_owned());
-        let join_handle1 = tokio::spawn(Self::write_loop(write, broadcast_rx, entry.clone()));
-        let join_handle2 = tokio::spawn(Self::read_loop(read, broadcast_tx, entry));
-        self.join_map
-            .write()
-            .unwrap()
-            .insert(id.to_owned(), (join_handle1, join_handle2));
-        Ok(true)
+        let ack = Self::next_init_pack(&mut read, capture.as_ref()).await?;
+        if !is_compatible(ack.protocol_version) {
+            return Err(LoaderError::IncompatibleProtocol {
+                id:      id.to_owned(),
+                version: ack.protocol_version,
+            });
+        }
+        log::debug!(
+            "[{id}] protocol v{}, capabilities {:?}",
+            ack.protocol_version,
+            ack.capabilities
+        );
+        let compression = ack
+            .compression
+            .filter(|c| c.is_supported() && ack.supports(&Capability::Compression));
+        if let Some(compression) = compression {
+            log::debug!("[{id}] compressing frames with {compression:?}");
+            write.encoder_mut().set_compression(Some(compression));
+        }
+        let broadcast_tx = broadcast_tx.clone();
+        Ok(async move {
+            tokio::select! {
+                () = Self::write_loop(write, broadcast_rx, capture.clone()) => {}
+                () = Self::read_loop(read, broadcast_tx, capture) => {}
+            }
+        })
     }
 
-    async fn next_init_pack(read: &mut FramedRead<Reader, DataPackCodec>) -> InitializeResult {
+    async fn next_init_pack(
+        read: &mut FramedRead<Reader, DataPackCodec>,
+        capture: Option<&Capture>,
+    ) -> InitializeResult {
         while let Some(res) = read.next().await {
             if let Ok(res) = res {
                 let matched = res.path.as_ref().map(|v| v == Initialize::<()>::path());
                 if matched == Some(true) {
-                    return res.payload().map_err(PluginInitError::InitPackDeserializeError)?;
+                    if let Some(capture) = capture {
+                        capture.record(Direction::FromPlugin, &res);
+                    }
+                    // Plugins predating the handshake reply with `Ok(null)`, which
+                    // reads as protocol version 0 and is refused as incompatible.
+                    let result: Result<Option<InitializeAck>, PluginInitError> = res
+                        .payload()
+                        .map_err(|err| PluginInitError::InitPackDeserializeError(err.message))?;
+                    return result.map(Option::unwrap_or_default);
                 }
             }
         }
@@ -225,13 +371,15 @@ impl Loader {
     async fn write_loop(
         mut write: FramedWrite<Writer, DataPackCodec>,
         mut broadcast_rx: broadcast::Receiver<DataPack>,
-        entry: Entry,
+        capture: Option<Capture>,
     ) {
         while let Ok(data) = broadcast_rx.recv().await {
+            if let Some(capture) = &capture {
+                capture.record(Direction::ToPlugin, &data);
+            }
             if let Err(err) = write.send(data).await {
                 log::log!(log::Level::Error, "Failed to send data {err}");
                 if err.is_io() {
-                    entry.abort();
                     return;
                 }
             }
@@ -241,20 +389,22 @@ impl Loader {
     async fn read_loop(
         mut read: FramedRead<Reader, DataPackCodec>,
         broadcast_tx: broadcast::Sender<DataPack>,
-        entry: Entry,
+        capture: Option<Capture>,
     ) {
         while let Some(data) = read.next().await {
             let data = match data {
                 Ok(data) => data,
                 Err(err) => {
                     log::error!("Failed to read data: {err}");
-                    if err.is_io() {
-                        entry.abort();
+                    if err.is_io() || err.is_frame_too_large() || err.is_compression() {
                         return;
                     }
                     continue;
                 }
             };
+            if let Some(capture) = &capture {
+                capture.record(Direction::FromPlugin, &data);
+            }
             let Some(data) = map_log(data) else {
                 continue;
             };
@@ -277,20 +427,20 @@ impl Loader {
     /// # Panics
     /// - If the lock is poisoned
     pub fn abort(&self, id: &str) {
-        let Some((join_handle1, join_handle2)) = self.join_map.write().unwrap().remove(id) else {
+        let Some(handles) = self.join_map.write().unwrap().remove(id) else {
             return;
         };
-        join_handle1.abort();
-        join_handle2.abort();
+        for handle in handles {
+            handle.abort();
+        }
         log::info!("[{id}] stopped");
     }
 
     /// # Panics
     /// - If the lock is poisoned
     pub fn abort_all(&self) {
-        for (_, (join_handle1, join_handle2)) in self.join_map.write().unwrap().drain() {
-            join_handle1.abort();
-            join_handle2.abort();
+        for handle in self.join_map.write().unwrap().drain().flat_map(|(_, handles)| handles) {
+            handle.abort();
         }
     }
 }
@@ -318,9 +468,38 @@ pub enum LoaderError {
     InitError(#[from] DataPackCodecError),
     #[error("{0}")]
     PluginInitError(#[from] PluginInitError),
+    #[error(
+        "Plugin {id} speaks protocol version {version}, host supports \
+         {MIN_PROTOCOL_VERSION}..={PROTOCOL_VERSION}"
+    )]
+    IncompatibleProtocol { id: String, version: u32 },
+    #[error("Failed to authenticate Plugin: {0}")]
+    Security(#[from] SecurityError),
+    #[error("Plugin did not complete the key handshake in time")]
+    HandshakeTimeout,
+    #[error("Plugin did not answer /initialize in time")]
+    InitTimeout,
+}
+
+/// Runs the key handshake on `peer` if a key is configured.
+async fn secure(peer: Peer, key: Option<&PresharedKey>) -> Result<Peer, LoaderError> {
+    let Some(key) = key else {
+        return Ok(peer);
+    };
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, peer.secure(key, Role::Host))
+        .await
+        .map_err(|_| LoaderError::HandshakeTimeout)?
+        .map_err(Into::into)
 }
 
-fn run<P, I, S>(path: P, args: I) -> Result<Peer, io::Error>
+/// Spawns the plugin executable at `path` and talks to it over its stdio.
+///
+/// # Errors
+/// Returns an error if the executable cannot be spawned.
+///
+/// # Panics
+/// Panics if the child's stdio cannot be turned into a peer, which is a bug.
+pub fn run<P, I, S>(path: P, args: I) -> Result<Peer, io::Error>
 where
     P: AsRef<OsStr>,
     I: IntoIterator<Item = S>,
@@ -339,8 +518,11 @@ where
     ))
 }
 
+/// Splits `peer`, reading with `read_codec`. The limits configured for the
+/// plugin only apply to what it sends, writes keep the default ones.
 fn split_peer(
     peer: Peer,
+    read_codec: DataPackCodec,
 ) -> (
     FramedWrite<Writer, DataPackCodec>,
     FramedRead<Reader, DataPackCodec>,
@@ -348,7 +530,7 @@ fn split_peer(
     let (write, read) = peer.split();
     (
         FramedWrite::new(write, DataPackCodec::new()),
-        FramedRead::new(read, DataPackCodec::new()),
+        FramedRead::new(read, read_codec),
     )
 }
 
@@ -356,8 +538,13 @@ fn init_datapack<D1: Display, D2: Display>(
     conf: transport::Value,
     name: D1,
     data_path: D2,
+    queues: ServerQueues,
+    roles: Roles,
 ) -> DataPack {
-    let init = Initialize::new(conf, name, data_path);
+    let init = Initialize::new(conf, name, data_path)
+        .with_compression(Compression::supported())
+        .with_queues(queues)
+        .with_roles(roles);
     DataPack::builder().payload(init).path("/initialize").build()
 }
 
@@ -371,7 +558,57 @@ fn map_log(data: DataPack) -> Option<DataPack> {
         return Some(data);
     };
 
+    // Group the plugin's log output by conversation turn.
+    let span = data.trace.map(|trace| tracing::info_span!("trace", id = %trace.trace_id));
+    let _entered = span.as_ref().map(tracing::Span::enter);
     payload.log();
 
     None
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::path::PathBuf;
+
+    use sithra_kit::transport::{peer::PeerAddr, util::framed};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn accept_rearms() {
+        let id = "accept-rearms";
+        let socket = std::env::temp_dir().join(format!("sithra-{id}-{}.sock", std::process::id()));
+        let base = toml::from_str(&format!("accept = \"unix://{}\"", socket.display())).unwrap();
+        let loader = Loader::new(Config {
+            ref_path: PathBuf::new(),
+            doc:      toml_edit::DocumentMut::new(),
+            config:   std::iter::once((id.to_owned(), base)).collect(),
+            path:     PathBuf::new(),
+        });
+        assert!(loader.load(id).await.unwrap());
+        let addr = PeerAddr::Unix(socket.clone());
+
+        // A stray client leaving without answering `/initialize`.
+        drop(Peer::connect(&addr).await.unwrap());
+        // The plugin attaches, and again after disconnecting.
+        for _ in 0..2 {
+            let mut plugin = framed(Peer::connect(&addr).await.unwrap());
+            let init = plugin.next().await.unwrap().unwrap();
+            assert_eq!(init.path.as_deref(), Some("/initialize"));
+            let ack = InitializeResult::Ok(InitializeAck::new(None));
+            plugin
+                .send(DataPack::builder().path("/initialize").build_with_payload(ack))
+                .await
+                .unwrap();
+            // Traffic of the plugin is broadcast, back to itself as well.
+            plugin.send(DataPack::builder().path("/ping").build()).await.unwrap();
+            let echo = plugin.next().await.unwrap().unwrap();
+            assert_eq!(echo.path.as_deref(), Some("/ping"));
+        }
+
+        loader.abort(id);
+        fs::remove_dir_all(PathBuf::from("data").join(id)).ok();
+        fs::remove_dir("data").ok();
+        fs::remove_file(socket).ok();
+    }
+}
@@ -1,23 +1,39 @@
 #![doc = include_str!("./channel.md")]
 
-use std::fmt::Display;
+use std::{
+    fmt::{self, Display, Write as _},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, MapAccess, Visitor, value::MapAccessDeserializer},
+};
+use thiserror::Error;
 use typeshare::typeshare;
 
 #[typeshare]
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 /// Represents a communication channel with a unique ID, type, name, and
 /// optional parent ID.
 ///
+/// Channels also have a one-line address, see [`Channel::from_str`]. It is
+/// accepted wherever a channel is deserialized, and equality and hashing
+/// compare what the address holds, ignoring `name`.
+///
 /// # Fields
 /// - `id`: Unique identifier for the channel. If the platform cannot provide a
 ///   user ID, the event ID is used.
-/// - `ty`: Type of the channel (`group`, `direct`, or `private`).
+/// - `ty`: Type of the channel (`group`, `direct`, `private`, `thread` or
+///   `guild`).
 /// - `name`: Display name of the channel. If the platform cannot provide a
 ///   nickname, the `id` is used.
 /// - `parent_id`: Optional parent channel ID, used for nested channels (e.g.,
-///   subgroups).
+///   subgroups, or the group a thread was started in).
+/// - `guild_id`: Optional ID of the guild (server) the channel belongs to.
+/// - `thread_id`: Optional ID of the thread or topic a `direct` channel was
+///   addressed in, so replies stay inside it.
 pub struct Channel {
     pub id:        String,
     #[serde(rename = "type")]
@@ -25,6 +41,10 @@ pub struct Channel {
     pub name:      String,
     pub parent_id: Option<String>,
     pub self_id:   Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id:  Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
 }
 
 impl Default for Channel {
@@ -35,6 +55,8 @@ impl Default for Channel {
             name:      String::new(),
             parent_id: None,
             self_id:   None,
+            guild_id:  None,
+            thread_id: None,
         }
     }
 }
@@ -48,6 +70,7 @@ impl Channel {
     ///
     /// # Example
     /// ```
+    /// # use sithra_transport::channel::Channel;
     /// let channel = Channel::Private("user123".to_string(), "Alice".to_string());
     /// ```
     #[allow(non_snake_case)]
@@ -59,6 +82,8 @@ impl Channel {
             name,
             parent_id: None,
             self_id: None,
+            guild_id: None,
+            thread_id: None,
         }
     }
 
@@ -70,6 +95,7 @@ impl Channel {
     ///
     /// # Example
     /// ```
+    /// # use sithra_transport::channel::Channel;
     /// let channel =
     ///     Channel::Group("group123".to_string(), "Developers".to_string());
     /// ```
@@ -82,6 +108,8 @@ impl Channel {
             name,
             parent_id: None,
             self_id: None,
+            guild_id: None,
+            thread_id: None,
         }
     }
 
@@ -93,6 +121,7 @@ impl Channel {
     ///
     /// # Example
     /// ```
+    /// # use sithra_transport::channel::Channel;
     /// let channel = Channel::Direct("user456".to_string(), "Bob".to_string());
     /// ```
     #[allow(non_snake_case)]
@@ -104,6 +133,8 @@ impl Channel {
             name,
             parent_id: None,
             self_id: None,
+            guild_id: None,
+            thread_id: None,
         }
     }
 
@@ -116,6 +147,7 @@ impl Channel {
     ///
     /// # Example
     /// ```
+    /// # use sithra_transport::channel::Channel;
     /// let channel = Channel::DirectFromGroup(
     ///     "group123".to_string(),
     ///     "user789".to_string(),
@@ -131,6 +163,100 @@ impl Channel {
             name,
             parent_id: Some(group_id),
             self_id: None,
+            guild_id: None,
+            thread_id: None,
+        }
+    }
+
+    /// Creates a new thread or topic channel.
+    ///
+    /// # Arguments
+    /// - `group_id`: Unique identifier for the group the thread belongs to.
+    /// - `id`: Unique identifier for the thread.
+    /// - `name`: Display name of the thread.
+    ///
+    /// # Example
+    /// ```
+    /// # use sithra_transport::channel::Channel;
+    /// let channel = Channel::Thread(
+    ///     "group123".to_string(),
+    ///     "thread1".to_string(),
+    ///     "Help".to_string(),
+    /// );
+    /// ```
+    #[allow(non_snake_case)]
+    #[must_use]
+    pub const fn Thread(group_id: String, id: String, name: String) -> Self {
+        Self {
+            id,
+            ty: ChannelType::Thread,
+            name,
+            parent_id: Some(group_id),
+            self_id: None,
+            guild_id: None,
+            thread_id: None,
+        }
+    }
+
+    /// Creates a new direct message channel associated with a thread.
+    ///
+    /// # Arguments
+    /// - `group_id`: Unique identifier for the group the thread belongs to.
+    /// - `thread_id`: Unique identifier for the thread.
+    /// - `id`: Unique identifier for the direct message.
+    /// - `name`: Display name of the recipient.
+    ///
+    /// # Example
+    /// ```
+    /// # use sithra_transport::channel::Channel;
+    /// let channel = Channel::DirectFromThread(
+    ///     "group123".to_string(),
+    ///     "thread1".to_string(),
+    ///     "user789".to_string(),
+    ///     "Charlie".to_string(),
+    /// );
+    /// ```
+    #[allow(non_snake_case)]
+    #[must_use]
+    pub const fn DirectFromThread(
+        group_id: String,
+        thread_id: String,
+        id: String,
+        name: String,
+    ) -> Self {
+        Self {
+            id,
+            ty: ChannelType::Direct,
+            name,
+            parent_id: Some(group_id),
+            self_id: None,
+            guild_id: None,
+            thread_id: Some(thread_id),
+        }
+    }
+
+    /// Creates a new guild channel.
+    ///
+    /// # Arguments
+    /// - `id`: Unique identifier for the guild.
+    /// - `name`: Display name of the guild.
+    ///
+    /// # Example
+    /// ```
+    /// # use sithra_transport::channel::Channel;
+    /// let channel = Channel::Guild("guild1".to_string(), "Sithra".to_string());
+    /// ```
+    #[allow(non_snake_case)]
+    #[must_use]
+    pub const fn Guild(id: String, name: String) -> Self {
+        Self {
+            id,
+            ty: ChannelType::Guild,
+            name,
+            parent_id: None,
+            self_id: None,
+            guild_id: None,
+            thread_id: None,
         }
     }
 
@@ -139,11 +265,263 @@ impl Channel {
         self.self_id = Some(id.to_string());
         self
     }
+
+    #[must_use]
+    pub fn set_guild_id<T: Display>(mut self, id: T) -> Self {
+        self.guild_id = Some(id.to_string());
+        self
+    }
+
+    /// The thread this channel is, or was addressed in.
+    ///
+    /// Replying to the returned channel posts into the thread instead of
+    /// directly to the user.
+    #[must_use]
+    pub fn thread(&self) -> Option<Self> {
+        let id = match self.ty {
+            ChannelType::Thread => self.id.clone(),
+            _ => self.thread_id.clone()?,
+        };
+        Some(Self {
+            name: id.clone(),
+            id,
+            ty: ChannelType::Thread,
+            parent_id: self.parent_id.clone(),
+            self_id: self.self_id.clone(),
+            guild_id: self.guild_id.clone(),
+            thread_id: None,
+        })
+    }
+}
+
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.ty == other.ty
+            && self.parent_id == other.parent_id
+            && self.self_id == other.self_id
+            && self.guild_id == other.guild_id
+            && self.thread_id == other.thread_id
+    }
+}
+
+impl Eq for Channel {}
+
+impl Hash for Channel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.ty.hash(state);
+        self.parent_id.hash(state);
+        self.self_id.hash(state);
+        self.guild_id.hash(state);
+        self.thread_id.hash(state);
+    }
+}
+
+/// Characters with a meaning in a channel address, escaped as `%XX` inside
+/// ids.
+const RESERVED: [char; 4] = ['%', '/', ':', '@'];
+
+/// The levels above a channel in its address, outermost first.
+const LEVELS: [&str; 3] = ["guild", "group", "thread"];
+
+fn escape(f: &mut fmt::Formatter<'_>, id: &str) -> fmt::Result {
+    for c in id.chars() {
+        if RESERVED.contains(&c) {
+            write!(f, "%{:02X}", c as u32)?;
+        } else {
+            f.write_char(c)?;
+        }
+    }
+    Ok(())
+}
+
+fn unescape(id: &str) -> Option<String> {
+    let mut out = String::with_capacity(id.len());
+    let mut chars = id.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {
+                let hi = chars.next()?.to_digit(16)?;
+                let lo = chars.next()?.to_digit(16)?;
+                out.push(char::from_u32(hi * 16 + lo)?);
+            }
+            c if RESERVED.contains(&c) => return None,
+            c => out.push(c),
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+/// Formats the address of the channel, see [`Channel::from_str`].
+impl Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let levels = [&self.guild_id, &self.parent_id, &self.thread_id];
+        for (label, id) in LEVELS.iter().zip(levels) {
+            if let Some(id) = id {
+                write!(f, "{label}:")?;
+                escape(f, id)?;
+                f.write_char('/')?;
+            }
+        }
+        write!(f, "{}:", self.ty)?;
+        escape(f, &self.id)?;
+        if let Some(self_id) = &self.self_id {
+            f.write_char('@')?;
+            escape(f, self_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = ParseChannelError;
+
+    /// Parses a channel address.
+    ///
+    /// An address is `<type>:<id>`, optionally followed by the bot as
+    /// `@<self_id>` and preceded by the levels above the channel, outermost
+    /// first: the guild as `guild:<guild_id>/`, the parent as
+    /// `group:<parent_id>/` and the thread as `thread:<thread_id>/`. For
+    /// example `group:123`, `private:456@bot`, `group:123/direct:456@bot` or
+    /// `guild:1/group:2/thread:3/direct:4`. `%`, `/`, `:` and `@` inside ids
+    /// are written as `%25`, `%2F`, `%3A` and `%40`.
+    ///
+    /// The parsed channel is named after its id.
+    ///
+    /// # Example
+    /// ```
+    /// # use sithra_transport::channel::Channel;
+    /// let channel: Channel = "group:123/direct:456@bot".parse().unwrap();
+    /// assert_eq!(
+    ///     channel,
+    ///     Channel::DirectFromGroup("123".into(), "456".into(), String::new())
+    ///         .set_self_id("bot")
+    /// );
+    /// assert_eq!(channel.to_string(), "group:123/direct:456@bot");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseChannelError(s.to_owned());
+        let (rest, self_id) = match s.split_once('@') {
+            Some((rest, self_id)) => (rest, Some(unescape(self_id).ok_or_else(err)?)),
+            None => (s, None),
+        };
+        let mut segments = rest.split('/');
+        let leaf = segments.next_back().ok_or_else(err)?;
+        let mut levels = [None, None, None];
+        let mut next = 0;
+        for segment in segments {
+            let (label, id) = segment.split_once(':').ok_or_else(err)?;
+            let level = LEVELS
+                .iter()
+                .position(|l| *l == label)
+                .filter(|level| *level >= next)
+                .ok_or_else(err)?;
+            levels[level] = Some(unescape(id).ok_or_else(err)?);
+            next = level + 1;
+        }
+        let [guild_id, parent_id, thread_id] = levels;
+        let (ty, id) = leaf.split_once(':').ok_or_else(err)?;
+        let id = unescape(id).ok_or_else(err)?;
+        Ok(Self {
+            name: id.clone(),
+            id,
+            ty: ty.parse().map_err(|_| err())?,
+            parent_id,
+            self_id,
+            guild_id,
+            thread_id,
+        })
+    }
+}
+
+/// Error returned when a string is not a valid channel address.
+#[derive(Debug, Error)]
+#[error("Invalid channel address `{0}`, expected e.g. `group:123/direct:456@bot`")]
+pub struct ParseChannelError(String);
+
+/// The fields of a [`Channel`], as sent on the wire.
+#[derive(Deserialize)]
+struct ChannelFields {
+    id:        String,
+    #[serde(rename = "type")]
+    ty:        ChannelType,
+    name:      String,
+    parent_id: Option<String>,
+    self_id:   Option<String>,
+    #[serde(default)]
+    guild_id:  Option<String>,
+    #[serde(default)]
+    thread_id: Option<String>,
+}
+
+/// Accepts both the fields of a channel and its address.
+impl<'de> Deserialize<'de> for Channel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ChannelVisitor;
+
+        impl<'de> Visitor<'de> for ChannelVisitor {
+            type Value = Channel;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a channel or a channel address")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let ChannelFields {
+                    id,
+                    ty,
+                    name,
+                    parent_id,
+                    self_id,
+                    guild_id,
+                    thread_id,
+                } = ChannelFields::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(Channel {
+                    id,
+                    ty,
+                    name,
+                    parent_id,
+                    self_id,
+                    guild_id,
+                    thread_id,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(ChannelVisitor)
+    }
+}
+
+/// Serializes a [`Channel`] as its address, for use with
+/// `#[serde(with = "sithra_transport::channel::address")]`.
+///
+/// The channel's `name` is not part of the address and is lost.
+pub mod address {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Channel;
+
+    /// # Errors
+    /// Returns an error if the serializer fails.
+    pub fn serialize<S: Serializer>(channel: &Channel, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(channel)
+    }
+
+    /// # Errors
+    /// Returns an error if the input is neither a channel nor an address.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Channel, D::Error> {
+        Channel::deserialize(deserializer)
+    }
 }
 
 /// Represents the type of a communication channel.
 #[typeshare]
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ChannelType {
     /// A group channel, typically used for multi-user conversations.
@@ -152,6 +530,11 @@ pub enum ChannelType {
     Direct,
     /// A private channel, used for restricted or hidden conversations.
     Private,
+    /// A thread or forum topic inside a group, whose `parent_id` is the group.
+    #[serde(alias = "topic")]
+    Thread,
+    /// A guild (server) holding groups, e.g. on Discord or KOOK.
+    Guild,
 }
 
 impl Display for ChannelType {
@@ -160,6 +543,88 @@ impl Display for ChannelType {
             Self::Group => write!(f, "group"),
             Self::Direct => write!(f, "direct"),
             Self::Private => write!(f, "private"),
+            Self::Thread => write!(f, "thread"),
+            Self::Guild => write!(f, "guild"),
         }
     }
 }
+
+impl FromStr for ChannelType {
+    type Err = ParseChannelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "group" => Ok(Self::Group),
+            "direct" => Ok(Self::Direct),
+            "private" => Ok(Self::Private),
+            "thread" | "topic" => Ok(Self::Thread),
+            "guild" => Ok(Self::Guild),
+            _ => Err(ParseChannelError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn address_round_trip() {
+        for address in
+            ["group:123", "private:456@bot", "group:123/direct:456@bot", "direct:a%2Fb%40c"]
+        {
+            let channel: Channel = address.parse().unwrap();
+            assert_eq!(channel.to_string(), address);
+        }
+        let channel: Channel = "direct:a%2Fb%40c".parse().unwrap();
+        assert_eq!(channel.id, "a/b@c");
+        for invalid in
+            ["", "group", "group:", "channel:1", "private:1/group:2", "group:1@", "group:%zz"]
+        {
+            assert!(invalid.parse::<Channel>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn guild_and_thread_levels() {
+        let address = "guild:1/group:2/thread:3/direct:4@bot";
+        let channel: Channel = address.parse().unwrap();
+        assert_eq!(
+            channel,
+            Channel::DirectFromThread("2".into(), "3".into(), "4".into(), String::new())
+                .set_guild_id(1)
+                .set_self_id("bot")
+        );
+        assert_eq!(channel.to_string(), address);
+        let thread = channel.thread().unwrap();
+        assert_eq!(thread.to_string(), "guild:1/group:2/thread:3@bot");
+        assert_eq!(thread.thread().as_ref(), Some(&thread));
+        assert!(Channel::Guild("1".into(), String::new()).thread().is_none());
+        assert!("thread:3/group:2/direct:4".parse::<Channel>().is_err());
+        assert_eq!(
+            "topic:3".parse::<Channel>().unwrap().to_string(),
+            "thread:3"
+        );
+    }
+
+    #[test]
+    fn equality_ignores_name() {
+        let alice = Channel::Private("1".into(), "Alice".into());
+        let renamed = Channel::Private("1".into(), "Alicia".into());
+        assert_eq!(alice, renamed);
+        assert_ne!(alice, alice.clone().set_self_id("bot"));
+        assert_eq!(HashSet::from([alice, renamed]).len(), 1);
+    }
+
+    #[test]
+    fn deserialize_fields_or_address() {
+        let channel = Channel::Group("123".into(), "Developers".into()).set_self_id("bot");
+        let fields = rmp_serde::to_vec_named(&channel).unwrap();
+        let decoded: Channel = rmp_serde::from_slice(&fields).unwrap();
+        assert_eq!(decoded.name, "Developers");
+        let parsed: Channel = serde_json::from_str("\"group:123@bot\"").unwrap();
+        assert_eq!(parsed, decoded);
+    }
+}
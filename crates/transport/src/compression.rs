@@ -0,0 +1,164 @@
+//! Per-frame payload compression.
+//!
+//! The two most significant bits of a frame's `u32` length prefix carry a
+//! compression flag, the remaining 30 bits the length of the (possibly
+//! compressed) payload that follows:
+//!
+//! | flag | meaning                     |
+//! |------|-----------------------------|
+//! | `0`  | uncompressed                |
+//! | `1`  | zstd                        |
+//! | `2`  | lz4 (block, size-prepended) |
+//!
+//! Decoders always understand the flag, so peers that never negotiated
+//! compression keep working unchanged. Which algorithm an encoder uses is
+//! agreed on during `/initialize`: the host offers
+//! [`Compression::supported`], the plugin answers with its choice.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use typeshare::typeshare;
+
+/// Mask selecting the payload length from a frame's length prefix.
+pub const LEN_MASK: u32 = 0x3FFF_FFFF;
+/// Number of bits the compression flag is shifted by in the length prefix.
+pub const FLAG_SHIFT: u32 = 30;
+/// Frames with a payload shorter than this are never compressed.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// A compression algorithm applicable to single frames.
+#[typeshare]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    /// Algorithms compiled into this build, in order of preference.
+    #[must_use]
+    pub const fn supported() -> &'static [Self] {
+        &[
+            #[cfg(feature = "zstd")]
+            Self::Zstd,
+            #[cfg(feature = "lz4")]
+            Self::Lz4,
+        ]
+    }
+
+    /// Returns `true` if this algorithm is compiled into this build.
+    #[must_use]
+    pub fn is_supported(self) -> bool {
+        Self::supported().contains(&self)
+    }
+
+    /// Picks the first algorithm of `offered` that this build supports.
+    #[must_use]
+    pub fn negotiate(offered: &[Self]) -> Option<Self> {
+        offered.iter().copied().find(|c| c.is_supported())
+    }
+
+    /// The flag written into the length prefix for this algorithm.
+    #[must_use]
+    pub const fn flag(self) -> u32 {
+        match self {
+            Self::Zstd => 1,
+            Self::Lz4 => 2,
+        }
+    }
+
+    /// Maps a length-prefix flag back to an algorithm. `Ok(None)` means the
+    /// frame is uncompressed.
+    ///
+    /// # Errors
+    /// Returns an error for flags not assigned to any algorithm.
+    pub const fn from_flag(flag: u32) -> Result<Option<Self>, CompressionError> {
+        match flag {
+            0 => Ok(None),
+            1 => Ok(Some(Self::Zstd)),
+            2 => Ok(Some(Self::Lz4)),
+            _ => Err(CompressionError::UnknownFlag(flag)),
+        }
+    }
+
+    /// Compresses `data`.
+    ///
+    /// # Errors
+    /// Returns an error if the algorithm is not compiled in or fails.
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "lz4")),
+        allow(unused_variables, clippy::missing_const_for_fn)
+    )]
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                zstd::bulk::compress(data, 0).map_err(|e| CompressionError::Codec(e.to_string()))
+            }
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::block::compress_prepend_size(data)),
+            #[allow(unreachable_patterns)]
+            _ => Err(CompressionError::Unsupported(self)),
+        }
+    }
+
+    /// Decompresses `data`, refusing to produce more than `max_len` bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the algorithm is not compiled in, the input is
+    /// corrupt, or the output would exceed `max_len`.
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "lz4")),
+        allow(unused_variables, clippy::missing_const_for_fn)
+    )]
+    pub fn decompress(self, data: &[u8], max_len: usize) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                use std::io::Read;
+
+                let decoder = zstd::stream::read::Decoder::new(data)
+                    .map_err(|e| CompressionError::Codec(e.to_string()))?;
+                let mut out = Vec::new();
+                decoder
+                    .take(max_len as u64 + 1)
+                    .read_to_end(&mut out)
+                    .map_err(|e| CompressionError::Codec(e.to_string()))?;
+                if out.len() > max_len {
+                    return Err(CompressionError::TooLarge { max: max_len });
+                }
+                Ok(out)
+            }
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => {
+                let (len, _) = lz4_flex::block::uncompressed_size(data)
+                    .map_err(|e| CompressionError::Codec(e.to_string()))?;
+                if len > max_len {
+                    return Err(CompressionError::TooLarge { max: max_len });
+                }
+                lz4_flex::block::decompress_size_prepended(data)
+                    .map_err(|e| CompressionError::Codec(e.to_string()))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(CompressionError::Unsupported(self)),
+        }
+    }
+}
+
+/// Error type for frame compression.
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    /// The length prefix carried a flag no algorithm is assigned to.
+    #[error("Unknown compression flag {0}")]
+    UnknownFlag(u32),
+    /// The algorithm is not compiled into this build.
+    #[error("Compression {0:?} is not supported by this build")]
+    Unsupported(Compression),
+    /// The decompressed payload would exceed the frame limit.
+    #[error("Decompressed frame exceeds the maximum of {max} bytes")]
+    TooLarge { max: usize },
+    /// The underlying algorithm failed.
+    #[error("Compression codec error: {0}")]
+    Codec(String),
+}
@@ -1,4 +1,7 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use either::Either;
@@ -7,7 +10,15 @@ use thiserror::Error;
 use tokio_util::codec::{Decoder, Encoder};
 use ulid::Ulid;
 
-use crate::{channel::Channel, util::get_chunk};
+use crate::{
+    DecodeError, EncodeError,
+    channel::Channel,
+    compression::{COMPRESSION_THRESHOLD, Compression, CompressionError, FLAG_SHIFT, LEN_MASK},
+    error::{DataError, ErrorCode},
+    payload::{RawPayload, skip_value},
+    trace::TraceContext,
+    util::get_chunk,
+};
 
 /// A raw data packet containing a length-prefixed byte buffer.
 ///
@@ -31,14 +42,45 @@ impl RawDataPack {
     }
 }
 
+/// Path of the request sent when the caller of a request stopped waiting for
+/// its response. It carries the correlation of the abandoned request.
+pub const CANCEL_PATH: &str = "/cancel";
+
+/// Default upper bound for the payload of a single frame (64 MiB).
+pub const DEFAULT_MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+/// What a codec does when a peer announces a frame larger than the configured
+/// maximum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizePolicy {
+    /// Fail with [`DataPackCodecError::FrameTooLarge`]. The framed stream ends
+    /// afterwards, so the peer is effectively dropped.
+    #[default]
+    Reject,
+    /// Discard the bytes of the oversized frame without buffering them and
+    /// continue with the next frame.
+    Skip,
+}
+
 /// A codec for encoding/decoding `RawDataPack` instances.
 ///
 /// Maintains internal buffers for partial reads/writes and tracks
-/// the current packet length during decoding.
+/// the current packet length during decoding. Frames whose length prefix
+/// exceeds `max_frame_len` are handled according to the configured
+/// [`OversizePolicy`].
+///
+/// Compressed frames (see [`crate::compression`]) are always decoded; frames
+/// are only compressed on encode once a [`Compression`] has been set.
 pub struct RawDataPackCodec {
-    data_len:  Option<u32>,
-    de_buffer: BytesMut,
-    en_buffer: BytesMut,
+    data_len:          Option<u32>,
+    frame_compression: Option<Compression>,
+    de_buffer:         BytesMut,
+    en_buffer:         BytesMut,
+    max_frame_len:     u32,
+    oversize:          OversizePolicy,
+    skip_remaining:    usize,
+    compression:       Option<Compression>,
 }
 
 impl RawDataPackCodec {
@@ -46,11 +88,70 @@ impl RawDataPackCodec {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            data_len:  None,
-            de_buffer: BytesMut::new(),
-            en_buffer: BytesMut::new(),
+            data_len:          None,
+            frame_compression: None,
+            de_buffer:         BytesMut::new(),
+            en_buffer:         BytesMut::new(),
+            max_frame_len:     DEFAULT_MAX_FRAME_LEN,
+            oversize:          OversizePolicy::default(),
+            skip_remaining:    0,
+            compression:       None,
         }
     }
+
+    /// Sets the maximum accepted payload length of a single frame, in bytes.
+    #[must_use]
+    pub const fn with_max_frame_len(mut self, max_frame_len: u32) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Sets how oversized frames are handled while decoding.
+    #[must_use]
+    pub const fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.oversize = policy;
+        self
+    }
+
+    /// Returns the maximum accepted payload length of a single frame.
+    #[must_use]
+    pub const fn max_frame_len(&self) -> u32 {
+        self.max_frame_len
+    }
+
+    /// Returns the configured [`OversizePolicy`].
+    #[must_use]
+    pub const fn oversize_policy(&self) -> OversizePolicy {
+        self.oversize
+    }
+
+    /// Sets the compression applied to encoded frames.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Changes the compression applied to encoded frames, e.g. once it has
+    /// been negotiated on an already framed connection.
+    pub const fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    /// Returns the compression applied to encoded frames.
+    #[must_use]
+    pub const fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Drops bytes belonging to a skipped frame. Returns `true` once the whole
+    /// frame has been discarded.
+    fn skip(&mut self) -> bool {
+        let len = self.skip_remaining.min(self.de_buffer.len());
+        self.de_buffer.advance(len);
+        self.skip_remaining -= len;
+        self.skip_remaining == 0
+    }
 }
 
 impl Default for RawDataPackCodec {
@@ -61,14 +162,44 @@ impl Default for RawDataPackCodec {
 }
 
 impl Encoder<RawDataPack> for RawDataPackCodec {
-    type Error = std::io::Error;
+    type Error = DataPackCodecError;
 
     /// Encodes a `RawDataPack` into the destination buffer.
     ///
-    /// Writes the length prefix followed by the data payload.
+    /// Writes the length prefix followed by the data payload. The payload is
+    /// compressed if a compression is set, it is at least
+    /// [`COMPRESSION_THRESHOLD`] bytes long and compressing actually shrinks
+    /// it.
     fn encode(&mut self, item: RawDataPack, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        self.en_buffer.put_u32(item.data_len);
-        self.en_buffer.put(item.data);
+        if item.data.len() > self.max_frame_len as usize {
+            return Err(DataPackCodecError::FrameTooLarge {
+                len: item.data.len(),
+                max: self.max_frame_len,
+            });
+        }
+        let (flag, data_len, data) = match self.compression {
+            Some(compression) if item.data.len() >= COMPRESSION_THRESHOLD => {
+                let compressed = compression.compress(&item.data)?;
+                if compressed.len() < item.data.len() {
+                    (
+                        compression.flag(),
+                        compressed.len(),
+                        Bytes::from(compressed),
+                    )
+                } else {
+                    (0, item.data_len as usize, item.data)
+                }
+            }
+            _ => (0, item.data_len as usize, item.data),
+        };
+        if data_len > LEN_MASK as usize {
+            return Err(DataPackCodecError::FrameTooLarge {
+                len: data_len,
+                max: LEN_MASK,
+            });
+        }
+        self.en_buffer.put_u32(flag << FLAG_SHIFT | data_len as u32);
+        self.en_buffer.put(data);
         while let Some(bytes) = get_chunk(&mut self.en_buffer) {
             dst.put(bytes);
         }
@@ -77,32 +208,77 @@ impl Encoder<RawDataPack> for RawDataPackCodec {
 }
 
 impl Decoder for RawDataPackCodec {
-    type Error = std::io::Error;
+    type Error = DataPackCodecError;
     type Item = RawDataPack;
 
     /// Decodes a `RawDataPack` from the source buffer.
     ///
     /// Reads the length prefix first, then the data payload once enough bytes
-    /// are available.
+    /// are available. Compressed payloads are decompressed, bounded by the
+    /// maximum frame length.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         self.de_buffer.put(src.split());
-        if self.de_buffer.len() < 4 && self.data_len.is_none() {
-            return Ok(None);
-        } else if self.data_len.is_none() {
-            self.data_len = Some(self.de_buffer.get_u32());
-        }
-        let Some(data_len) = self.data_len else {
-            return Ok(None);
-        };
-        if self.de_buffer.len() < (data_len as usize) {
-            return Ok(None);
+        loop {
+            if !self.skip() {
+                return Ok(None);
+            }
+            if self.de_buffer.len() < 4 && self.data_len.is_none() {
+                return Ok(None);
+            } else if self.data_len.is_none() {
+                let header = self.de_buffer.get_u32();
+                match Compression::from_flag(header >> FLAG_SHIFT) {
+                    Ok(compression) => self.frame_compression = compression,
+                    Err(err) => {
+                        self.de_buffer.clear();
+                        return Err(err.into());
+                    }
+                }
+                self.data_len = Some(header & LEN_MASK);
+            }
+            let Some(data_len) = self.data_len else {
+                return Ok(None);
+            };
+            if data_len > self.max_frame_len {
+                self.data_len = None;
+                match self.oversize {
+                    OversizePolicy::Reject => {
+                        self.de_buffer.clear();
+                        return Err(DataPackCodecError::FrameTooLarge {
+                            len: data_len as usize,
+                            max: self.max_frame_len,
+                        });
+                    }
+                    OversizePolicy::Skip => {
+                        log::warn!(
+                            "Skipping oversized frame: {data_len} bytes (max {})",
+                            self.max_frame_len
+                        );
+                        self.skip_remaining = data_len as usize;
+                        continue;
+                    }
+                }
+            }
+            if self.de_buffer.len() < (data_len as usize) {
+                return Ok(None);
+            }
+            let data = self.de_buffer.split_to(data_len as usize);
+            self.data_len = None;
+            let Some(compression) = self.frame_compression.take() else {
+                return Ok(Some(Self::Item {
+                    data_len,
+                    data: data.into(),
+                }));
+            };
+            match compression.decompress(&data, self.max_frame_len as usize) {
+                Ok(data) => return Ok(Some(RawDataPack::new(data.into()))),
+                Err(CompressionError::TooLarge { max })
+                    if self.oversize == OversizePolicy::Skip =>
+                {
+                    log::warn!("Skipping frame that decompresses to more than {max} bytes");
+                }
+                Err(err) => return Err(err.into()),
+            }
         }
-        let data = self.de_buffer.split_to(data_len as usize);
-        self.data_len = None;
-        Ok(Some(Self::Item {
-            data_len,
-            data: data.into(),
-        }))
     }
 }
 
@@ -110,6 +286,16 @@ impl Decoder for RawDataPackCodec {
 ///
 /// Contains optional metadata (`path`, `channel`), a correlation ID,
 /// and a `result` field that can be either a payload or an error.
+///
+/// Binary data travels in `attachments`, encoded as msgpack `bin` next to the
+/// payload instead of inside it. Payloads refer to an attachment by its index.
+///
+/// The payload is kept as a [`RawPayload`]: decoding a `DataPack` only decodes
+/// the metadata, and encoding it copies the payload bytes verbatim.
+///
+/// Requests may carry a `deadline` in Unix milliseconds, after which the
+/// caller no longer waits for the response, and a [`TraceContext`] linking
+/// them to the conversation turn they are part of.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DataPack {
     pub bot_id:      Option<String>,
@@ -118,6 +304,12 @@ pub struct DataPack {
     pub channel:     Option<Channel>,
     #[serde(flatten)]
     pub result:      DataResult,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Bytes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline:    Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace:       Option<TraceContext>,
 }
 
 impl Default for DataPack {
@@ -127,7 +319,10 @@ impl Default for DataPack {
             path:        None,
             correlation: Ulid::new(),
             channel:     None,
-            result:      DataResult::Payload(crate::Value::Null),
+            result:      DataResult::Payload(RawPayload::null()),
+            attachments: Vec::new(),
+            deadline:    None,
+            trace:       None,
         }
     }
 }
@@ -140,6 +335,9 @@ impl From<RequestDataPack> for DataPack {
             correlation,
             channel,
             payload,
+            attachments,
+            deadline,
+            trace,
         } = value;
         Self {
             bot_id,
@@ -147,6 +345,9 @@ impl From<RequestDataPack> for DataPack {
             correlation,
             channel,
             result: DataResult::Payload(payload),
+            attachments,
+            deadline,
+            trace,
         }
     }
 }
@@ -157,11 +358,18 @@ impl From<RequestDataPack> for DataPack {
 /// metadata and a correlation ID for tracking.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RequestDataPack {
-    pub bot_id:  Option<String>,
-    pub path:    String,
-    correlation: Ulid,
-    pub channel: Option<Channel>,
-    pub payload: crate::Value,
+    pub bot_id:      Option<String>,
+    pub path:        String,
+    correlation:     Ulid,
+    pub channel:     Option<Channel>,
+    pub payload:     RawPayload,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Bytes>,
+    /// Unix time in milliseconds after which the caller stops waiting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline:    Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace:       Option<TraceContext>,
 }
 
 impl Default for RequestDataPack {
@@ -172,12 +380,32 @@ impl Default for RequestDataPack {
             path:        String::new(),
             correlation: Ulid::new(),
             channel:     None,
-            payload:     crate::Value::Null,
+            payload:     RawPayload::null(),
+            attachments: Vec::new(),
+            deadline:    None,
+            trace:       None,
         }
     }
 }
 
 impl RequestDataPack {
+    /// Creates the request cancelling the request with the given correlation.
+    #[must_use]
+    pub fn cancel(correlation: Ulid) -> Self {
+        Self {
+            path: CANCEL_PATH.to_owned(),
+            correlation,
+            ..Self::default()
+        }
+    }
+
+    /// Returns `true` if this request cancels another one, see
+    /// [`Self::cancel`].
+    #[must_use]
+    pub fn is_cancel(&self) -> bool {
+        self.path == CANCEL_PATH
+    }
+
     #[must_use]
     pub fn bot_id(mut self, bot_id: impl Display) -> Self {
         self.bot_id = Some(bot_id.to_string());
@@ -206,22 +434,85 @@ impl RequestDataPack {
 
     #[must_use]
     pub fn payload_value(mut self, payload: impl Into<crate::Value>) -> Self {
-        self.payload = payload.into();
+        self.payload = payload.into().into();
         self
     }
 
     #[must_use]
     pub fn payload<S: Serialize>(mut self, payload: S) -> Self {
-        self.payload = crate::to_value(payload).unwrap_or(crate::Value::Null);
+        self.payload = RawPayload::encode(&payload).unwrap_or_default();
         self
     }
 
+    /// Sets an already encoded payload.
+    #[must_use]
+    pub fn payload_raw(mut self, payload: RawPayload) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Deserializes the payload straight into `T`.
+    ///
+    /// # Errors
+    /// Returns an error if the payload does not match `T`.
+    pub fn decode_payload<'de, T: Deserialize<'de>>(&'de self) -> Result<T, DecodeError> {
+        self.payload.decode()
+    }
+
+    /// Appends a binary attachment. Attachments are indexed in the order they
+    /// are added.
+    #[must_use]
+    pub fn attach(mut self, data: impl Into<Bytes>) -> Self {
+        self.attachments.push(data.into());
+        self
+    }
+
+    /// Returns the attachment at `index`.
+    #[must_use]
+    pub fn attachment(&self, index: usize) -> Option<&Bytes> {
+        self.attachments.get(index)
+    }
+
     #[must_use]
     /// Returns the correlation ID of the `DataPack`.
     pub const fn correlation(&self) -> Ulid {
         self.correlation
     }
 
+    /// Sets the point in time after which the caller stops waiting.
+    #[must_use]
+    pub fn deadline(mut self, deadline: SystemTime) -> Self {
+        self.deadline = Some(unix_millis(deadline));
+        self
+    }
+
+    /// Sets the deadline to `timeout` from now, unless an earlier deadline
+    /// is already set.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        let deadline = unix_millis(SystemTime::now() + timeout);
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+        self
+    }
+
+    /// Returns the time left until the deadline, `None` without a deadline.
+    #[must_use]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(remaining_until)
+    }
+
+    /// Returns `true` if the deadline has passed.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|r| r.is_zero())
+    }
+
+    #[must_use]
+    pub const fn trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
     #[must_use]
     pub fn link(mut self, other: &Self) -> Self {
         if self.bot_id.is_none() {
@@ -230,6 +521,9 @@ impl RequestDataPack {
         if self.channel.is_none() {
             self.channel.clone_from(&other.channel);
         }
+        if self.trace.is_none() {
+            self.trace = other.trace;
+        }
         self.correlation = other.correlation();
         self
     }
@@ -240,17 +534,17 @@ impl RequestDataPack {
 pub enum DataResult {
     /// Successful operation with a payload value.
     #[serde(rename = "payload")]
-    Payload(crate::Value),
-    /// Failed operation with an error message.
+    Payload(RawPayload),
+    /// Failed operation with an error.
     #[serde(rename = "error")]
-    Error(String),
+    Error(DataError),
 }
 
 /// Converts a `DataResult` into a standard `Result`.
 ///
 /// - `Payload(v)` becomes `Ok(v)`
 /// - `Error(e)` becomes `Err(e)`
-impl From<DataResult> for Result<crate::Value, String> {
+impl From<DataResult> for Result<RawPayload, DataError> {
     fn from(value: DataResult) -> Self {
         match value {
             DataResult::Payload(v) => Ok(v),
@@ -261,17 +555,20 @@ impl From<DataResult> for Result<crate::Value, String> {
 
 /// Converts a standard `Result` into a `DataResult`.
 ///
-/// - `Ok(payload)` becomes `Payload(payload.into())`
-/// - `Err(error)` becomes `Error(error.to_string())`
+/// - `Ok(payload)` becomes `Payload(payload)` encoded
+/// - `Err(error)` becomes `Error(error.to_string().into())`
 impl<P, E> From<Result<P, E>> for DataResult
 where
-    P: Into<crate::Value>,
+    P: Serialize,
     E: Display,
 {
     fn from(value: Result<P, E>) -> Self {
         match value {
-            Ok(payload) => Self::Payload(payload.into()),
-            Err(error) => Self::Error(error.to_string()),
+            Ok(payload) => match RawPayload::encode(&payload) {
+                Ok(payload) => Self::Payload(payload),
+                Err(error) => Self::Error(DataError::new(ErrorCode::InvalidPayload, error)),
+            },
+            Err(error) => Self::Error(error.to_string().into()),
         }
     }
 }
@@ -286,6 +583,9 @@ pub struct DataPackBuilder {
     pub correlation: Option<Ulid>,
     pub channel:     Option<Channel>,
     pub result:      Option<DataResult>,
+    pub attachments: Vec<Bytes>,
+    pub deadline:    Option<u64>,
+    pub trace:       Option<TraceContext>,
 }
 
 impl Default for DataPackBuilder {
@@ -304,6 +604,9 @@ impl DataPackBuilder {
             correlation: None,
             channel:     None,
             result:      None,
+            attachments: Vec::new(),
+            deadline:    None,
+            trace:       None,
         }
     }
 
@@ -334,6 +637,27 @@ impl DataPackBuilder {
         self
     }
 
+    /// Appends a binary attachment to the `DataPack`.
+    #[must_use]
+    pub fn attach(mut self, data: impl Into<Bytes>) -> Self {
+        self.attachments.push(data.into());
+        self
+    }
+
+    /// Sets the `deadline` field for the `DataPack`.
+    #[must_use]
+    pub fn deadline(mut self, deadline: SystemTime) -> Self {
+        self.deadline = Some(unix_millis(deadline));
+        self
+    }
+
+    /// Sets the `trace` field for the `DataPack`.
+    #[must_use]
+    pub const fn trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
     /// Sets the `result` field for the `DataPack`.
     #[must_use]
     pub fn result(mut self, result: impl Into<DataResult>) -> Self {
@@ -345,7 +669,7 @@ impl DataPackBuilder {
     ///
     /// Defaults:
     /// - `correlation`: A new `Ulid` if not set.
-    /// - `result`: `DataResult::Payload(crate::Value::Null)` if not set.
+    /// - `result`: `DataResult::Payload(RawPayload::null())` if not set.
     #[must_use]
     pub fn build(self) -> DataPack {
         let Self {
@@ -354,11 +678,14 @@ impl DataPackBuilder {
             correlation,
             channel,
             result,
+            attachments,
+            deadline,
+            trace,
         } = self;
 
         let correlation = correlation.unwrap_or_else(Ulid::new);
 
-        let result = result.unwrap_or(DataResult::Payload(crate::Value::Null));
+        let result = result.unwrap_or_else(|| DataResult::Payload(RawPayload::null()));
 
         DataPack {
             bot_id,
@@ -366,19 +693,22 @@ impl DataPackBuilder {
             correlation,
             channel,
             result,
+            attachments,
+            deadline,
+            trace,
         }
     }
 
     /// Sets the `result` field to a `Payload` variant.
     #[must_use]
     pub fn payload(mut self, payload: impl Serialize) -> Self {
-        let payload = crate::to_value(payload);
+        let payload = RawPayload::encode(&payload);
         match payload {
             Ok(payload) => {
                 self.result = Some(DataResult::Payload(payload));
             }
             Err(err) => {
-                return self.error(err);
+                return self.error(DataError::new(ErrorCode::InvalidPayload, err));
             }
         }
         self
@@ -386,21 +716,21 @@ impl DataPackBuilder {
 
     /// Sets the `result` field to an `Error` variant.
     #[must_use]
-    pub fn error(mut self, error: impl Display) -> Self {
-        self.result = Some(DataResult::Error(error.to_string()));
+    pub fn error(mut self, error: impl Into<DataError>) -> Self {
+        self.result = Some(DataResult::Error(error.into()));
         self
     }
 
     /// Builds a `DataPack` with a `Payload` result.
     #[must_use]
     pub fn build_with_payload(mut self, payload: impl Serialize) -> DataPack {
-        let payload = crate::to_value(payload);
+        let payload = RawPayload::encode(&payload);
         match payload {
             Ok(payload) => {
                 self.result = Some(DataResult::Payload(payload));
             }
             Err(err) => {
-                return self.error(err).build();
+                return self.error(DataError::new(ErrorCode::InvalidPayload, err)).build();
             }
         }
         self.build()
@@ -408,7 +738,7 @@ impl DataPackBuilder {
 
     /// Builds a `DataPack` with an `Error` result.
     #[must_use]
-    pub fn build_with_error(self, error: impl Display) -> DataPack {
+    pub fn build_with_error(self, error: impl Into<DataError>) -> DataPack {
         self.error(error).build()
     }
 }
@@ -427,54 +757,146 @@ impl DataPack {
         if self.channel.is_none() {
             self.channel.clone_from(&other.channel);
         }
+        if self.trace.is_none() {
+            self.trace = other.trace;
+        }
         self.correlate(other.correlation());
         self
     }
 
+    /// Returns the attachment at `index`.
+    #[must_use]
+    pub fn attachment(&self, index: usize) -> Option<&Bytes> {
+        self.attachments.get(index)
+    }
+
+    /// Returns the time left until the deadline, `None` without a deadline.
+    #[must_use]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(remaining_until)
+    }
+
+    /// Returns `true` if the deadline has passed.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|r| r.is_zero())
+    }
+
     /// # Errors
-    /// Returns an error if deserialization fails.
-    pub fn payload<T: for<'de> Deserialize<'de>>(&self) -> Result<T, String> {
-        let payload = match &self.result {
-            DataResult::Error(err) => return Err(err.clone()),
-            DataResult::Payload(payload) => payload.clone(),
-        };
-        crate::from_value(payload).map_err(|err| format!("{err}"))
+    /// Returns the error result of the peer, or an
+    /// [`ErrorCode::InvalidPayload`] error if deserialization fails.
+    pub fn payload<'de, T: Deserialize<'de>>(&'de self) -> Result<T, DataError> {
+        match &self.result {
+            DataResult::Error(err) => Err(err.clone()),
+            DataResult::Payload(payload) => {
+                payload.decode().map_err(|err| DataError::new(ErrorCode::InvalidPayload, err))
+            }
+        }
     }
 
     /// Deserialize a `DataPack` from a byte slice.
     ///
     /// # Errors
     /// Returns an error if the byte slice is not a valid `DataPack`.
-    /// Deserialize a `DataPack` from a byte slice.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::from_bytes(&Bytes::copy_from_slice(bytes))
+    }
+
+    /// Deserialize a `DataPack` from an encoded frame.
+    ///
+    /// Only the metadata is decoded; the payload and attachments are slices of
+    /// `bytes` and are not copied.
     ///
     /// # Errors
-    /// Returns an error if the byte slice is not a valid `DataPack`.
-    pub fn deserialize(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
-        rmp_serde::from_slice(bytes)
+    /// Returns an error if the bytes are not a valid `DataPack`.
+    pub fn from_bytes(bytes: &Bytes) -> Result<Self, DecodeError> {
+        let mut rd = bytes.as_ref();
+        let offset = |rd: &[u8]| bytes.len() - rd.len();
+        let mut pack = Self::default();
+        let mut correlation = None;
+        for _ in 0..rmp::decode::read_map_len(&mut rd)? {
+            match decode_field::<&str>(&mut rd)? {
+                "bot_id" => pack.bot_id = decode_field(&mut rd)?,
+                "path" => pack.path = decode_field(&mut rd)?,
+                "correlation" => correlation = Some(decode_field(&mut rd)?),
+                "channel" => pack.channel = decode_field(&mut rd)?,
+                "payload" => {
+                    let start = offset(rd);
+                    skip_value(&mut rd)?;
+                    let payload = bytes.slice(start..offset(rd));
+                    pack.result = DataResult::Payload(RawPayload::from_bytes_unchecked(payload));
+                }
+                "error" => pack.result = DataResult::Error(decode_field(&mut rd)?),
+                "deadline" => pack.deadline = decode_field(&mut rd)?,
+                "trace" => pack.trace = decode_field(&mut rd)?,
+                "attachments" => {
+                    let len = rmp::decode::read_array_len(&mut rd)?;
+                    for _ in 0..len {
+                        let len = rmp::decode::read_bin_len(&mut rd)? as usize;
+                        let start = offset(rd);
+                        rd = rd.get(len..).ok_or(DecodeError::LengthMismatch(len as u32))?;
+                        pack.attachments.push(bytes.slice(start..start + len));
+                    }
+                }
+                _ => skip_value(&mut rd)?,
+            }
+        }
+        pack.correlation = correlation
+            .ok_or_else(|| DecodeError::Syntax("missing field `correlation`".to_owned()))?;
+        Ok(pack)
     }
 
     /// Serialize a `DataPack` into a byte slice.
     ///
-    /// # Errors
-    /// Returns an error if the `DataPack` cannot be serialized.
-    /// Serialize a `DataPack` into a byte slice.
+    /// The payload is written verbatim, without re-encoding it.
     ///
     /// # Errors
     /// Returns an error if the `DataPack` cannot be serialized.
-    pub fn serialize(&self) -> Result<Bytes, rmp_serde::encode::Error> {
-        rmp_serde::to_vec_named(self).map(Bytes::from)
+    pub fn serialize(&self) -> Result<Bytes, EncodeError> {
+        let payload_len = match &self.result {
+            DataResult::Payload(payload) => payload.as_bytes().len(),
+            DataResult::Error(err) => err.message.len(),
+        };
+        let attachments_len: usize = self.attachments.iter().map(Bytes::len).sum();
+        let mut buf = Vec::with_capacity(128 + payload_len + attachments_len);
+        let fields = 5
+            + u32::from(!self.attachments.is_empty())
+            + u32::from(self.deadline.is_some())
+            + u32::from(self.trace.is_some());
+        rmp::encode::write_map_len(&mut buf, fields)?;
+        encode_field(&mut buf, "bot_id", &self.bot_id)?;
+        encode_field(&mut buf, "path", &self.path)?;
+        encode_field(&mut buf, "correlation", &self.correlation)?;
+        encode_field(&mut buf, "channel", &self.channel)?;
+        match &self.result {
+            DataResult::Payload(payload) => {
+                rmp::encode::write_str(&mut buf, "payload")?;
+                buf.extend_from_slice(payload.as_bytes());
+            }
+            DataResult::Error(err) => encode_field(&mut buf, "error", err)?,
+        }
+        if let Some(deadline) = &self.deadline {
+            encode_field(&mut buf, "deadline", deadline)?;
+        }
+        if let Some(trace) = &self.trace {
+            encode_field(&mut buf, "trace", trace)?;
+        }
+        if !self.attachments.is_empty() {
+            rmp::encode::write_str(&mut buf, "attachments")?;
+            rmp::encode::write_array_len(&mut buf, self.attachments.len() as u32)?;
+            for attachment in &self.attachments {
+                rmp::encode::write_bin(&mut buf, attachment)?;
+            }
+        }
+        Ok(buf.into())
     }
 
     /// Serialize a `DataPack` into a raw byte slice.
     ///
     /// # Errors
     /// Returns an error if the `DataPack` cannot be serialized.
-    /// Serialize a `DataPack` into a raw byte slice.
-    ///
-    /// # Errors
-    /// Returns an error if the `DataPack` cannot be serialized.
-    pub fn serialize_to_raw(&self) -> Result<RawDataPack, rmp_serde::encode::Error> {
-        rmp_serde::to_vec_named(self).map(|v| RawDataPack::new(Bytes::from(v)))
+    pub fn serialize_to_raw(&self) -> Result<RawDataPack, EncodeError> {
+        self.serialize().map(RawDataPack::new)
     }
 
     #[must_use]
@@ -510,6 +932,9 @@ impl DataPack {
             correlation,
             channel,
             result,
+            attachments,
+            deadline,
+            trace,
         } = self;
         let payload: Result<_, _> = result.into();
         RequestDataPack {
@@ -517,11 +942,39 @@ impl DataPack {
             path: path.unwrap_or_default(),
             correlation,
             channel,
-            payload: payload.unwrap_or(crate::Value::Null),
+            payload: payload.unwrap_or_default(),
+            attachments,
+            deadline,
+            trace,
         }
     }
 }
 
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+fn remaining_until(deadline: u64) -> Duration {
+    let deadline = UNIX_EPOCH + Duration::from_millis(deadline);
+    deadline.duration_since(SystemTime::now()).unwrap_or_default()
+}
+
+/// Decodes the next msgpack value of `rd`, advancing past it.
+fn decode_field<'a, T: Deserialize<'a>>(rd: &mut &'a [u8]) -> Result<T, DecodeError> {
+    let start = *rd;
+    skip_value(rd)?;
+    rmp_serde::from_slice(&start[..start.len() - rd.len()])
+}
+
+fn encode_field<T: Serialize + ?Sized>(
+    buf: &mut Vec<u8>,
+    key: &str,
+    value: &T,
+) -> Result<(), EncodeError> {
+    rmp::encode::write_str(buf, key)?;
+    rmp_serde::encode::write_named(buf, value)
+}
+
 /// A codec for encoding/decoding `DataPack` instances.
 ///
 /// Wraps a `RawDataPackCodec` to handle the low-level byte operations
@@ -538,6 +991,51 @@ impl DataPackCodec {
             raw: RawDataPackCodec::new(),
         }
     }
+
+    /// Sets the maximum accepted payload length of a single frame, in bytes.
+    #[must_use]
+    pub const fn with_max_frame_len(mut self, max_frame_len: u32) -> Self {
+        self.raw.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Sets how oversized frames are handled while decoding.
+    #[must_use]
+    pub const fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.raw.oversize = policy;
+        self
+    }
+
+    /// Returns the maximum accepted payload length of a single frame.
+    #[must_use]
+    pub const fn max_frame_len(&self) -> u32 {
+        self.raw.max_frame_len()
+    }
+
+    /// Returns the configured [`OversizePolicy`].
+    #[must_use]
+    pub const fn oversize_policy(&self) -> OversizePolicy {
+        self.raw.oversize_policy()
+    }
+
+    /// Sets the compression applied to encoded frames.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.raw.compression = compression;
+        self
+    }
+
+    /// Changes the compression applied to encoded frames, e.g. once it has
+    /// been negotiated on an already framed connection.
+    pub const fn set_compression(&mut self, compression: Option<Compression>) {
+        self.raw.set_compression(compression);
+    }
+
+    /// Returns the compression applied to encoded frames.
+    #[must_use]
+    pub const fn compression(&self) -> Option<Compression> {
+        self.raw.compression()
+    }
 }
 
 impl Default for DataPackCodec {
@@ -556,7 +1054,7 @@ impl Decoder for DataPackCodec {
         let Some(raw_data) = raw_data else {
             return Ok(None);
         };
-        Ok(Some(DataPack::deserialize(&raw_data.data)?))
+        Ok(Some(DataPack::from_bytes(&raw_data.data)?))
     }
 }
 
@@ -604,6 +1102,12 @@ pub enum DataPackCodecError {
     /// Wraps deserialization errors when converting bytes to `DataPack`.
     #[error("DataPack deserialization error: {0}")]
     Deserialize(#[from] rmp_serde::decode::Error),
+    /// A frame exceeded the codec's maximum frame length.
+    #[error("Frame of {len} bytes exceeds the maximum of {max} bytes")]
+    FrameTooLarge { len: usize, max: u32 },
+    /// A frame could not be compressed or decompressed.
+    #[error("DataPack compression error: {0}")]
+    Compression(#[from] CompressionError),
 }
 
 impl DataPackCodecError {
@@ -621,4 +1125,138 @@ impl DataPackCodecError {
     pub const fn is_deserialize(&self) -> bool {
         matches!(self, Self::Deserialize(_))
     }
+
+    #[must_use]
+    pub const fn is_frame_too_large(&self) -> bool {
+        matches!(self, Self::FrameTooLarge { .. })
+    }
+
+    #[must_use]
+    pub const fn is_compression(&self) -> bool {
+        matches!(self, Self::Compression(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(len: usize) -> BytesMut {
+        let mut buf = BytesMut::new();
+        buf.put_u32(len as u32);
+        buf.put_bytes(7, len);
+        buf
+    }
+
+    #[test]
+    fn reject_oversized_frame() {
+        let mut codec = RawDataPackCodec::new().with_max_frame_len(8);
+        let mut src = frame(16);
+        let err = codec.decode(&mut src).err().expect("oversized frame must fail");
+        assert!(err.is_frame_too_large());
+    }
+
+    #[test]
+    fn skip_oversized_frame() {
+        let mut codec = RawDataPackCodec::new()
+            .with_max_frame_len(8)
+            .with_oversize_policy(OversizePolicy::Skip);
+        let mut src = frame(16);
+        let tail = src.split_off(10);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+
+        let mut src = tail;
+        src.extend_from_slice(&frame(4));
+        let raw = codec.decode(&mut src).unwrap().expect("frame after the skipped one");
+        assert_eq!(raw.data.as_ref(), &[7; 4]);
+        assert!(codec.decode(&mut BytesMut::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn lazy_payload_matches_serde_encoding() {
+        let datapack = DataPack::builder()
+            .path("/message")
+            .bot_id("bot")
+            .attach(Bytes::from_static(b"bin"))
+            .build_with_payload(serde_json::json!({ "content": ["hello", 1, null] }));
+        let bytes = datapack.serialize().unwrap();
+        let derived: DataPack = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(
+            derived.payload::<crate::Value>(),
+            datapack.payload::<crate::Value>()
+        );
+
+        let bytes = Bytes::from(rmp_serde::to_vec_named(&derived).unwrap());
+        let decoded = DataPack::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.serialize().unwrap(), datapack.serialize().unwrap());
+        let DataResult::Payload(payload) = &decoded.result else {
+            panic!("expected a payload");
+        };
+        // The payload is a view into the frame, not a copy.
+        assert!(bytes.as_ptr_range().contains(&payload.as_bytes().as_ptr()));
+    }
+
+    #[test]
+    fn deadline_round_trip() {
+        let request = RequestDataPack::default()
+            .path("/slow")
+            .timeout(Duration::from_mins(1))
+            .timeout(Duration::from_mins(2));
+        let remaining = request.remaining().unwrap();
+        assert!(remaining <= Duration::from_mins(1) && !request.is_expired());
+
+        let decoded = DataPack::deserialize(&DataPack::from(request.clone()).serialize().unwrap())
+            .unwrap()
+            .into_request();
+        assert_eq!(decoded.deadline, request.deadline);
+
+        let expired = request.deadline(UNIX_EPOCH + Duration::from_secs(1));
+        assert!(expired.is_expired());
+    }
+
+    #[test]
+    fn attachments_are_msgpack_bin() {
+        let image = Bytes::from_static(&[0x89, b'P', b'N', b'G']);
+        let datapack: DataPack =
+            RequestDataPack::default().path("/image").attach(image.clone()).into();
+        let bytes = datapack.serialize().unwrap();
+        // bin8 marker followed by the length, then the raw bytes.
+        assert!(bytes.windows(6).any(|w| w == [0xc4, 4, 0x89, b'P', b'N', b'G']));
+
+        let decoded = DataPack::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.attachment(0), Some(&image));
+        assert_eq!(decoded.into_request().attachments, [image]);
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        for &compression in Compression::supported() {
+            let mut codec = RawDataPackCodec::new().with_compression(Some(compression));
+            let data = Bytes::from(vec![b'a'; 4096]);
+            let mut dst = BytesMut::new();
+            codec.encode(RawDataPack::new(data.clone()), &mut dst).unwrap();
+            assert_eq!(
+                u32::from_be_bytes(dst[..4].try_into().unwrap()) >> FLAG_SHIFT,
+                compression.flag()
+            );
+            assert!(dst.len() < data.len());
+
+            let mut plain = RawDataPackCodec::new();
+            let raw = plain.decode(&mut dst).unwrap().expect("decompressed frame");
+            assert_eq!(raw.data, data);
+        }
+    }
+
+    #[test]
+    fn reject_decompression_bomb() {
+        for &compression in Compression::supported() {
+            let mut codec = RawDataPackCodec::new().with_compression(Some(compression));
+            let mut dst = BytesMut::new();
+            codec.encode(RawDataPack::new(Bytes::from(vec![0; 4096])), &mut dst).unwrap();
+
+            let mut small = RawDataPackCodec::new().with_max_frame_len(1024);
+            let err = small.decode(&mut dst).err().expect("bomb must fail");
+            assert!(err.is_compression());
+        }
+    }
 }
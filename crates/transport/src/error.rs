@@ -0,0 +1,141 @@
+//! Structured errors carried by datapacks, see [`DataError`].
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use typeshare::typeshare;
+
+/// What kind of failure a [`DataError`] reports, so peers can react to it
+/// without parsing the message.
+#[typeshare]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The peer failed on its own, e.g. a handler panicked.
+    Internal,
+    /// The request is malformed or its arguments are invalid.
+    InvalidRequest,
+    /// A payload could not be encoded or decoded.
+    InvalidPayload,
+    /// The peer does not implement the request.
+    NotSupported,
+    /// The sender of the request is not allowed to make it.
+    PermissionDenied,
+    /// Too many requests were made, try again later.
+    RateLimited,
+    /// The request was not answered in time.
+    Timeout,
+    /// The peer is overloaded, e.g. its request queue is full.
+    Busy,
+    /// The peer the request is meant for, such as a bot's platform, is not
+    /// connected.
+    PeerOffline,
+    /// A failure without a more specific code, such as the plain text errors
+    /// of older peers, or a code this build does not know.
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+/// The error result of a datapack.
+///
+/// Older peers send a plain message instead, which is read as
+/// [`ErrorCode::Other`].
+#[typeshare]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Error)]
+#[error("{message}")]
+pub struct DataError {
+    pub code:    ErrorCode,
+    pub message: String,
+    /// Anything else the peer reports, such as the status of a platform API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[typeshare(serialized_as = "Option<any>")]
+    pub details: Option<Value>,
+}
+
+impl DataError {
+    pub fn new(code: ErrorCode, message: impl Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            details: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+impl From<String> for DataError {
+    fn from(message: String) -> Self {
+        Self {
+            code: ErrorCode::Other,
+            message,
+            details: None,
+        }
+    }
+}
+
+impl From<&str> for DataError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_owned())
+    }
+}
+
+impl<'de> Deserialize<'de> for DataError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Message(String),
+            Structured {
+                #[serde(default)]
+                code:    ErrorCode,
+                message: String,
+                #[serde(default)]
+                details: Option<Value>,
+            },
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Message(message) => Self::from(message),
+            Repr::Structured {
+                code,
+                message,
+                details,
+            } => Self {
+                code,
+                message,
+                details,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{DataError, ErrorCode};
+
+    #[test]
+    fn reads_plain_and_structured_errors() {
+        let error =
+            DataError::new(ErrorCode::NotSupported, "no").with_details(json!({ "retcode": 1404 }));
+        let bytes = rmp_serde::to_vec_named(&error).unwrap();
+        assert_eq!(rmp_serde::from_slice::<DataError>(&bytes).unwrap(), error);
+
+        let bytes = rmp_serde::to_vec_named("legacy").unwrap();
+        let legacy: DataError = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(legacy, DataError::from("legacy"));
+
+        let unknown: DataError =
+            serde_json::from_value(json!({ "code": "on_fire", "message": "hot" })).unwrap();
+        assert_eq!(unknown.code, ErrorCode::Other);
+    }
+}
@@ -2,8 +2,13 @@
 //!
 //! This crate provides core networking abstractions including:
 //! - [`channel`]: Channel management for message passing
+//! - [`compression`]: Negotiable per-frame compression
 //! - [`datapack`]: Structured data packet serialization
+//! - [`error`]: Structured errors carried by datapacks
+//! - [`payload`]: Lazily decoded payloads
 //! - [`peer`]: Peer connection management
+//! - [`security`]: Pre-shared key authentication and encryption for peers
+//! - [`trace`]: Trace context propagated across peers
 //! - [`util`]: Shared utilities
 //!
 //! # Features
@@ -12,9 +17,16 @@
 #![allow(clippy::cast_possible_truncation)]
 
 pub mod channel;
+pub mod compression;
 pub mod datapack;
+pub mod error;
+pub mod payload;
 pub mod peer;
+#[cfg(feature = "security")]
+pub mod security;
+pub mod trace;
 pub mod util;
 
+pub use bytes::Bytes;
 pub use rmp_serde::{decode::Error as DecodeError, encode::Error as EncodeError};
 pub use serde_json::{Error as ValueError, Value, from_value, to_value};
@@ -0,0 +1,247 @@
+//! Lazily decoded payloads.
+//!
+//! A [`RawPayload`] keeps the msgpack encoding of a payload exactly as it was
+//! received. It is only deserialized when somebody asks for a concrete type,
+//! and is written back out byte for byte, so the host can route and forward
+//! datapacks without ever looking inside the payload.
+
+use std::{fmt, io::Read};
+
+use bytes::Bytes;
+use rmp::Marker;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::{DecodeError, EncodeError};
+
+const NULL: &[u8] = &[0xc0];
+
+/// The msgpack encoding of a single payload value.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RawPayload(Bytes);
+
+impl RawPayload {
+    /// The `nil` payload.
+    #[must_use]
+    pub const fn null() -> Self {
+        Self(Bytes::from_static(NULL))
+    }
+
+    /// Encodes `value` into a payload.
+    ///
+    /// # Errors
+    /// Returns an error if `value` cannot be serialized.
+    pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Self, EncodeError> {
+        rmp_serde::to_vec_named(value).map(|v| Self(v.into()))
+    }
+
+    /// Wraps bytes known to hold exactly one msgpack value.
+    pub(crate) const fn from_bytes_unchecked(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+
+    /// Wraps already encoded bytes, checking that they hold exactly one
+    /// msgpack value.
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` is not a single, complete msgpack value.
+    pub fn from_msgpack(bytes: Bytes) -> Result<Self, DecodeError> {
+        let mut rest = bytes.as_ref();
+        skip_value(&mut rest)?;
+        if !rest.is_empty() {
+            return Err(DecodeError::Syntax(
+                "trailing bytes after payload".to_owned(),
+            ));
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Deserializes the payload straight into `T`.
+    ///
+    /// # Errors
+    /// Returns an error if the payload does not match `T`.
+    pub fn decode<'de, T: Deserialize<'de>>(&'de self) -> Result<T, DecodeError> {
+        rmp_serde::from_slice(&self.0)
+    }
+
+    /// Decodes the payload into a dynamically typed [`crate::Value`].
+    ///
+    /// # Errors
+    /// Returns an error if the payload cannot be represented as a `Value`.
+    pub fn to_value(&self) -> Result<crate::Value, DecodeError> {
+        self.decode()
+    }
+
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        self.0.as_ref() == NULL
+    }
+
+    /// The encoded payload.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Default for RawPayload {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl fmt::Debug for RawPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_value() {
+            Ok(value) => fmt::Debug::fmt(&value, f),
+            Err(_) => write!(f, "RawPayload({} bytes)", self.0.len()),
+        }
+    }
+}
+
+impl From<crate::Value> for RawPayload {
+    fn from(value: crate::Value) -> Self {
+        Self::encode(&value).unwrap_or_default()
+    }
+}
+
+/// Serializes the payload into any format by transcoding the stored msgpack,
+/// without an intermediate [`crate::Value`].
+impl Serialize for RawPayload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut de = rmp_serde::Deserializer::from_read_ref(self.0.as_ref());
+        serde_transcode::transcode(&mut de, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawPayload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut buf = Vec::new();
+        let mut ser = rmp_serde::Serializer::new(&mut buf).with_struct_map();
+        serde_transcode::transcode(deserializer, &mut ser).map_err(D::Error::custom)?;
+        Ok(Self(buf.into()))
+    }
+}
+
+/// Advances `rd` past one complete msgpack value without decoding it.
+///
+/// Nested containers are walked iteratively, so hostile nesting depth cannot
+/// overflow the stack.
+pub(crate) fn skip_value(rd: &mut &[u8]) -> Result<(), DecodeError> {
+    let mut pending: u64 = 1;
+    while pending > 0 {
+        pending -= 1;
+        let marker = rmp::decode::read_marker(rd)?;
+        let len = match marker {
+            Marker::Reserved => return Err(DecodeError::TypeMismatch(marker)),
+            Marker::FixPos(_) | Marker::FixNeg(_) | Marker::Null | Marker::True | Marker::False => {
+                0
+            }
+            Marker::U8 | Marker::I8 => 1,
+            Marker::U16 | Marker::I16 | Marker::FixExt1 => 2,
+            Marker::U32 | Marker::I32 | Marker::F32 => 4,
+            Marker::U64 | Marker::I64 | Marker::F64 => 8,
+            Marker::FixStr(len) => u64::from(len),
+            Marker::Str8 | Marker::Bin8 => u64::from(read_u8(rd)?),
+            Marker::Str16 | Marker::Bin16 => u64::from(read_u16(rd)?),
+            Marker::Str32 | Marker::Bin32 => u64::from(read_u32(rd)?),
+            Marker::FixExt2 => 3,
+            Marker::FixExt4 => 5,
+            Marker::FixExt8 => 9,
+            Marker::FixExt16 => 17,
+            Marker::Ext8 => u64::from(read_u8(rd)?) + 1,
+            Marker::Ext16 => u64::from(read_u16(rd)?) + 1,
+            Marker::Ext32 => u64::from(read_u32(rd)?) + 1,
+            Marker::FixArray(len) => {
+                pending += u64::from(len);
+                0
+            }
+            Marker::Array16 => {
+                pending += u64::from(read_u16(rd)?);
+                0
+            }
+            Marker::Array32 => {
+                pending += u64::from(read_u32(rd)?);
+                0
+            }
+            Marker::FixMap(len) => {
+                pending += u64::from(len) * 2;
+                0
+            }
+            Marker::Map16 => {
+                pending += u64::from(read_u16(rd)?) * 2;
+                0
+            }
+            Marker::Map32 => {
+                pending += u64::from(read_u32(rd)?) * 2;
+                0
+            }
+        };
+        advance(rd, len)?;
+    }
+    Ok(())
+}
+
+fn advance(rd: &mut &[u8], len: u64) -> Result<(), DecodeError> {
+    let len = usize::try_from(len)?;
+    let Some(rest) = rd.get(len..) else {
+        return Err(unexpected_eof());
+    };
+    *rd = rest;
+    Ok(())
+}
+
+fn read_array<const N: usize>(rd: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+    let mut buf = [0; N];
+    rd.read_exact(&mut buf).map_err(DecodeError::InvalidDataRead)?;
+    Ok(buf)
+}
+
+fn read_u8(rd: &mut &[u8]) -> Result<u8, DecodeError> {
+    read_array::<1>(rd).map(|[b]| b)
+}
+
+fn read_u16(rd: &mut &[u8]) -> Result<u16, DecodeError> {
+    read_array(rd).map(u16::from_be_bytes)
+}
+
+fn read_u32(rd: &mut &[u8]) -> Result<u32, DecodeError> {
+    read_array(rd).map(u32::from_be_bytes)
+}
+
+fn unexpected_eof() -> DecodeError {
+    DecodeError::InvalidDataRead(std::io::ErrorKind::UnexpectedEof.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn skip_nested_value() {
+        let value = json!({ "a": [1, -2, 3.5, "text", null, { "b": true }], "c": "x".repeat(300) });
+        let mut bytes = rmp_serde::to_vec_named(&value).unwrap();
+        bytes.push(0xc3);
+        let mut rest = bytes.as_slice();
+        skip_value(&mut rest).unwrap();
+        assert_eq!(rest, [0xc3]);
+    }
+
+    #[test]
+    fn decode_without_value() {
+        #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+        struct Ping {
+            seq: u32,
+        }
+        let payload = RawPayload::encode(&Ping { seq: 7 }).unwrap();
+        assert_eq!(payload.decode::<Ping>().unwrap(), Ping { seq: 7 });
+        assert_eq!(serde_json::to_value(&payload).unwrap(), json!({ "seq": 7 }));
+        assert!(RawPayload::from_msgpack(payload.as_bytes().slice(..1)).is_err());
+    }
+}
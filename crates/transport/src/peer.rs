@@ -1,21 +1,35 @@
-use either::Either;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::{
+    fmt, io,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream, unix};
 use tokio::{
-    io::{AsyncRead, AsyncWrite, Stdin, Stdout, stdin, stdout},
-    pin,
+    io::{AsyncRead, AsyncWrite, ReadBuf, Stdin, Stdout, stdin, stdout},
+    net::{TcpListener, TcpStream, tcp},
     process::{Child, ChildStdin, ChildStdout},
 };
 use triomphe::Arc;
 
-/// A peer represents a communication endpoint, either as a child process or the
-/// current process.
+#[cfg(feature = "security")]
+use crate::security::{self, PresharedKey, Role, SecureReader, SecureWriter, SecurityError};
+
+/// A peer represents a communication endpoint: a child process, the current
+/// process, or a socket connection.
 ///
 /// It encapsulates the input and output streams (`incoming` and `outgoing`) and
-/// optionally manages a child process (`process`). The streams are wrapped in
-/// `Either` to handle both child process streams and standard I/O streams.
+/// optionally manages a child process (`process`).
 pub struct Peer {
     process:  Option<Child>,
-    incoming: Either<ChildStdout, Stdin>,
-    outgoing: Either<ChildStdin, Stdout>,
+    incoming: Incoming,
+    outgoing: Outgoing,
 }
 
 /// A reader for a peer's incoming data stream.
@@ -25,7 +39,7 @@ pub struct Peer {
 /// the reader is active.
 pub struct Reader {
     _process: Option<Arc<Child>>,
-    incoming: Either<ChildStdout, Stdin>,
+    incoming: Incoming,
 }
 
 /// A writer for a peer's outgoing data stream.
@@ -35,7 +49,27 @@ pub struct Reader {
 /// the writer is active.
 pub struct Writer {
     _process: Option<Arc<Child>>,
-    outgoing: Either<ChildStdin, Stdout>,
+    outgoing: Outgoing,
+}
+
+enum Incoming {
+    Child(ChildStdout),
+    Stdio(Stdin),
+    Tcp(tcp::OwnedReadHalf),
+    #[cfg(unix)]
+    Unix(unix::OwnedReadHalf),
+    #[cfg(feature = "security")]
+    Secure(Box<SecureReader<Self>>),
+}
+
+enum Outgoing {
+    Child(ChildStdin),
+    Stdio(Stdout),
+    Tcp(tcp::OwnedWriteHalf),
+    #[cfg(unix)]
+    Unix(unix::OwnedWriteHalf),
+    #[cfg(feature = "security")]
+    Secure(Box<SecureWriter<Self>>),
 }
 
 impl Default for Peer {
@@ -56,8 +90,8 @@ impl Peer {
     pub fn new() -> Self {
         Self {
             process:  None,
-            incoming: Either::Right(stdin()),
-            outgoing: Either::Right(stdout()),
+            incoming: Incoming::Stdio(stdin()),
+            outgoing: Outgoing::Stdio(stdout()),
         }
     }
 
@@ -109,8 +143,70 @@ impl Peer {
 
         Ok(Self {
             process:  Some(child),
-            incoming: Either::Left(stdout),
-            outgoing: Either::Left(stdin),
+            incoming: Incoming::Child(stdout),
+            outgoing: Outgoing::Child(stdin),
+        })
+    }
+
+    /// Creates a new `Peer` instance from a connected TCP stream.
+    #[must_use]
+    pub fn from_tcp(stream: TcpStream) -> Self {
+        let (incoming, outgoing) = stream.into_split();
+        Self {
+            process:  None,
+            incoming: Incoming::Tcp(incoming),
+            outgoing: Outgoing::Tcp(outgoing),
+        }
+    }
+
+    /// Creates a new `Peer` instance from a connected Unix domain socket.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn from_unix(stream: UnixStream) -> Self {
+        let (incoming, outgoing) = stream.into_split();
+        Self {
+            process:  None,
+            incoming: Incoming::Unix(incoming),
+            outgoing: Outgoing::Unix(outgoing),
+        }
+    }
+
+    /// Connects to the peer listening on `addr`.
+    ///
+    /// # Errors
+    /// Returns an `std::io::Error` if the connection could not be established,
+    /// or if `addr` is a Unix socket on a platform without Unix sockets.
+    pub async fn connect(addr: &PeerAddr) -> Result<Self, io::Error> {
+        match addr {
+            PeerAddr::Tcp(addr) => Ok(Self::from_tcp(TcpStream::connect(addr).await?)),
+            #[cfg(unix)]
+            PeerAddr::Unix(path) => Ok(Self::from_unix(UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            PeerAddr::Unix(_) => Err(unix_unsupported()),
+        }
+    }
+
+    /// Authenticates the other end with a pre-shared key and encrypts all
+    /// further traffic, see [`crate::security`].
+    ///
+    /// Both ends must call this with the same key and different roles before
+    /// exchanging anything else.
+    ///
+    /// # Errors
+    /// Returns [`SecurityError::AuthenticationFailed`] if the other end does
+    /// not hold `key`, and other errors if the handshake could not complete.
+    #[cfg(feature = "security")]
+    pub async fn secure(self, key: &PresharedKey, role: Role) -> Result<Self, SecurityError> {
+        let Self {
+            process,
+            mut incoming,
+            mut outgoing,
+        } = self;
+        let (sealer, opener) = security::handshake(&mut incoming, &mut outgoing, key, role).await?;
+        Ok(Self {
+            process,
+            incoming: Incoming::Secure(Box::new(SecureReader::new(incoming, opener))),
+            outgoing: Outgoing::Secure(Box::new(SecureWriter::new(outgoing, sealer))),
         })
     }
 
@@ -146,158 +242,285 @@ impl TryFrom<Child> for Peer {
     }
 }
 
+impl From<TcpStream> for Peer {
+    fn from(value: TcpStream) -> Self {
+        Self::from_tcp(value)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Peer {
+    fn from(value: UnixStream) -> Self {
+        Self::from_unix(value)
+    }
+}
+
+/// The address of a socket peer.
+///
+/// Written as `tcp://<host>:<port>` or `unix://<path>`, e.g.
+/// `tcp://127.0.0.1:7650` or `unix:///run/sithra/echo.sock`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PeerAddr {
+    /// A TCP address, resolved when connecting or binding.
+    Tcp(String),
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl FromStr for PeerAddr {
+    type Err = PeerAddrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            if addr.is_empty() {
+                return Err(PeerAddrError(s.to_owned()));
+            }
+            Ok(Self::Tcp(addr.to_owned()))
+        } else if let Some(path) = s.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err(PeerAddrError(s.to_owned()));
+            }
+            Ok(Self::Unix(path.into()))
+        } else {
+            Err(PeerAddrError(s.to_owned()))
+        }
+    }
+}
+
+impl TryFrom<String> for PeerAddr {
+    type Error = PeerAddrError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PeerAddr> for String {
+    fn from(value: PeerAddr) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for PeerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "tcp://{addr}"),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// Error returned when a string is not a valid [`PeerAddr`].
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid peer address `{0}`, expected `tcp://<host>:<port>` or `unix://<path>`")]
+pub struct PeerAddrError(String);
+
+/// A listening socket that accepts socket peers.
+pub enum PeerListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl PeerListener {
+    /// Binds a listener to `addr`.
+    ///
+    /// A stale Unix socket file left behind at `addr` is removed first.
+    ///
+    /// # Errors
+    /// Returns an `std::io::Error` if the address could not be bound, if a
+    /// Unix socket path is taken by something other than a socket, or if
+    /// `addr` is a Unix socket on a platform without Unix sockets.
+    pub async fn bind(addr: &PeerAddr) -> Result<Self, io::Error> {
+        match addr {
+            PeerAddr::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            PeerAddr::Unix(path) => {
+                if let Ok(metadata) = tokio::fs::symlink_metadata(path).await {
+                    if !metadata.file_type().is_socket() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!("{} exists and is not a socket", path.display()),
+                        ));
+                    }
+                    if UnixStream::connect(path).await.is_err() {
+                        tokio::fs::remove_file(path).await?;
+                    }
+                }
+                Ok(Self::Unix(UnixListener::bind(path)?))
+            }
+            #[cfg(not(unix))]
+            PeerAddr::Unix(_) => Err(unix_unsupported()),
+        }
+    }
+
+    /// Waits for the next peer to connect.
+    ///
+    /// # Errors
+    /// Returns an `std::io::Error` if accepting the connection failed.
+    pub async fn accept(&self) -> Result<Peer, io::Error> {
+        match self {
+            Self::Tcp(listener) => Ok(Peer::from_tcp(listener.accept().await?.0)),
+            #[cfg(unix)]
+            Self::Unix(listener) => Ok(Peer::from_unix(listener.accept().await?.0)),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    )
+}
+
+macro_rules! dispatch {
+    ($stream:expr, $ty:ident, $method:ident($($arg:expr),*)) => {
+        match $stream {
+            $ty::Child(io) => Pin::new(io).$method($($arg),*),
+            $ty::Stdio(io) => Pin::new(io).$method($($arg),*),
+            $ty::Tcp(io) => Pin::new(io).$method($($arg),*),
+            #[cfg(unix)]
+            $ty::Unix(io) => Pin::new(io).$method($($arg),*),
+            #[cfg(feature = "security")]
+            $ty::Secure(io) => Pin::new(io.as_mut()).$method($($arg),*),
+        }
+    };
+}
+
+impl AsyncRead for Incoming {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), Incoming, poll_read(cx, buf))
+    }
+}
+
+impl AsyncWrite for Outgoing {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        dispatch!(self.get_mut(), Outgoing, poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), Outgoing, poll_flush(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), Outgoing, poll_shutdown(cx))
+    }
+}
+
 impl AsyncRead for Reader {
     /// Polls the underlying stream for data to read.
     ///
-    /// This delegates to either the child process's `stdout` or the current
-    /// process's `stdin`, depending on the configuration of the `Reader`.
+    /// This delegates to the child process's `stdout`, the current process's
+    /// `stdin` or the socket, depending on the configuration of the `Reader`.
     fn poll_read(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-        buf: &mut tokio::io::ReadBuf<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().incoming {
-            Either::Left(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_read(cx, buf)
-            }
-            Either::Right(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_read(cx, buf)
-            }
-        }
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().incoming).poll_read(cx, buf)
     }
 }
 
 impl AsyncWrite for Writer {
     /// Polls the underlying stream for readiness to write data.
     ///
-    /// This delegates to either the child process's `stdin` or the current
-    /// process's `stdout`, depending on the configuration of the `Writer`.
+    /// This delegates to the child process's `stdin`, the current process's
+    /// `stdout` or the socket, depending on the configuration of the `Writer`.
     fn poll_write(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
         buf: &[u8],
-    ) -> std::task::Poll<std::io::Result<usize>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_write(cx, buf)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_write(cx, buf)
-            }
-        }
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_write(cx, buf)
     }
 
-    fn poll_flush(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_flush(cx)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_flush(cx)
-            }
-        }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_flush(cx)
     }
 
-    fn poll_shutdown(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_shutdown(cx)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_shutdown(cx)
-            }
-        }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_shutdown(cx)
     }
 }
 
 impl AsyncRead for Peer {
     /// Polls the underlying stream for data to read.
     ///
-    /// This delegates to either the child process's `stdout` or the current
-    /// process's `stdin`, depending on the configuration of the `Peer`.
+    /// This delegates to the child process's `stdout`, the current process's
+    /// `stdin` or the socket, depending on the configuration of the `Peer`.
     fn poll_read(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-        buf: &mut tokio::io::ReadBuf<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().incoming {
-            Either::Left(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_read(cx, buf)
-            }
-            Either::Right(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_read(cx, buf)
-            }
-        }
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().incoming).poll_read(cx, buf)
     }
 }
 
 impl AsyncWrite for Peer {
     /// Polls the underlying stream for readiness to write data.
     ///
-    /// This delegates to either the child process's `stdin` or the current
-    /// process's `stdout`, depending on the configuration of the `Peer`.
+    /// This delegates to the child process's `stdin`, the current process's
+    /// `stdout` or the socket, depending on the configuration of the `Peer`.
     fn poll_write(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
         buf: &[u8],
-    ) -> std::task::Poll<std::io::Result<usize>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_write(cx, buf)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_write(cx, buf)
-            }
-        }
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_write(cx, buf)
     }
 
-    fn poll_flush(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_flush(cx)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_flush(cx)
-            }
-        }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_flush(cx)
     }
 
-    fn poll_shutdown(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<std::io::Result<()>> {
-        match self.get_mut().outgoing {
-            Either::Left(ref mut stdin) => {
-                pin!(stdin);
-                stdin.poll_shutdown(cx)
-            }
-            Either::Right(ref mut stdout) => {
-                pin!(stdout);
-                stdout.poll_shutdown(cx)
-            }
-        }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().outgoing).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{PeerAddr, PeerListener};
+
+    #[test]
+    fn parse_peer_addr() {
+        let tcp: PeerAddr = "tcp://127.0.0.1:7650".parse().unwrap();
+        assert_eq!(tcp, PeerAddr::Tcp("127.0.0.1:7650".to_owned()));
+        assert_eq!(tcp.to_string(), "tcp://127.0.0.1:7650");
+
+        let unix: PeerAddr = "unix:///run/sithra/echo.sock".parse().unwrap();
+        assert_eq!(unix, PeerAddr::Unix("/run/sithra/echo.sock".into()));
+        assert_eq!(unix.to_string(), "unix:///run/sithra/echo.sock");
+
+        assert!("127.0.0.1:7650".parse::<PeerAddr>().is_err());
+        assert!("tcp://".parse::<PeerAddr>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn bind_keeps_non_socket_files() {
+        let path = std::env::temp_dir().join(format!("sithra-bind-{}", std::process::id()));
+        std::fs::write(&path, "not a socket").unwrap();
+        let result = PeerListener::bind(&PeerAddr::Unix(path.clone())).await;
+        assert_eq!(
+            result.err().map(|err| err.kind()),
+            Some(io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,380 @@
+//! Pre-shared key authentication and encryption for peer connections.
+//!
+//! Both ends of a connection hold the same [`PresharedKey`]. The handshake
+//! exchanges a fresh nonce from each side and proves knowledge of the key
+//! with an HMAC over both nonces, so neither the key nor a reusable proof
+//! crosses the wire. Traffic keys for each direction are derived from the key
+//! and the nonces with HKDF, and every record is sealed with
+//! ChaCha20-Poly1305 under a counter nonce.
+//!
+//! A record is a `u32` big-endian length followed by the sealed bytes, which
+//! end in the authentication tag.
+//!
+//! Use [`Peer::secure`](crate::peer::Peer::secure) to protect a connection.
+//! The key is not stretched, so it should be long and random rather than a
+//! password.
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, BufMut, BytesMut};
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    hkdf, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// Sent first by both ends, followed by their nonce.
+pub const MAGIC: &[u8; 8] = b"SITHPSK1";
+
+const HELLO_NONCE_LEN: usize = 32;
+const PROOF_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+/// Largest plaintext sealed into a single record.
+const MAX_RECORD: usize = 16 * 1024;
+
+/// A key shared by the host and a plugin.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub struct PresharedKey(Vec<u8>);
+
+impl PresharedKey {
+    #[must_use]
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self(key.into())
+    }
+}
+
+impl From<String> for PresharedKey {
+    fn from(value: String) -> Self {
+        Self(value.into_bytes())
+    }
+}
+
+impl From<&str> for PresharedKey {
+    fn from(value: &str) -> Self {
+        Self(value.as_bytes().to_vec())
+    }
+}
+
+impl fmt::Debug for PresharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PresharedKey(..)")
+    }
+}
+
+/// Which end of the connection we are. Both ends must pick different roles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Host,
+    Plugin,
+}
+
+impl Role {
+    const fn label(self) -> &'static [u8] {
+        match self {
+            Self::Host => b"sithra host",
+            Self::Plugin => b"sithra plugin",
+        }
+    }
+
+    const fn other(self) -> Self {
+        match self {
+            Self::Host => Self::Plugin,
+            Self::Plugin => Self::Host,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SecurityError {
+    #[error("I/O error during handshake: {0}")]
+    Io(#[from] io::Error),
+    #[error("Peer does not speak the pre-shared key protocol")]
+    Protocol,
+    #[error("Peer failed to authenticate")]
+    AuthenticationFailed,
+    #[error("Cryptographic failure")]
+    Crypto,
+}
+
+impl From<ring::error::Unspecified> for SecurityError {
+    fn from(_value: ring::error::Unspecified) -> Self {
+        Self::Crypto
+    }
+}
+
+/// One direction of an established connection.
+pub(crate) struct Cipher {
+    key:     LessSafeKey,
+    counter: u64,
+}
+
+impl Cipher {
+    fn new(prk: &hkdf::Prk, label: &[u8]) -> Result<Self, SecurityError> {
+        let key = UnboundKey::from(prk.expand(&[b"traffic ", label], &CHACHA20_POLY1305)?);
+        Ok(Self {
+            key:     LessSafeKey::new(key),
+            counter: 0,
+        })
+    }
+
+    fn nonce(&mut self) -> Nonce {
+        let mut nonce = [0; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter += 1;
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    fn seal(&mut self, record: &mut Vec<u8>) -> Result<(), ring::error::Unspecified> {
+        let nonce = self.nonce();
+        self.key.seal_in_place_append_tag(nonce, Aad::empty(), record)
+    }
+
+    fn open<'a>(&mut self, record: &'a mut [u8]) -> Result<&'a mut [u8], ring::error::Unspecified> {
+        let nonce = self.nonce();
+        self.key.open_in_place(nonce, Aad::empty(), record)
+    }
+}
+
+/// Authenticates the other end and derives the ciphers for both directions,
+/// as `(outgoing, incoming)`.
+pub(crate) async fn handshake<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &PresharedKey,
+    role: Role,
+) -> Result<(Cipher, Cipher), SecurityError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut ours = [0; HELLO_NONCE_LEN];
+    SystemRandom::new().fill(&mut ours)?;
+    writer.write_all(MAGIC).await?;
+    writer.write_all(&ours).await?;
+    writer.flush().await?;
+
+    let mut hello = [0; MAGIC.len() + HELLO_NONCE_LEN];
+    reader.read_exact(&mut hello).await?;
+    let (magic, theirs) = hello.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(SecurityError::Protocol);
+    }
+    let transcript = match role {
+        Role::Host => [&ours[..], theirs].concat(),
+        Role::Plugin => [theirs, &ours[..]].concat(),
+    };
+
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &transcript).extract(&key.0);
+    let auth = hmac::Key::from(prk.expand(&[b"auth"], hmac::HMAC_SHA256)?);
+    let proof = hmac::sign(&auth, &[role.label(), &transcript].concat());
+    writer.write_all(proof.as_ref()).await?;
+    writer.flush().await?;
+
+    let mut their_proof = [0; PROOF_LEN];
+    reader.read_exact(&mut their_proof).await?;
+    hmac::verify(
+        &auth,
+        &[role.other().label(), &transcript].concat(),
+        &their_proof,
+    )
+    .map_err(|_| SecurityError::AuthenticationFailed)?;
+
+    Ok((
+        Cipher::new(&prk, role.label())?,
+        Cipher::new(&prk, role.other().label())?,
+    ))
+}
+
+/// Decrypts the records read from `R`.
+pub(crate) struct SecureReader<R> {
+    inner:     R,
+    cipher:    Cipher,
+    encrypted: BytesMut,
+    plain:     BytesMut,
+}
+
+impl<R> SecureReader<R> {
+    pub(crate) fn new(inner: R, cipher: Cipher) -> Self {
+        Self {
+            inner,
+            cipher,
+            encrypted: BytesMut::new(),
+            plain: BytesMut::new(),
+        }
+    }
+
+    /// Opens the next complete record, if one was read.
+    fn open_record(&mut self) -> io::Result<bool> {
+        if self.encrypted.len() < 4 {
+            return Ok(false);
+        }
+        let len = u32::from_be_bytes(self.encrypted[..4].try_into().unwrap_or_default()) as usize;
+        if !(TAG_LEN..=MAX_RECORD + TAG_LEN).contains(&len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid record length",
+            ));
+        }
+        if self.encrypted.len() < 4 + len {
+            return Ok(false);
+        }
+        self.encrypted.advance(4);
+        let mut record = self.encrypted.split_to(len);
+        let plain_len = self
+            .cipher
+            .open(&mut record)
+            .map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "record failed to authenticate")
+            })?
+            .len();
+        record.truncate(plain_len);
+        self.plain = record;
+        Ok(true)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for SecureReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.plain.is_empty() {
+                let len = this.plain.len().min(buf.remaining());
+                buf.put_slice(&this.plain[..len]);
+                this.plain.advance(len);
+                return Poll::Ready(Ok(()));
+            }
+            if this.open_record()? {
+                continue;
+            }
+            let mut chunk = [0; 8192];
+            let mut read = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                if this.encrypted.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            this.encrypted.extend_from_slice(read.filled());
+        }
+    }
+}
+
+/// Encrypts everything written to `W` into records.
+pub(crate) struct SecureWriter<W> {
+    inner:   W,
+    cipher:  Cipher,
+    pending: BytesMut,
+}
+
+impl<W> SecureWriter<W> {
+    pub(crate) fn new(inner: W, cipher: Cipher) -> Self {
+        Self {
+            inner,
+            cipher,
+            pending: BytesMut::new(),
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> SecureWriter<W> {
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.advance(written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for SecureWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        let len = buf.len().min(MAX_RECORD);
+        let mut record = buf[..len].to_vec();
+        this.cipher
+            .seal(&mut record)
+            .map_err(|_| io::Error::other("failed to seal record"))?;
+        this.pending.put_u32(record.len() as u32);
+        this.pending.extend_from_slice(&record);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::UnixStream;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    use super::*;
+    use crate::{
+        datapack::{DataPack, DataPackCodec, RequestDataPack},
+        peer::Peer,
+    };
+
+    #[tokio::test]
+    async fn authenticated_peers_exchange_datapacks() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let key = PresharedKey::from("correct horse battery staple");
+        let (host, plugin) = tokio::join!(
+            Peer::from_unix(a).secure(&key, Role::Host),
+            Peer::from_unix(b).secure(&key, Role::Plugin),
+        );
+        let (writer, _reader) = host.unwrap().split();
+        let (_writer, reader) = plugin.unwrap().split();
+        let mut writer = FramedWrite::new(writer, DataPackCodec::new());
+        let mut reader = FramedRead::new(reader, DataPackCodec::new());
+
+        let large = "x".repeat(MAX_RECORD * 2);
+        let request = RequestDataPack::default().path("/secret").payload(&large);
+        writer.send(DataPack::from(request.clone())).await.unwrap();
+        let received = reader.next().await.unwrap().unwrap();
+        assert_eq!(received.correlation, request.correlation());
+        assert_eq!(received.payload::<String>().unwrap(), large);
+    }
+
+    #[tokio::test]
+    async fn wrong_key_is_rejected() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (host_key, guess) = (PresharedKey::from("host key"), PresharedKey::from("guess"));
+        let (host, plugin) = tokio::join!(
+            Peer::from_unix(a).secure(&host_key, Role::Host),
+            Peer::from_unix(b).secure(&guess, Role::Plugin),
+        );
+        assert!(matches!(host, Err(SecurityError::AuthenticationFailed)));
+        assert!(matches!(plugin, Err(SecurityError::AuthenticationFailed)));
+    }
+}
@@ -0,0 +1,48 @@
+//! Trace context carried in the datapack header.
+//!
+//! Every request belongs to a trace, started by whoever sent the first
+//! request without one, e.g. an adapter posting an incoming chat message.
+//! Requests sent while handling another request continue its trace with a
+//! new span, so a whole conversation turn shares one `trace_id`.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct TraceContext {
+    pub trace_id:  Ulid,
+    pub span_id:   Ulid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Ulid>,
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    #[must_use]
+    pub fn root() -> Self {
+        let id = Ulid::new();
+        Self {
+            trace_id:  id,
+            span_id:   id,
+            parent_id: None,
+        }
+    }
+
+    /// Creates a new span in the same trace, with this span as its parent.
+    #[must_use]
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id:  self.trace_id,
+            span_id:   Ulid::new(),
+            parent_id: Some(self.span_id),
+        }
+    }
+}
+
+impl Display for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.trace_id, self.span_id)
+    }
+}
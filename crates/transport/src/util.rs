@@ -2,13 +2,18 @@
 //!
 //! Provides helper functions for creating framed transports and chunking data.
 
+use std::io;
+
 use bytes::BytesMut;
-use tokio::process::Child;
+use tokio::{
+    net::{TcpStream, ToSocketAddrs},
+    process::Child,
+};
 use tokio_util::codec::Framed;
 
 use crate::{
     datapack::{DataPackCodec, RawDataPackCodec},
-    peer::Peer,
+    peer::{Peer, PeerAddr},
 };
 
 pub type FramedPeer = Framed<Peer, DataPackCodec>;
@@ -38,6 +43,34 @@ pub fn connect(child: Child) -> Result<Framed<Peer, DataPackCodec>, Child> {
     Ok(Framed::new(peer, codec))
 }
 
+/// Connects to a socket peer and returns a framed transport.
+///
+/// # Errors
+/// Returns an error if the connection could not be established.
+pub async fn connect_to(addr: &PeerAddr) -> Result<FramedPeer, io::Error> {
+    let peer = Peer::connect(addr).await?;
+    Ok(framed(peer))
+}
+
+/// Connects to a TCP peer and returns a framed transport.
+///
+/// # Errors
+/// Returns an error if the connection could not be established.
+pub async fn connect_tcp(addr: impl ToSocketAddrs) -> Result<FramedPeer, io::Error> {
+    let stream = TcpStream::connect(addr).await?;
+    Ok(framed(Peer::from_tcp(stream)))
+}
+
+/// Connects to a Unix domain socket peer and returns a framed transport.
+///
+/// # Errors
+/// Returns an error if the connection could not be established.
+#[cfg(unix)]
+pub async fn connect_unix(path: impl AsRef<std::path::Path>) -> Result<FramedPeer, io::Error> {
+    let stream = tokio::net::UnixStream::connect(path).await?;
+    Ok(framed(Peer::from_unix(stream)))
+}
+
 /// Connects to a child process and returns a framed transport using raw data
 /// packing.
 ///
@@ -1,14 +1,78 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
-use sithra_transport::{Value, ValueError};
+use sithra_server::queue::ServerQueues;
+use sithra_transport::{Value, ValueError, compression::Compression};
 use thiserror::Error;
 
+use crate::role::Roles;
+
+/// Revision of the host/plugin protocol implemented by this crate.
+///
+/// Bump this whenever a change to the wire format or the handshake would make
+/// older peers misbehave.
+///
+/// Revision 2 sends errors as a structured
+/// [`DataError`](sithra_transport::error::DataError) instead of a string.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest protocol revision this crate still talks to.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+/// Returns `true` if a peer speaking `version` can be talked to.
+#[must_use]
+pub const fn is_compatible(version: u32) -> bool {
+    version >= MIN_PROTOCOL_VERSION && version <= PROTOCOL_VERSION
+}
+
+/// An optional protocol feature a peer may implement.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Per-frame compression, see [`sithra_transport::compression`].
+    Compression,
+    /// Cancelling in-flight requests.
+    Cancellation,
+    /// Multiple responses to a single request.
+    Streaming,
+    /// A capability this build does not know about.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl Capability {
+    /// Capabilities implemented by this build.
+    #[must_use]
+    pub fn supported() -> Vec<Self> {
+        let mut capabilities = Vec::new();
+        if !Compression::supported().is_empty() {
+            capabilities.push(Self::Compression);
+        }
+        capabilities.push(Self::Cancellation);
+        capabilities
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Initialize<C = ()> {
-    pub config:    C,
-    pub id:        String,
-    pub data_path: String,
+    pub config:           C,
+    pub id:               String,
+    pub data_path:        String,
+    /// Protocol revision spoken by the host. `0` for hosts predating the
+    /// handshake.
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// Optional features implemented by the host.
+    #[serde(default)]
+    pub capabilities:     Vec<Capability>,
+    /// Frame compressions the host is able to decode, in order of preference.
+    #[serde(default)]
+    pub compression:      Vec<Compression>,
+    /// Bounds for the queues of the plugin's server.
+    #[serde(default)]
+    pub queues:           ServerQueues,
+    /// The users holding roles, see [`Roles`].
+    #[serde(default)]
+    pub roles:            Roles,
 }
 
 impl<C> Initialize<C> {
@@ -17,8 +81,34 @@ impl<C> Initialize<C> {
             config,
             id: name.to_string(),
             data_path: data_path.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capability::supported(),
+            compression: Vec::new(),
+            queues: ServerQueues::default(),
+            roles: Roles::default(),
         }
     }
+
+    /// Offers the given frame compressions to the plugin.
+    #[must_use]
+    pub fn with_compression(mut self, compression: impl Into<Vec<Compression>>) -> Self {
+        self.compression = compression.into();
+        self
+    }
+
+    /// Sets the queue bounds the plugin's server should use.
+    #[must_use]
+    pub const fn with_queues(mut self, queues: ServerQueues) -> Self {
+        self.queues = queues;
+        self
+    }
+
+    /// Sets the users holding roles.
+    #[must_use]
+    pub fn with_roles(mut self, roles: Roles) -> Self {
+        self.roles = roles;
+        self
+    }
 }
 
 impl<C> Initialize<C>
@@ -34,7 +124,42 @@ where
     }
 }
 
-pub type InitializeResult = Result<(), PluginInitError>;
+/// The plugin's answer to a successful [`Initialize`].
+///
+/// The [`Default`] value describes a plugin predating the handshake.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InitializeAck {
+    /// Protocol revision spoken by the plugin.
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// Optional features implemented by the plugin.
+    #[serde(default)]
+    pub capabilities:     Vec<Capability>,
+    /// Compression the host should apply to the frames it sends, picked from
+    /// [`Initialize::compression`].
+    #[serde(default)]
+    pub compression:      Option<Compression>,
+}
+
+impl InitializeAck {
+    /// Creates an ack for this build's protocol revision and capabilities.
+    #[must_use]
+    pub fn new(compression: Option<Compression>) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capability::supported(),
+            compression,
+        }
+    }
+
+    /// Returns `true` if the plugin implements `capability`.
+    #[must_use]
+    pub fn supports(&self, capability: &Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+pub type InitializeResult = Result<InitializeAck, PluginInitError>;
 
 #[derive(Debug, Error, Deserialize, Serialize)]
 pub enum PluginInitError {
@@ -46,6 +171,20 @@ pub enum PluginInitError {
     JsonSerializationError(String),
     #[error("Failed to deserialize init pack: {0}")]
     InitPackDeserializeError(String),
+    #[error("Host speaks protocol version {host}, plugin supports {min}..={max}")]
+    IncompatibleProtocol { host: u32, min: u32, max: u32 },
+}
+
+impl PluginInitError {
+    /// The error a plugin reports for a host speaking protocol `host`.
+    #[must_use]
+    pub const fn incompatible_protocol(host: u32) -> Self {
+        Self::IncompatibleProtocol {
+            host,
+            min: MIN_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        }
+    }
 }
 
 impl From<serde_json::Error> for PluginInitError {
@@ -120,3 +259,29 @@ pub mod command {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Capability, InitializeAck, PluginInitError, is_compatible};
+
+    #[test]
+    fn legacy_ack_is_incompatible() {
+        let value = serde_json::json!({ "Ok": null });
+        let result: Result<Option<InitializeAck>, PluginInitError> =
+            serde_json::from_value(value).unwrap();
+        let ack = result.unwrap().unwrap_or_default();
+        assert_eq!(ack.protocol_version, 0);
+        assert!(!is_compatible(ack.protocol_version));
+        assert!(!is_compatible(1));
+    }
+
+    #[test]
+    fn unknown_capability() {
+        let value = serde_json::json!(["compression", "telepathy"]);
+        let capabilities: Vec<Capability> = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            capabilities,
+            [Capability::Compression, Capability::Unknown("telepathy".to_owned())]
+        );
+    }
+}
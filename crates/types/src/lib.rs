@@ -10,5 +10,6 @@ pub mod channel;
 pub mod initialize;
 pub mod log;
 pub mod message;
+pub mod role;
 
 pub use smallvec;
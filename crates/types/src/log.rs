@@ -62,8 +62,9 @@ impl From<&log::Record<'_>> for Log {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub enum Value {
+    #[default]
     None,
     Bool(bool),
     Char(char),
@@ -77,7 +78,7 @@ pub enum Value {
 }
 
 impl log::kv::ToValue for Value {
-    fn to_value(&self) -> log::kv::Value {
+    fn to_value(&self) -> log::kv::Value<'_> {
         match self {
             Self::None => log::kv::Value::null(),
             Self::Bool(b) => log::kv::Value::from(*b),
@@ -108,12 +109,6 @@ impl Display for ErrorString {
 
 impl std::error::Error for ErrorString {}
 
-impl Default for Value {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl log::kv::VisitValue<'_> for Value {
     fn visit_any(&mut self, _value: log::kv::Value) -> Result<(), log::kv::Error> {
         Ok(())
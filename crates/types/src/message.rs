@@ -1,14 +1,20 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 use sithra_server::{
     extract::context::{Clientful, Context},
     server::PostError,
+    session::Sessionful,
 };
-use sithra_transport::{Value, ValueError, channel::Channel, datapack::RequestDataPack};
+use sithra_transport::{Bytes, Value, ValueError, channel::Channel, datapack::RequestDataPack};
 use smallvec::SmallVec;
 use typeshare::typeshare;
 
+use crate::role::Role;
+
 pub const NIL: sithra_transport::Value = sithra_transport::Value::Null;
 
 pub type Segments<Seg> = SmallVec<[Seg; 1]>;
@@ -19,6 +25,9 @@ pub struct Message<Seg = Segment> {
     pub id:      String,
     #[typeshare(serialized_as = "Vec<Seg>")]
     pub content: SmallVec<[Seg; 1]>,
+    /// The role of the sender in the channel, if the platform reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role:    Option<Role>,
 }
 
 #[typeshare]
@@ -56,6 +65,27 @@ impl Segment {
         }
     }
 
+    /// A segment of type `ty` whose content is the attachment at `index` of
+    /// the carrying datapack.
+    pub fn attachment<T: Display>(ty: T, index: usize) -> Self {
+        Self {
+            ty:   ty.to_string(),
+            data: AttachmentRef { attachment: index }.into(),
+        }
+    }
+
+    /// An image whose content is the attachment at `index`.
+    #[must_use]
+    pub fn image_attachment(index: usize) -> Self {
+        Self::attachment("image", index)
+    }
+
+    /// Returns the attachment index if this segment references one.
+    #[must_use]
+    pub fn attachment_index(&self) -> Option<usize> {
+        AttachmentRef::from_value(&self.data).map(|r| r.attachment)
+    }
+
     /// # Errors
     pub fn custom<T: Display, V: Serialize>(ty: T, data: V) -> Result<Self, ValueError> {
         Ok(Self {
@@ -71,6 +101,29 @@ impl From<String> for Segment {
     }
 }
 
+/// Segment data pointing at a binary attachment of the carrying datapack
+/// instead of inlining the content, e.g. as `base64://`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AttachmentRef {
+    pub attachment: usize,
+}
+
+impl AttachmentRef {
+    #[must_use]
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let index = value.as_object()?.get("attachment")?.as_u64()?;
+        Some(Self {
+            attachment: usize::try_from(index).ok()?,
+        })
+    }
+}
+
+impl From<AttachmentRef> for Value {
+    fn from(value: AttachmentRef) -> Self {
+        serde_json::json!({ "attachment": value.attachment })
+    }
+}
+
 impl From<&str> for Segment {
     fn from(value: &str) -> Self {
         Self::text(value)
@@ -102,16 +155,65 @@ macro_rules! smsg {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SendMessage<Seg = Segment> {
     #[typeshare(serialized_as = "Vec<Seg>")]
-    pub content: SmallVec<[Seg; 1]>,
+    pub content:     SmallVec<[Seg; 1]>,
+    /// Binary data referenced by [`AttachmentRef`] segments. Sent as
+    /// attachments of the request datapack, not as part of the payload.
+    #[serde(skip)]
+    #[typeshare(skip)]
+    pub attachments: Vec<Bytes>,
+}
+
+impl Default for SendMessage {
+    fn default() -> Self {
+        Self::new::<Segment>(SmallVec::new())
+    }
 }
 
 impl SendMessage {
     #[must_use]
     pub fn new<Seg: Into<Segment>>(content: SmallVec<[Seg; 1]>) -> Self {
         Self {
-            content: content.into_iter().map(Into::into).collect(),
+            content:     content.into_iter().map(Into::into).collect(),
+            attachments: Vec::new(),
         }
     }
+
+    /// Adds an attachment and returns its index for use in
+    /// [`Segment::attachment`].
+    pub fn attach(&mut self, data: impl Into<Bytes>) -> usize {
+        self.attachments.push(data.into());
+        self.attachments.len() - 1
+    }
+
+    /// Appends an image segment carrying `data` as an attachment.
+    #[must_use]
+    pub fn with_image(mut self, data: impl Into<Bytes>) -> Self {
+        let index = self.attach(data);
+        self.content.push(Segment::image_attachment(index));
+        self
+    }
+}
+
+impl From<SendMessage> for RequestDataPack {
+    fn from(value: SendMessage) -> Self {
+        let SendMessage {
+            content,
+            attachments,
+        } = value;
+        let mut datapack =
+            Self::default().path(SendMessage::path()).payload(SendMessage::<Segment> {
+                content,
+                attachments: Vec::new(),
+            });
+        datapack.attachments = attachments;
+        datapack
+    }
+}
+
+impl sithra_server::response::IntoResponse for SendMessage {
+    fn into_response(self) -> sithra_server::response::Response {
+        RequestDataPack::from(self).into_response()
+    }
 }
 
 // impl<Seg: Into<Segment>> From<SmallVec<[Seg; 1]>> for SendMessage {
@@ -125,7 +227,8 @@ impl SendMessage {
 impl<Seg: TryInto<Segment>> From<SmallVec<[Seg; 1]>> for SendMessage {
     fn from(content: SmallVec<[Seg; 1]>) -> Self {
         Self {
-            content: content.into_iter().filter_map(|seg| seg.try_into().ok()).collect(),
+            content:     content.into_iter().filter_map(|seg| seg.try_into().ok()).collect(),
+            attachments: Vec::new(),
         }
     }
 }
@@ -133,7 +236,8 @@ impl<Seg: TryInto<Segment>> From<SmallVec<[Seg; 1]>> for SendMessage {
 impl From<String> for SendMessage {
     fn from(content: String) -> Self {
         Self {
-            content: SmallVec::from([content.into()]),
+            content:     SmallVec::from([content.into()]),
+            attachments: Vec::new(),
         }
     }
 }
@@ -141,7 +245,8 @@ impl From<String> for SendMessage {
 impl From<&str> for SendMessage {
     fn from(content: &str) -> Self {
         Self {
-            content: SmallVec::from([content.into()]),
+            content:     SmallVec::from([content.into()]),
+            attachments: Vec::new(),
         }
     }
 }
@@ -153,7 +258,8 @@ impl<D1: Display, D2: Display> From<Result<D1, D2>> for SendMessage {
             Err(err) => err.to_string(),
         };
         Self {
-            content: SmallVec::from([content.into()]),
+            content:     SmallVec::from([content.into()]),
+            attachments: Vec::new(),
         }
     }
 }
@@ -173,18 +279,52 @@ where
     async fn reply(&self, msg: impl Into<SendMessage>) -> Result<Message, PostError> {
         let datapack = self
             .client()
-            .post(
-                RequestDataPack::default()
-                    .path("/command/message.create")
-                    .channel_opt(self.request.channel())
-                    .payload(msg.into()),
-            )?
+            .post(RequestDataPack::from(msg.into()).channel_opt(self.request.channel()))?
             .await?;
         let msg = datapack.payload::<Message>()?;
         Ok(msg)
     }
 }
 
+pub trait PromptExt {
+    /// Replies with `msg` and waits for the next message from the same user
+    /// in the same channel.
+    ///
+    /// The message goes to this handler instead of the normal ones, as long
+    /// as the router is layered with the [`Sessions`] of the state.
+    ///
+    /// Returns `None` if the reply cannot be sent, the request has no
+    /// channel, or no message arrives within `timeout`, which also bounds
+    /// sending the reply.
+    ///
+    /// [`Sessions`]: sithra_server::session::Sessions
+    fn prompt(
+        &self,
+        msg: impl Into<SendMessage> + Send,
+        timeout: Duration,
+    ) -> impl Future<Output = Option<Message>> + Send;
+}
+
+impl<S, T> PromptExt for Context<T, S>
+where
+    S: Clientful + Sessionful + Send + Sync,
+    T: for<'de> Deserialize<'de> + Send + Sync,
+{
+    async fn prompt(
+        &self,
+        msg: impl Into<SendMessage> + Send,
+        timeout: Duration,
+    ) -> Option<Message> {
+        let channel = self.request.channel()?;
+        let sessions = self.state.sessions();
+        let waiter = sessions.wait(self.request.bot_id(), Message::path(), channel.clone());
+        let started = Instant::now();
+        let reply = RequestDataPack::from(msg.into()).channel(channel);
+        self.client().post_with_timeout(reply, timeout).ok()?.await.ok()?;
+        waiter.recv(timeout.saturating_sub(started.elapsed())).await?.payload().ok()
+    }
+}
+
 pub trait ClientfulExt {
     fn send_message(
         &self,
@@ -204,12 +344,7 @@ where
     ) -> Result<Message, PostError> {
         let datapack = self
             .client()
-            .post(
-                RequestDataPack::default()
-                    .path("/command/message.create")
-                    .channel(channel.into())
-                    .payload(msg.into()),
-            )?
+            .post(RequestDataPack::from(msg.into()).channel(channel.into()))?
             .await?;
         let msg = datapack.payload::<Message>()?;
         Ok(msg)
@@ -227,15 +362,12 @@ pub mod command {
     use sithra_server::{traits::TypedRequest, typed};
 
     use super::SendMessage;
-    use crate::{into_request, into_response, message::Message};
+    use crate::message::Message;
     typed!("/command/message.create" => impl SendMessage);
 
     impl TypedRequest for SendMessage {
         type Response = Message;
     }
-
-    into_response!("/command/message.create", SendMessage);
-    into_request!("/command/message.create", SendMessage);
 }
 
 pub mod common {
@@ -245,12 +377,14 @@ pub mod common {
     use serde::{Deserialize, Serialize, de};
     use sithra_transport::ValueError;
 
-    use crate::message::Segment;
+    use crate::message::{AttachmentRef, Segment};
 
     #[derive(Debug, Clone)]
     pub enum CommonSegment {
         Text(String),
         Image(String),
+        /// An image carried as the attachment with the given index.
+        ImageAttachment(usize),
         At(String),
         Unknown(Segment),
     }
@@ -288,7 +422,10 @@ pub mod common {
             let Segment { ty, data } = value;
             match ty.as_str() {
                 "text" => Ok(Self::Text(sithra_transport::from_value(data)?)),
-                "image" => Ok(Self::Image(sithra_transport::from_value(data)?)),
+                "image" => match AttachmentRef::from_value(&data) {
+                    Some(r) => Ok(Self::ImageAttachment(r.attachment)),
+                    None => Ok(Self::Image(sithra_transport::from_value(data)?)),
+                },
                 "at" => Ok(Self::At(sithra_transport::from_value(data)?)),
                 _ => Ok(Self::Unknown(Segment { ty, data })),
             }
@@ -300,6 +437,7 @@ pub mod common {
             match value {
                 CommonSegment::Text(text) => Self::text(&text),
                 CommonSegment::Image(image) => Self::image(&image),
+                CommonSegment::ImageAttachment(index) => Self::image_attachment(index),
                 CommonSegment::At(target) => Self::at(&target),
                 CommonSegment::Unknown(segment) => segment,
             }
@@ -397,4 +535,20 @@ mod tests {
             Message[on_message, on_message2, on_message3]
         };
     }
+
+    #[test]
+    fn attachments_move_to_datapack() {
+        let msg = SendMessage::default().with_image(vec![1u8, 2, 3]);
+        let datapack = sithra_transport::datapack::DataPack::from(
+            sithra_transport::datapack::RequestDataPack::from(msg),
+        );
+        assert_eq!(datapack.attachments.len(), 1);
+        let payload: SendMessage = datapack.payload().unwrap();
+        assert!(payload.attachments.is_empty());
+        assert_eq!(payload.content[0].attachment_index(), Some(0));
+        assert!(matches!(
+            CommonSegment::try_from(payload.content[0].clone()),
+            Ok(CommonSegment::ImageAttachment(0))
+        ));
+    }
 }
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sithra_transport::channel::Channel;
+use typeshare::typeshare;
+
+/// What a user is allowed to do, from least to most.
+#[typeshare]
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    Member,
+    /// Moderates a channel: configured in [`Roles::moderators`], or an admin
+    /// of the group as reported by the adapter.
+    Moderator,
+    /// Administrates the bot.
+    Admin,
+    /// Owns the bot.
+    Owner,
+}
+
+/// The users holding roles, given to plugins with
+/// [`Initialize`](crate::initialize::Initialize).
+///
+/// Users are the `id` of their channel, channels the `parent_id`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Roles {
+    #[serde(default)]
+    pub owners:     Vec<String>,
+    #[serde(default)]
+    pub admins:     Vec<String>,
+    /// The moderators of each channel.
+    #[serde(default)]
+    pub moderators: HashMap<String, Vec<String>>,
+}
+
+impl Roles {
+    /// The role of the user of `channel`, at least `reported`, the role the
+    /// adapter reported for them. Adapters only speak for their channels, so
+    /// `reported` counts as [`Role::Moderator`] at most.
+    #[must_use]
+    pub fn role_of(&self, channel: &Channel, reported: Option<Role>) -> Role {
+        let user = &channel.id;
+        let configured = if self.owners.contains(user) {
+            Role::Owner
+        } else if self.admins.contains(user) {
+            Role::Admin
+        } else if channel
+            .parent_id
+            .as_ref()
+            .and_then(|parent| self.moderators.get(parent))
+            .is_some_and(|moderators| moderators.contains(user))
+        {
+            Role::Moderator
+        } else {
+            Role::Member
+        };
+        configured.max(reported.unwrap_or_default().min(Role::Moderator))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty() && self.admins.is_empty() && self.moderators.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sithra_transport::channel::Channel;
+
+    use super::{Role, Roles};
+
+    #[test]
+    fn role_of() {
+        let roles: Roles = serde_json::from_value(serde_json::json!({
+            "owners": ["1"],
+            "admins": ["2"],
+            "moderators": { "100": ["3"] },
+        }))
+        .unwrap();
+        let in_group = |user: &str, group: &str| {
+            Channel::DirectFromGroup(group.to_owned(), user.to_owned(), user.to_owned())
+        };
+        assert_eq!(roles.role_of(&in_group("1", "100"), None), Role::Owner);
+        assert_eq!(roles.role_of(&in_group("2", "200"), None), Role::Admin);
+        assert_eq!(roles.role_of(&in_group("3", "100"), None), Role::Moderator);
+        assert_eq!(roles.role_of(&in_group("3", "200"), None), Role::Member);
+        assert_eq!(
+            roles.role_of(&in_group("4", "200"), Some(Role::Moderator)),
+            Role::Moderator
+        );
+        assert_eq!(
+            roles.role_of(&in_group("2", "200"), Some(Role::Member)),
+            Role::Admin
+        );
+        assert_eq!(
+            roles.role_of(&in_group("4", "200"), Some(Role::Owner)),
+            Role::Moderator
+        );
+        assert!(Role::Owner > Role::Admin && Role::Moderator > Role::Member);
+    }
+}
@@ -26,13 +26,18 @@ struct Config {
 }
 
 #[derive(Deserialize)]
-struct ChannelConfig {
-    /// # 机器人ID
-    #[serde(rename = "bot-id")]
-    bot_id: String,
-    /// # 频道类型
-    #[serde(flatten)]
-    kind:   ChannelKind,
+#[serde(untagged)]
+enum ChannelConfig {
+    Table {
+        /// # 机器人ID
+        #[serde(rename = "bot-id")]
+        bot_id: String,
+        /// # 频道类型
+        #[serde(flatten)]
+        kind:   ChannelKind,
+    },
+    /// # 频道地址，如 `group:123@bot`
+    Address(Channel),
 }
 
 #[derive(Deserialize)]
@@ -50,10 +55,20 @@ enum ChannelKind {
     ),
 }
 
-impl From<ChannelConfig> for (Channel, String) {
-    fn from(value: ChannelConfig) -> Self {
-        let ChannelConfig { bot_id, kind } = value;
-        match kind {
+impl TryFrom<ChannelConfig> for (Channel, String) {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ChannelConfig) -> Result<Self, Self::Error> {
+        let (bot_id, kind) = match value {
+            ChannelConfig::Table { bot_id, kind } => (bot_id, kind),
+            ChannelConfig::Address(channel) => {
+                let Some(bot_id) = channel.self_id.clone() else {
+                    anyhow::bail!("channel `{channel}` does not name a bot, append `@<bot-id>`");
+                };
+                return Ok((channel, bot_id));
+            }
+        };
+        Ok(match kind {
             ChannelKind::Group(name) => (
                 Channel {
                     parent_id: Some(name),
@@ -70,7 +85,7 @@ impl From<ChannelConfig> for (Channel, String) {
                 },
                 bot_id,
             ),
-        }
+        })
     }
 }
 
@@ -86,7 +101,11 @@ async fn main() -> anyhow::Result<()> {
     let (plugin, Initialize { config, .. }) = plugin!(Config);
 
     let state = AppState {
-        channels: config.channels.into_iter().map(<(Channel, String)>::from).collect(),
+        channels: config
+            .channels
+            .into_iter()
+            .map(<(Channel, String)>::try_from)
+            .collect::<anyhow::Result<_>>()?,
         client:   plugin.server.client(),
         secret:   config.secret,
     };
@@ -52,7 +52,7 @@ pub async fn webhook(
     let result = mac.finalize();
     let expected_signature = format!("sha256={}", hex::encode(result.into_bytes()));
     if !signature.eq_ignore_ascii_case(&expected_signature) {
-        log::warn!("Invalid signature: expected {expected_signature}, got {signature}",);
+        log::warn!("Invalid signature: expected {expected_signature}, got {signature}");
         return StatusCode::UNAUTHORIZED;
     }
 
@@ -5,6 +5,7 @@ use std::{
 
 use serde::Deserialize;
 use sithra_kit::{
+    permission::{Permission, Permissions},
     plugin,
     server::{
         extract::{
@@ -14,27 +15,35 @@ use sithra_kit::{
         },
         router,
         server::Client,
+        traits::FromRef,
     },
     transport::channel::Channel,
     types::{
         channel::ContextExt as _,
         initialize::Initialize,
         message::{Message, SendMessage, common::CommonSegment as H},
+        role::Role,
         smsg,
     },
 };
-use triomphe::Arc;
 
 #[derive(Debug, Clone, Default, Deserialize)]
 struct Config {
+    /// Admins besides the ones in the roles given by the host.
     #[serde(default)]
     admins: Vec<String>,
 }
 
 #[derive(Clone)]
 struct AppState {
-    admins: Arc<Vec<String>>,
-    client: Client,
+    permissions: Permissions,
+    client:      Client,
+}
+
+impl FromRef<AppState> for Permissions {
+    fn from_ref(input: &AppState) -> Self {
+        input.permissions.clone()
+    }
 }
 
 impl Clientful for AppState {
@@ -45,12 +54,18 @@ impl Clientful for AppState {
 
 #[tokio::main]
 async fn main() {
-    let (plugin, Initialize { config, .. }) = plugin!(Config);
+    let (
+        plugin,
+        Initialize {
+            config, mut roles, ..
+        },
+    ) = plugin!(Config);
 
     let client = plugin.server.client();
+    roles.admins.extend(config.admins);
 
     let state = AppState {
-        admins: Arc::new(config.admins),
+        permissions: Permissions::new(roles),
         client,
     };
 
@@ -97,7 +112,7 @@ async fn channelinfo(
         ty,
         name,
         parent_id,
-        self_id: _,
+        ..
     } = channel;
     let info = format!(
         "频道 ID: {}\n频道类型: {}\n频道名称: {}\n父频道 ID: {}\nBOT ID: {}",
@@ -110,7 +125,7 @@ async fn channelinfo(
     Some(smsg!(info))
 }
 
-async fn mute(ctx: Context<Message<H>, AppState>) -> Option<SendMessage> {
+async fn mute(ctx: Context<Message<H>, AppState>, permission: Permission) -> Option<SendMessage> {
     let args = parse_cmd(&ctx.content);
     let channel = ctx.request.channel()?;
     let (id, duration) = match args {
@@ -126,7 +141,7 @@ async fn mute(ctx: Context<Message<H>, AppState>) -> Option<SendMessage> {
         return Some(smsg!("只能在群聊中使用喵"));
     }
 
-    if !auth(&channel.id, &ctx.state.admins) {
+    if !permission.has(Role::Moderator) {
         return Some(smsg!("你没有权限喵"));
     }
 
@@ -141,10 +156,6 @@ async fn mute(ctx: Context<Message<H>, AppState>) -> Option<SendMessage> {
     ]))
 }
 
-fn auth(user: &String, admins: &[String]) -> bool {
-    admins.contains(user)
-}
-
 fn parse_cmd(segs: &[H]) -> Result<(&str, Duration), ParseErr> {
     match segs {
         [H::Text(cmd), H::At(user_id), H::Text(duration)] if cmd.trim() == "mute" => {
@@ -1,4 +1,3 @@
-use base64::{Engine, prelude::BASE64_STANDARD};
 use bytes::Bytes;
 use resvg::{
     render, tiny_skia,
@@ -183,8 +182,5 @@ pub async fn mcserver(
         return Some(smsg!("卡片渲染失败喵"));
     };
 
-    let base64 = BASE64_STANDARD.encode(img);
-    let img = format!("base64://{base64}");
-
-    Some(smsg!(H[img: img]))
+    Some(SendMessage::default().with_image(img))
 }
@@ -1,4 +1,4 @@
-use base64::{Engine, prelude::BASE64_STANDARD};
+use bytes::Bytes;
 use sithra_kit::{
     server::extract::payload::Payload,
     types::{
@@ -9,12 +9,9 @@ use sithra_kit::{
 
 use crate::util::cmd;
 
-async fn get_image(url: &str) -> Option<String> {
+async fn get_image(url: &str) -> Option<Bytes> {
     match reqwest::get(url).await {
-        Ok(response) => response.bytes().await.ok().map(|bytes| {
-            let base64 = BASE64_STANDARD.encode(bytes);
-            format!("base64://{base64}")
-        }),
+        Ok(response) => response.bytes().await.ok(),
         Err(_) => None,
     }
 }
@@ -23,7 +20,7 @@ async fn handle_mc_command(id: &str, endpoint: &str, error_message: &str) -> Sen
     let id = id.trim();
     let url = format!("https://nmsr.nickac.dev/{endpoint}/{id}");
     if let Some(image) = get_image(&url).await {
-        smsg!(H[img: image])
+        SendMessage::default().with_image(image)
     } else {
         smsg!(error_message)
     }
@@ -1,6 +1,15 @@
 import type { DataPack, RequestDataPack } from ".."
 import { encode as msgpackEncode, decode as msgpackDecode } from "@msgpack/msgpack";
 
+/**
+ * The two most significant bits of a frame's length prefix carry the
+ * compression flag (0 = none, 1 = zstd, 2 = lz4), the rest the payload length.
+ * This codec never advertises compression during `/initialize`, so the host
+ * only ever sends it uncompressed frames; compressed ones are rejected.
+ */
+export const LENGTH_MASK = 0x3fff_ffff;
+export const FLAG_SHIFT = 30;
+
 export function encode(data: unknown): Uint8Array<ArrayBuffer> {
   let dataRaw = msgpackEncode(data);
   const totalLength = 4 + dataRaw.length;
@@ -29,12 +38,19 @@ export function tryDecodeFromRaw(buffer: Buffer): [unknown, Buffer] | null | num
   if (buffer.byteLength < 4) {
     return null;
   }
-  const length = buffer.readUInt32BE(0);
+  const length = buffer.readUInt32BE(0) & LENGTH_MASK;
   return tryDecodeFromRawWithLength(length, buffer) ?? length;
 }
 
 export interface Codec<D, E> {
-  decode(chunk: Buffer): D | null;
+  /** Buffers a chunk read from the peer. */
+  push(chunk: Buffer): void;
+  /**
+   * Takes the next complete frame out of the buffer, `null` once none is
+   * left. Throws for frames this codec cannot read; the frame is skipped, so
+   * calling it again continues with the one after it.
+   */
+  next(): D | null;
   encode(data: E): Buffer;
 }
 
@@ -43,33 +59,35 @@ export type IDataPackCodec = Codec<RequestDataPack<unknown>, DataPack<unknown>>;
 export class DataPackCodec implements Codec<RequestDataPack<unknown>, DataPack<unknown>> {
   deBuffer: Buffer
   enBuffer: Buffer
-  dataLength: number | null
 
   constructor() {
     this.deBuffer = Buffer.from([]);
     this.enBuffer = Buffer.from([]);
-    this.dataLength = null;
   }
 
-  decode(chunk: Buffer): RequestDataPack<unknown> | null {
+  push(chunk: Buffer) {
     this.deBuffer = Buffer.concat([this.deBuffer, chunk]);
-    if (this.deBuffer.byteLength <= 0) {
-      return null;
-    }
-    if (this.dataLength == null) {
-      if (this.deBuffer.byteLength < 4) {
+  }
+
+  next(): RequestDataPack<unknown> | null {
+    while (this.deBuffer.byteLength >= 4) {
+      const header = this.deBuffer.readUInt32BE(0);
+      const length = header & LENGTH_MASK;
+      const flag = header >>> FLAG_SHIFT;
+      if (this.deBuffer.byteLength < 4 + length) {
         return null;
       }
-      this.dataLength = this.deBuffer.readUInt32BE(0);
-      this.deBuffer = this.deBuffer.subarray(4);
-    }
-    let [data, remainingBuffer] = tryDecodeFromRawWithLength(this.dataLength, this.deBuffer) ?? [null, this.deBuffer];
-    this.deBuffer = remainingBuffer;
-    this.dataLength = null;
-    if (!(data as any)["path"]) {
-      return null;
+      const frame = this.deBuffer.subarray(4, 4 + length);
+      this.deBuffer = this.deBuffer.subarray(4 + length);
+      if (flag !== 0) {
+        throw new Error(`Cannot read a frame compressed with flag ${flag}: compression is not negotiated by this codec`);
+      }
+      const data = decodeFromRaw(frame);
+      if ((data as any)?.["path"]) {
+        return data as RequestDataPack<unknown>;
+      }
     }
-    return data as RequestDataPack<unknown>;
+    return null;
   }
 
   encode(data: DataPack<unknown>): Buffer {
@@ -8,7 +8,7 @@ export interface IDataPack<T, R extends "response" | "request"> {
   correlation: string,
   channel?: transport.Channel,
   payload?: R extends "response" ? T | undefined : T,
-  error?: R extends "response" ? string | undefined : never,
+  error?: R extends "response" ? transport.DataError | undefined : never,
 }
 
 export type DataPack<T> = IDataPack<T, "response" | "request">;
@@ -1,7 +1,17 @@
+import { types } from "sithra-types";
 import { DataPack, RequestDataPack } from ".";
 import { IDataPackCodec } from "./codec";
 import { asChunks, initStdio } from "./util";
 
+/** Revision of the host/plugin protocol implemented by this SDK. */
+export const PROTOCOL_VERSION = 2;
+/** Oldest protocol revision this SDK still talks to. */
+export const MIN_PROTOCOL_VERSION = 2;
+
+export function isCompatible(version: number): boolean {
+  return version >= MIN_PROTOCOL_VERSION && version <= PROTOCOL_VERSION;
+}
+
 export class Peer {
   codec: IDataPackCodec
   buffer: Buffer
@@ -14,11 +24,35 @@ export class Peer {
     process.stdin.on("data", (
       data: Buffer
     ) => {
-      let decoded = codec.decode(data)
-      if (decoded) {
-        this.listeners.forEach(listener => listener(decoded));
+      codec.push(data);
+      for (;;) {
+        let decoded: RequestDataPack<unknown> | null;
+        try {
+          decoded = codec.next();
+        } catch (err) {
+          console.error(err);
+          continue;
+        }
+        if (!decoded) {
+          break;
+        }
+        const pack = decoded;
+        this.listeners.forEach(listener => listener(pack));
       }
     });
+    this.route("/initialize", (data) => this.#acknowledge(data));
+  }
+  /**
+   * Answers the host's `/initialize` with the protocol revision of this SDK.
+   * No compression is picked, as the codec cannot read compressed frames.
+   */
+  #acknowledge(init: RequestDataPack<unknown>) {
+    const host = (init.payload as types.Initialize<unknown> | undefined)?.protocol_version ?? 0;
+    const ack: types.InitializeAck = { protocol_version: PROTOCOL_VERSION, capabilities: [] };
+    const payload = isCompatible(host)
+      ? { Ok: ack }
+      : { Err: { IncompatibleProtocol: { host, min: MIN_PROTOCOL_VERSION, max: PROTOCOL_VERSION } } };
+    this.send({ path: "/initialize", correlation: init.correlation, payload });
   }
   onData(callback: (data: RequestDataPack<unknown>) => void) {
     this.listeners.push(callback);
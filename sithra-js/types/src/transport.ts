@@ -11,25 +11,92 @@ export enum ChannelType {
 	Direct = "direct",
 	/** A private channel, used for restricted or hidden conversations. */
 	Private = "private",
+	/** A thread or forum topic inside a group, whose `parent_id` is the group. */
+	Thread = "thread",
+	/** A guild (server) holding groups, e.g. on Discord or KOOK. */
+	Guild = "guild",
 }
 
 /**
  * Represents a communication channel with a unique ID, type, name, and
  * optional parent ID.
  * 
+ * Channels also have a one-line address, see [`Channel::from_str`]. It is
+ * accepted wherever a channel is deserialized, and equality and hashing
+ * compare what the address holds, ignoring `name`.
+ * 
  * # Fields
  * - `id`: Unique identifier for the channel. If the platform cannot provide a
  * user ID, the event ID is used.
- * - `ty`: Type of the channel (`group`, `direct`, or `private`).
+ * - `ty`: Type of the channel (`group`, `direct`, `private`, `thread` or
+ * `guild`).
  * - `name`: Display name of the channel. If the platform cannot provide a
  * nickname, the `id` is used.
  * - `parent_id`: Optional parent channel ID, used for nested channels (e.g.,
- * subgroups).
+ * subgroups, or the group a thread was started in).
+ * - `guild_id`: Optional ID of the guild (server) the channel belongs to.
+ * - `thread_id`: Optional ID of the thread or topic a `direct` channel was
+ * addressed in, so replies stay inside it.
  */
 export interface Channel {
 	id: string;
 	type: ChannelType;
 	name: string;
 	parent_id?: string;
+	self_id?: string;
+	guild_id?: string;
+	thread_id?: string;
+}
+
+/** A compression algorithm applicable to single frames. */
+export enum Compression {
+	Zstd = "zstd",
+	Lz4 = "lz4",
+}
+
+/**
+ * What kind of failure a [`DataError`] reports, so peers can react to it
+ * without parsing the message.
+ */
+export enum ErrorCode {
+	/** The peer failed on its own, e.g. a handler panicked. */
+	Internal = "internal",
+	/** The request is malformed or its arguments are invalid. */
+	InvalidRequest = "invalid_request",
+	/** A payload could not be encoded or decoded. */
+	InvalidPayload = "invalid_payload",
+	/** The peer does not implement the request. */
+	NotSupported = "not_supported",
+	/** The sender of the request is not allowed to make it. */
+	PermissionDenied = "permission_denied",
+	/** Too many requests were made, try again later. */
+	RateLimited = "rate_limited",
+	/** The request was not answered in time. */
+	Timeout = "timeout",
+	/** The peer is overloaded, e.g. its request queue is full. */
+	Busy = "busy",
+	/**
+	 * The peer the request is meant for, such as a bot's platform, is not
+	 * connected.
+	 */
+	PeerOffline = "peer_offline",
+	/**
+	 * A failure without a more specific code, such as the plain text errors
+	 * of older peers, or a code this build does not know.
+	 */
+	Other = "other",
+}
+
+/**
+ * The error result of a datapack.
+ * 
+ * Older peers send a plain message instead, which is read as
+ * [`ErrorCode::Other`].
+ */
+export interface DataError {
+	code: ErrorCode;
+	message: string;
+	/** Anything else the peer reports, such as the status of a platform API. */
+	details?: any;
 }
 
@@ -2,10 +2,52 @@
  Generated by typeshare 1.13.3
 */
 
+import { Compression } from "./transport";
+
+/** An optional protocol feature a peer may implement. */
+export enum Capability {
+	/** Per-frame compression. */
+	Compression = "compression",
+	/** Cancelling in-flight requests. */
+	Cancellation = "cancellation",
+	/** Multiple responses to a single request. */
+	Streaming = "streaming",
+}
+
+/** What the host sends on `/initialize`. */
+export interface Initialize<C> {
+	config: C;
+	id: string;
+	data_path: string;
+	/**
+	 * Protocol revision spoken by the host. `0` for hosts predating the
+	 * handshake.
+	 */
+	protocol_version?: number;
+	/** Optional features implemented by the host. */
+	capabilities?: Capability[];
+	/** Frame compressions the host is able to decode, in order of preference. */
+	compression?: Compression[];
+}
+
+/** The plugin's answer to a successful `Initialize`. */
+export interface InitializeAck {
+	/** Protocol revision spoken by the plugin. */
+	protocol_version: number;
+	/** Optional features implemented by the plugin. */
+	capabilities: Capability[];
+	/**
+	 * Compression the host should apply to the frames it sends, picked from
+	 * `Initialize.compression`.
+	 */
+	compression?: Compression;
+}
 
 export interface Message<Seg> {
 	id: string;
 	content: Seg[];
+	/** The role of the sender in the channel, if the platform reports it. */
+	role?: Role;
 }
 
 export interface Segment {
@@ -17,3 +59,17 @@ export interface SendMessage {
 	content: Segment[];
 }
 
+/** What a user is allowed to do, from least to most. */
+export enum Role {
+	Member = "member",
+	/**
+	 * Moderates a channel: configured in [`Roles::moderators`], or an admin
+	 * of the group as reported by the adapter.
+	 */
+	Moderator = "moderator",
+	/** Administrates the bot. */
+	Admin = "admin",
+	/** Owns the bot. */
+	Owner = "owner",
+}
+